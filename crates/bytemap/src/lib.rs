@@ -1,7 +1,18 @@
+//! An ordered map specialized for `u8` keys.
+//!
+//! `ByteMap` is an adaptive node in the style of the [Adaptive Radix
+//! Trie](https://db.in.tum.de/~leis/papers/ART.pdf): it starts as a
+//! 4-entry sorted array and grows into 16-entry, 48-entry (indirect)
+//! and 256-entry (direct) representations as more keys are inserted.
 use std::fmt::Debug;
 use std::iter::FromIterator;
 
+mod set;
+
+pub use set::ByteSet;
+
 // TODO(alan): SIMD-ify
+/// An ordered map from `u8` to `T`
 #[derive(Debug, Eq, PartialEq)]
 pub struct ByteMap<T> {
     len: u16,
@@ -40,7 +51,7 @@ impl<T> Node<T> {
             Node::N16(ref mut n) => {
                 let mut new = Box::new(Node48 {
                     positions: [u8::max_value(); 256],
-                    values: unsafe { std::mem::zeroed() },
+                    values: std::array::from_fn(|_| None),
                 });
 
                 for i in 0..16 {
@@ -51,7 +62,7 @@ impl<T> Node<T> {
             }
             Node::N48(ref mut n) => {
                 let mut new = Box::new(Node256 {
-                    values: unsafe { std::mem::zeroed() },
+                    values: std::array::from_fn(|_| None),
                 });
 
                 for i in 0..=255 {
@@ -136,6 +147,7 @@ impl<T: PartialEq> PartialEq for Node256<T> {
     }
 }
 
+/// A view into a single entry of a `ByteMap`
 pub enum Entry<'a, T> {
     Occupied(OccupiedEntry<'a, T>),
     Vacant(VacantEntry<'a, T>),
@@ -148,6 +160,7 @@ pub struct OccupiedEntry<'a, T> {
 }
 
 impl<'a, T> OccupiedEntry<'a, T> {
+    /// Return a mutable reference to the entry's value
     pub fn get_mut(&mut self) -> &mut T {
         match self.map.node {
             Node::N4(ref mut n) => n.values[self.rank].as_mut().unwrap(),
@@ -162,30 +175,36 @@ impl<'a, T> OccupiedEntry<'a, T> {
         }
     }
 
-    pub fn remove(&mut self) {
+    /// Remove the entry from the map, returning its value
+    pub fn remove(&mut self) -> T {
         let len = self.map.len;
-        match self.map.node {
+        let value = match self.map.node {
             Node::N4(ref mut n) => {
+                let value = n.values[self.rank].take();
                 for i in self.rank..(n.bytes.len() - 1) {
                     n.bytes[i] = n.bytes[i + 1];
                     n.values.swap(i, i + 1);
                 }
                 n.bytes[n.bytes.len() - 1] = 0;
                 n.values[n.bytes.len() - 1] = None;
+                value
             }
             Node::N16(ref mut n) => {
+                let value = n.values[self.rank].take();
                 for i in self.rank..(n.bytes.len() - 1) {
                     n.bytes[i] = n.bytes[i + 1];
                     n.values.swap(i, i + 1);
                 }
                 n.bytes[n.bytes.len() - 1] = 0;
                 n.values[n.bytes.len() - 1] = None;
+                value
             }
             Node::N48(ref mut n) => {
                 let pos = std::mem::replace(
                     &mut n.positions[self.key as usize],
                     0xFF,
                 );
+                let value = n.values[pos as usize].take();
                 if pos != (len - 1) as u8 {
                     let last = n
                         .positions
@@ -196,12 +215,12 @@ impl<'a, T> OccupiedEntry<'a, T> {
                     n.positions[last] = pos;
                 }
                 n.values[(len - 1) as usize] = None;
+                value
             }
-            Node::N256(ref mut n) => {
-                n.values[self.key as usize] = None;
-            }
-        }
+            Node::N256(ref mut n) => n.values[self.key as usize].take(),
+        };
         self.map.len -= 1;
+        value.unwrap()
     }
 }
 
@@ -212,6 +231,7 @@ pub struct VacantEntry<'a, T> {
 }
 
 impl<'a, T> VacantEntry<'a, T> {
+    /// Insert a value into the (empty) entry
     pub fn insert(&mut self, value: T) {
         // Resize if necessary
         match (&self.map.node, self.map.len) {
@@ -251,6 +271,7 @@ impl<'a, T> VacantEntry<'a, T> {
 }
 
 impl<T> ByteMap<T> {
+    /// Create an empty map
     pub fn new() -> ByteMap<T> {
         ByteMap {
             node: Node::N4(Box::new(Node4 {
@@ -265,10 +286,83 @@ impl<T> ByteMap<T> {
         self.len == 0
     }
 
+    /// Return the number of keys in the map
     pub fn len(&self) -> usize {
         self.len as usize
     }
 
+    /// Return the name of the current representation ("N4", "N16", "N48" or
+    /// "N256")
+    pub fn variant_name(&self) -> &'static str {
+        match self.node {
+            Node::N4(_) => "N4",
+            Node::N16(_) => "N16",
+            Node::N48(_) => "N48",
+            Node::N256(_) => "N256",
+        }
+    }
+
+    /// Return the number of keys the current representation holds before it
+    /// has to grow
+    pub fn capacity(&self) -> usize {
+        match self.node {
+            Node::N4(_) => 4,
+            Node::N16(_) => 16,
+            Node::N48(_) => 48,
+            Node::N256(_) => 256,
+        }
+    }
+
+    /// Clear the map, removing all keys and values
+    pub fn clear(&mut self) {
+        *self = ByteMap::new();
+    }
+
+    /// Iterate over the (key, value) pairs in ascending key order
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &T)> + '_ {
+        let mut next = Some(0u8);
+        std::iter::from_fn(move || {
+            let (byte, value) = self.successor(next?)?;
+            next = byte.checked_add(1);
+            Some((byte, value))
+        })
+    }
+
+    /// Call `f` on each (key, value) pair in ascending key order
+    ///
+    /// This scans the node directly, so it is cheaper than `iter`, which
+    /// does a `successor` search for every key.
+    pub fn for_each_sorted<F: FnMut(u8, &T)>(&self, mut f: F) {
+        let len = self.len as usize;
+        match self.node {
+            Node::N4(ref n) => {
+                for (byte, value) in n.bytes[..len].iter().zip(&n.values) {
+                    f(*byte, value.as_ref().unwrap());
+                }
+            }
+            Node::N16(ref n) => {
+                for (byte, value) in n.bytes[..len].iter().zip(&n.values) {
+                    f(*byte, value.as_ref().unwrap());
+                }
+            }
+            Node::N48(ref n) => {
+                for (byte, &pos) in n.positions.iter().enumerate() {
+                    if pos < 48 {
+                        f(byte as u8, n.values[pos as usize].as_ref().unwrap());
+                    }
+                }
+            }
+            Node::N256(ref n) => {
+                for (byte, value) in n.values.iter().enumerate() {
+                    if let Some(value) = value {
+                        f(byte as u8, value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return the largest key <= byte (and its value)
     pub fn predecessor(&self, byte: u8) -> Option<(u8, &T)> {
         if self.is_empty() {
             return None;
@@ -308,6 +402,7 @@ impl<T> ByteMap<T> {
         None
     }
 
+    /// Return the largest key <= byte (and its value)
     pub fn predecessor_mut(&mut self, byte: u8) -> Option<(u8, &mut T)> {
         if self.is_empty() {
             return None;
@@ -347,20 +442,22 @@ impl<T> ByteMap<T> {
         None
     }
 
+    /// Return the smallest key >= byte (and its value)
     pub fn successor(&self, byte: u8) -> Option<(u8, &T)> {
         if self.is_empty() {
             return None;
         }
+        let len = self.len as usize;
         match self.node {
             Node::N4(ref n) => {
-                for (i, b) in n.bytes.iter().cloned().enumerate() {
+                for (i, b) in n.bytes[..len].iter().cloned().enumerate() {
                     if b >= byte {
                         return n.values[i].as_ref().map(|r| (b, r));
                     }
                 }
             }
             Node::N16(ref n) => {
-                for (i, b) in n.bytes.iter().cloned().enumerate() {
+                for (i, b) in n.bytes[..len].iter().cloned().enumerate() {
                     if b >= byte {
                         return n.values[i].as_ref().map(|r| (b, r));
                     }
@@ -385,20 +482,22 @@ impl<T> ByteMap<T> {
         None
     }
 
+    /// Return the smallest key >= byte (and its value)
     pub fn successor_mut(&mut self, byte: u8) -> Option<(u8, &mut T)> {
         if self.is_empty() {
             return None;
         }
+        let len = self.len as usize;
         match self.node {
             Node::N4(ref mut n) => {
-                for (i, b) in n.bytes.iter().cloned().enumerate() {
+                for (i, b) in n.bytes[..len].iter().cloned().enumerate() {
                     if b >= byte {
                         return n.values[i].as_mut().map(|r| (b, r));
                     }
                 }
             }
             Node::N16(ref mut n) => {
-                for (i, b) in n.bytes.iter().cloned().enumerate() {
+                for (i, b) in n.bytes[..len].iter().cloned().enumerate() {
                     if b >= byte {
                         return n.values[i].as_mut().map(|r| (b, r));
                     }
@@ -423,6 +522,7 @@ impl<T> ByteMap<T> {
         None
     }
 
+    /// Insert a key-value pair, returning the old value if present
     pub fn insert(&mut self, key: u8, value: T) -> Option<T> {
         match self.entry(key) {
             Entry::Vacant(mut v) => {
@@ -435,6 +535,15 @@ impl<T> ByteMap<T> {
         }
     }
 
+    /// Remove a key from the map, returning its value if present
+    pub fn remove(&mut self, key: u8) -> Option<T> {
+        match self.entry(key) {
+            Entry::Vacant(_) => None,
+            Entry::Occupied(mut o) => Some(o.remove()),
+        }
+    }
+
+    /// Return a reference to the value corresponding to the key
     pub fn get(&self, key: u8) -> Option<&T> {
         let len = self.len as usize;
         match self.node {
@@ -455,12 +564,18 @@ impl<T> ByteMap<T> {
                 None
             }
             Node::N48(ref n) => {
-                n.values[n.positions[key as usize] as usize].as_ref()
+                let pos = n.positions[key as usize] as usize;
+                if pos < 48 {
+                    n.values[pos].as_ref()
+                } else {
+                    None
+                }
             }
             Node::N256(ref n) => n.values[key as usize].as_ref(),
         }
     }
 
+    /// Return a mutable reference to the value corresponding to the key
     pub fn get_mut(&mut self, key: u8) -> Option<&mut T> {
         let len = self.len as usize;
         match self.node {
@@ -481,12 +596,18 @@ impl<T> ByteMap<T> {
                 None
             }
             Node::N48(ref mut n) => {
-                n.values[n.positions[key as usize] as usize].as_mut()
+                let pos = n.positions[key as usize] as usize;
+                if pos < 48 {
+                    n.values[pos].as_mut()
+                } else {
+                    None
+                }
             }
             Node::N256(ref mut n) => n.values[key as usize].as_mut(),
         }
     }
 
+    /// Get the entry for `key` for in-place manipulation
     pub fn entry(&mut self, key: u8) -> Entry<'_, T> {
         match self.node {
             Node::N4(ref n) => {
@@ -616,4 +737,208 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_bytemap_partial_node16() {
+        // 5 keys forces an upsize into a Node16 with 11 unused slots
+        let mut map = ByteMap::from_iter(
+            [200, 150, 100, 50, 25].iter().map(|k| (*k, *k)),
+        );
+        assert!(matches!(map.node, Node::N16(_)));
+
+        // Removing keys leaves trailing stale slots in the Node16
+        assert_eq!(map.remove(200), Some(200));
+        assert_eq!(map.remove(25), Some(25));
+        assert!(matches!(map.node, Node::N16(_)));
+
+        let expected: BTreeSet<u8> = [50, 100, 150].iter().cloned().collect();
+        for i in 0..=255 {
+            assert_eq!(
+                map.successor(i),
+                expected.range(i..).next().map(|k| (*k, k))
+            );
+            assert_eq!(
+                map.successor_mut(i).map(|(k, v)| (k, *v)),
+                expected.range(i..).next().map(|k| (*k, *k))
+            );
+        }
+        assert_eq!(map.successor(151), None);
+        assert_eq!(map.get(200), None);
+    }
+
+    #[test]
+    fn test_bytemap_successor_ignores_removed_slots() {
+        let mut map = ByteMap::new();
+        map.insert(0x05, 'a');
+        map.insert(0xF0, 'b');
+        assert_eq!(map.remove(0xF0), Some('b'));
+        assert_eq!(map.remove(0xF0), None);
+
+        assert_eq!(map.successor(0), Some((0x05, &'a')));
+        assert_eq!(map.successor(0x06), None);
+
+        // Same thing, but with stale slots in a Node16
+        for i in 0..10 {
+            map.insert(0xF0 + i, 'c');
+        }
+        for i in 0..10 {
+            assert_eq!(map.remove(0xF0 + i), Some('c'));
+        }
+        assert!(matches!(map.node, Node::N16(_)));
+        assert_eq!(map.successor(0), Some((0x05, &'a')));
+        assert_eq!(map.successor_mut(0), Some((0x05, &mut 'a')));
+        assert_eq!(map.successor(0x06), None);
+    }
+
+    #[test]
+    fn test_bytemap_remove() {
+        let mut map = ByteMap::new();
+        let mut expected = BTreeSet::new();
+        for i in 0..=255u8 {
+            map.insert(i.wrapping_mul(31), i);
+            expected.insert(i.wrapping_mul(31));
+        }
+        for i in 0..=255u8 {
+            let key = i.wrapping_mul(31);
+            assert_eq!(map.remove(key), Some(i));
+            expected.remove(&key);
+            assert_eq!(map.len(), expected.len());
+            assert_eq!(
+                map.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+                expected.iter().cloned().collect::<Vec<_>>()
+            );
+        }
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_bytemap_iter_clear() {
+        for n in [0usize, 3, 10, 40, 200].iter().cloned() {
+            let keys = (0..n).map(|i| (i * 97 % 256) as u8).collect::<Vec<_>>();
+            let mut map = ByteMap::from_iter(keys.iter().map(|k| (*k, *k)));
+
+            let mut expected = keys.clone();
+            expected.sort();
+            assert_eq!(
+                map.iter().map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+                expected.iter().map(|k| (*k, *k)).collect::<Vec<_>>()
+            );
+
+            // Keys which were never inserted should be absent
+            for i in 0..=255 {
+                assert_eq!(map.get(i).is_some(), expected.contains(&i));
+                assert_eq!(map.get_mut(i).is_some(), expected.contains(&i));
+            }
+
+            map.clear();
+            assert!(map.is_empty());
+            assert_eq!(map.len(), 0);
+            assert_eq!(map.iter().next(), None);
+        }
+    }
+
+    #[test]
+    fn test_bytemap_for_each_sorted() {
+        // N4, N16, a partially filled N48 and N256
+        for &n in &[0usize, 3, 10, 30, 200] {
+            let keys = (0..n).map(|i| (i * 97 % 256) as u8).collect::<Vec<_>>();
+            let mut map = ByteMap::new();
+            for &key in keys.iter() {
+                map.insert(key, u32::from(key) * 3);
+            }
+            // Leave holes in the middle of the node
+            for &key in keys.iter().step_by(4) {
+                map.remove(key);
+            }
+
+            let mut expected = keys
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| i % 4 != 0)
+                .map(|(_, &key)| (key, u32::from(key) * 3))
+                .collect::<Vec<_>>();
+            expected.sort();
+
+            let mut visited = Vec::new();
+            map.for_each_sorted(|key, &value| visited.push((key, value)));
+            assert_eq!(visited, expected);
+            assert_eq!(
+                map.iter().map(|(k, &v)| (k, v)).collect::<Vec<_>>(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_bytemap_variant_transitions() {
+        let mut map = ByteMap::new();
+        assert_eq!((map.variant_name(), map.capacity()), ("N4", 4));
+        for len in 1..=256usize {
+            let key = (len as u8).wrapping_mul(157);
+            map.insert(key, ());
+            let expected = match len {
+                0..=4 => ("N4", 4),
+                5..=16 => ("N16", 16),
+                17..=48 => ("N48", 48),
+                _ => ("N256", 256),
+            };
+            assert_eq!((map.variant_name(), map.capacity()), expected);
+            assert!(map.len() <= map.capacity());
+        }
+
+        // Removing keys never shrinks the representation
+        for key in 0..=255 {
+            map.remove(key);
+        }
+        assert_eq!(map.variant_name(), "N256");
+        map.clear();
+        assert_eq!(map.variant_name(), "N4");
+    }
+
+    #[test]
+    fn test_bytemap_upsize_niche_values() {
+        // `None` is not all-zeros for `Option<bool>`, so the larger nodes
+        // must not be zero-initialized
+        let mut map = ByteMap::new();
+        for key in 0..=40 {
+            map.insert(key * 3, key % 2 == 0);
+        }
+        assert!(matches!(map.node, Node::N48(_)));
+        for key in 0..=255 {
+            assert_eq!(map.get(key).is_some(), key % 3 == 0 && key <= 120);
+        }
+
+        for key in 41..=70 {
+            map.insert(key * 3, key % 2 == 0);
+        }
+        assert!(matches!(map.node, Node::N256(_)));
+        for key in 0..=255 {
+            assert_eq!(map.get(key).is_some(), key % 3 == 0 && key <= 210);
+        }
+    }
+
+    #[test]
+    fn test_bytemap_upsize_f32_values() {
+        let mut map = ByteMap::new();
+        for key in 0..20u8 {
+            map.insert(key * 2, f32::from(key));
+        }
+        assert!(matches!(map.node, Node::N48(_)));
+        for key in 0..=255u8 {
+            let expected = if key % 2 == 0 && key < 40 {
+                Some(f32::from(key / 2))
+            } else {
+                None
+            };
+            assert_eq!(map.get(key).cloned(), expected);
+        }
+
+        for key in 20..60u8 {
+            map.insert(key * 2, f32::from(key));
+        }
+        assert!(matches!(map.node, Node::N256(_)));
+        assert_eq!(map.get(1), None);
+        assert_eq!(map.get(255), None);
+        assert_eq!(map.iter().count(), 60);
+    }
 }
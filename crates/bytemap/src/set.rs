@@ -0,0 +1,135 @@
+use std::iter::FromIterator;
+
+/// An ordered set of `u8`, stored as a 256-bit presence bitmap
+///
+/// This is the set counterpart of `ByteMap<()>`, without a slot per key.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ByteSet {
+    bits: [u64; 4],
+}
+
+impl ByteSet {
+    pub fn new() -> ByteSet {
+        ByteSet { bits: [0; 4] }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == [0; 4]
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Remove all keys from the set
+    pub fn clear(&mut self) {
+        self.bits = [0; 4];
+    }
+
+    pub fn contains(&self, key: u8) -> bool {
+        self.bits[key as usize / 64] & (1 << (key % 64)) != 0
+    }
+
+    /// Insert a key, returning whether it was newly added
+    pub fn insert(&mut self, key: u8) -> bool {
+        let present = self.contains(key);
+        self.bits[key as usize / 64] |= 1 << (key % 64);
+        !present
+    }
+
+    /// Remove a key, returning whether it was present
+    pub fn remove(&mut self, key: u8) -> bool {
+        let present = self.contains(key);
+        self.bits[key as usize / 64] &= !(1 << (key % 64));
+        present
+    }
+
+    /// Return the largest key <= byte
+    pub fn predecessor(&self, byte: u8) -> Option<u8> {
+        let upper = byte as usize / 64;
+        let below = u64::MAX >> (63 - byte % 64);
+        let masked = self.bits[upper] & below;
+        if masked != 0 {
+            return Some(
+                (64 * upper + 63 - masked.leading_zeros() as usize) as u8,
+            );
+        }
+        (0..upper).rev().find(|&i| self.bits[i] != 0).map(|i| {
+            (64 * i + 63 - self.bits[i].leading_zeros() as usize) as u8
+        })
+    }
+
+    /// Return the smallest key >= byte
+    pub fn successor(&self, byte: u8) -> Option<u8> {
+        let upper = byte as usize / 64;
+        let masked = self.bits[upper] & (u64::MAX << (byte % 64));
+        if masked != 0 {
+            return Some((64 * upper + masked.trailing_zeros() as usize) as u8);
+        }
+        ((upper + 1)..4)
+            .find(|&i| self.bits[i] != 0)
+            .map(|i| (64 * i + self.bits[i].trailing_zeros() as usize) as u8)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        let mut next = Some(0u8);
+        std::iter::from_fn(move || {
+            let byte = self.successor(next?)?;
+            next = byte.checked_add(1);
+            Some(byte)
+        })
+    }
+}
+
+impl FromIterator<u8> for ByteSet {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> ByteSet {
+        let mut set = ByteSet::new();
+        for key in iter {
+            set.insert(key);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_byteset() {
+        let keys: Vec<u8> = vec![38, 0, 1, 39, 63, 64, 127, 128, 255, 200, 0];
+
+        let mut set = ByteSet::new();
+        let mut expected = BTreeSet::new();
+        for key in keys.iter().cloned() {
+            assert_eq!(set.insert(key), expected.insert(key));
+            assert_eq!(set.len(), expected.len());
+
+            for i in 0..=255 {
+                assert_eq!(set.contains(i), expected.contains(&i));
+                assert_eq!(
+                    set.successor(i),
+                    expected.range(i..).next().cloned()
+                );
+                assert_eq!(
+                    set.predecessor(i),
+                    expected.range(0..=i).next_back().cloned()
+                );
+            }
+        }
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            expected.iter().cloned().collect::<Vec<_>>()
+        );
+
+        assert!(set.remove(64));
+        assert!(!set.remove(64));
+        assert_eq!(set.successor(64), Some(127));
+        assert_eq!(set.predecessor(126), Some(63));
+
+        set.clear();
+        assert!(set.is_empty());
+        assert_eq!(set.iter().next(), None);
+    }
+}
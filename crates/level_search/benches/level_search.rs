@@ -6,7 +6,10 @@ use criterion::{
 };
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 
-use psearch::{xfast::XFastSet, yfast::YFastSet};
+use level_search::{
+    xfast::XFastSet,
+    yfast::{YFastMap, YFastSet},
+};
 
 fn criterion_benchmark(c: &mut Criterion) {
     macro_rules! sorted_insert {
@@ -301,6 +304,48 @@ fn criterion_benchmark(c: &mut Criterion) {
             }),
         ),
     );
+
+    macro_rules! count_keys {
+        ($count:expr) => {{
+            |b, &n| {
+                let mut rng = SmallRng::from_seed([5; 16]);
+                let keys = (0..n)
+                    .map(|_| rng.gen_range(0, n as u64 / 4))
+                    .collect::<Vec<_>>();
+
+                b.iter(|| {
+                    let mut map = YFastMap::new();
+                    for key in keys.iter() {
+                        $count(&mut map, *key);
+                    }
+                    black_box(map);
+                });
+            }
+        }};
+    }
+    c.bench(
+        "count_u64",
+        ParameterizedBenchmark::new(
+            "get_insert",
+            count_keys!(|map: &mut YFastMap<u64, u64>, key| {
+                let count = map.get(key).cloned().unwrap_or(0);
+                map.insert(key, count + 1);
+            }),
+            vec![1000, 10000, 100000],
+        )
+        .with_function(
+            "descend",
+            count_keys!(|map: &mut YFastMap<u64, u64>, key| {
+                match map.descend(key) {
+                    (Some(count), _) => *count += 1,
+                    (None, token) => {
+                        map.insert_with_token(token, 1);
+                    }
+                }
+            }),
+        )
+        .sample_size(10),
+    );
 }
 
 criterion_group!(benches, criterion_benchmark);
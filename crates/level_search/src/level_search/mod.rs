@@ -1,10 +1,93 @@
+/// Implement `LevelSearchable` for `$key`, a key that is searched by the
+/// big-endian bytes of an `$int` through the `$bytes` trait, using the
+/// `$lss` level search for keys of that width
+macro_rules! impl_level_searchable {
+    (
+        $key: ty,
+        $int: ty,
+        $bytes: ident,
+        $lss: ident,
+        $min: expr,
+        $max: expr
+    ) => {
+        impl<T> LevelSearchable<T> for $key {
+            type LSS = $lss<$key, T>;
+            const MIN: $key = $min;
+            const MAX: $key = $max;
+            const LEN: usize = <$int>::BITS as usize;
+
+            fn common_prefix_bits(self, other: Self) -> u32 {
+                let a = <$int>::from_be_bytes($bytes::to_bytes(self));
+                let b = <$int>::from_be_bytes($bytes::to_bytes(other));
+                (a ^ b).leading_zeros()
+            }
+
+            fn distance(self, other: Self) -> u64 {
+                let a = <$int>::from_be_bytes($bytes::to_bytes(self));
+                let b = <$int>::from_be_bytes($bytes::to_bytes(other));
+                u64::from(a.abs_diff(b))
+            }
+
+            fn checked_increment(self) -> Option<Self> {
+                let a = <$int>::from_be_bytes($bytes::to_bytes(self));
+                a.checked_add(1)
+                    .map(|a| <Self as $bytes>::from_bytes(a.to_be_bytes()))
+            }
+
+            fn checked_decrement(self) -> Option<Self> {
+                let a = <$int>::from_be_bytes($bytes::to_bytes(self));
+                a.checked_sub(1)
+                    .map(|a| <Self as $bytes>::from_bytes(a.to_be_bytes()))
+            }
+
+            fn lss_new() -> $lss<$key, T> {
+                $lss::new()
+            }
+
+            fn lss_clear(lss: &mut $lss<$key, T>) {
+                lss.clear();
+            }
+
+            fn lss_insert(lss: &mut $lss<$key, T>, node: &mut LNode<$key, T>) {
+                lss.insert(node);
+            }
+
+            fn lss_remove(lss: &mut $lss<$key, T>, node: &LNode<$key, T>) {
+                lss.remove(node);
+            }
+
+            fn lss_level_sizes(lss: &$lss<$key, T>) -> Vec<usize> {
+                lss.level_sizes()
+            }
+
+            fn lss_longest_descendant(
+                lss: &$lss<$key, T>,
+                key: Self,
+            ) -> (u8, &Descendant<$key, T>) {
+                lss.longest_descendant(key)
+            }
+
+            fn lss_longest_descendant_mut(
+                lss: &mut $lss<$key, T>,
+                key: Self,
+            ) -> (u8, &mut Descendant<$key, T>) {
+                lss.longest_descendant_mut(key)
+            }
+        }
+    };
+}
+
+mod u16;
 mod u32;
 mod u64;
+mod uint;
 
 use std::ptr;
 
 use bytemap::{ByteMap, Entry};
 
+pub use self::uint::UInt;
+
 pub trait LevelSearchable<T>:
     std::fmt::Debug
     + std::hash::Hash
@@ -21,12 +104,25 @@ pub trait LevelSearchable<T>:
     const MAX: Self;
     const LEN: usize;
 
+    /// The number of leading bits shared by two keys
+    fn common_prefix_bits(self, other: Self) -> u32;
+
+    /// The absolute difference between two keys, read as integers
+    fn distance(self, other: Self) -> u64;
+
+    /// The next key, or `None` for `MAX`
+    fn checked_increment(self) -> Option<Self>;
+
+    /// The previous key, or `None` for `MIN`
+    fn checked_decrement(self) -> Option<Self>;
+
     // No GATs, so can't implement LSS with a trait bound. Instead, add
     // LSS methods to this trait.
     fn lss_new() -> Self::LSS;
     fn lss_clear(lss: &mut Self::LSS);
     fn lss_insert(lss: &mut Self::LSS, node: &mut LNode<Self, T>);
     fn lss_remove(lss: &mut Self::LSS, node: &LNode<Self, T>);
+    fn lss_level_sizes(lss: &Self::LSS) -> Vec<usize>;
     fn lss_longest_descendant(
         lss: &Self::LSS,
         key: Self,
@@ -42,6 +138,10 @@ pub trait LevelSearchable<T>:
         desc.successor(0)
     }
 
+    fn lss_max(lss: &Self::LSS) -> Option<&LNode<Self, T>> {
+        Self::lss_predecessor(lss, Self::MAX)
+    }
+
     fn lss_predecessor(lss: &Self::LSS, key: Self) -> Option<&LNode<Self, T>> {
         let (byte, desc) = Self::lss_longest_descendant(lss, key);
         desc.predecessor(byte).or_else(|| {
@@ -59,6 +159,34 @@ pub trait LevelSearchable<T>:
     }
 }
 
+/// Return the maximal inclusive ranges of keys missing from `keys`, which
+/// must be sorted and unique
+pub(crate) fn gaps<K, T>(
+    keys: impl Iterator<Item = K>,
+) -> impl Iterator<Item = (K, K)>
+where
+    K: LevelSearchable<T>,
+{
+    let mut keys = keys.fuse();
+    // The smallest key that may be missing, or `None` once we are past `MAX`
+    let mut start = Some(K::MIN);
+    std::iter::from_fn(move || loop {
+        let gap_start = start?;
+        match keys.next() {
+            Some(key) => {
+                start = key.checked_increment();
+                if gap_start < key {
+                    return Some((gap_start, key.checked_decrement().unwrap()));
+                }
+            }
+            None => {
+                start = None;
+                return Some((gap_start, K::MAX));
+            }
+        }
+    })
+}
+
 type Ptr<K, V> = ptr::NonNull<LNode<K, V>>;
 
 #[derive(Debug, Default, Eq, PartialEq)]
@@ -161,7 +289,11 @@ impl<K: LevelSearchable<V>, V> Descendant<K, V> {
             }
             Entry::Occupied(mut o) => {
                 let max = o.get_mut();
-                let min = self.min.as_ref().unwrap().1;
+                let min = self
+                    .min
+                    .as_ref()
+                    .expect("a non-empty Descendant has a min")
+                    .1;
 
                 if node.key < unsafe { min.as_ref() }.key {
                     self.min = Some((byte, ptr::NonNull::from(node)));
@@ -178,10 +310,22 @@ impl<K: LevelSearchable<V>, V> Descendant<K, V> {
 
     /// Remove the byte/node pair from the descendant pointers
     fn remove(&mut self, byte: u8, node: &LNode<K, V>) {
+        // If the previous node is the max of a smaller byte, then node is
+        // the only one under this byte
+        let alone = byte > 0
+            && matches!(
+                self.maxes.predecessor(byte - 1),
+                Some((_, prev)) if ptr::eq(prev.as_ptr(), node.prev)
+            );
+
         match self.maxes.entry(byte) {
             Entry::Occupied(mut o) => {
                 let max = o.get_mut();
-                let min = &mut self.min.as_mut().unwrap().1;
+                let min = &mut self
+                    .min
+                    .as_mut()
+                    .expect("a non-empty Descendant has a min")
+                    .1;
 
                 if ptr::eq(min.as_ptr(), node) {
                     if ptr::eq(max.as_ptr(), node) {
@@ -198,10 +342,19 @@ impl<K: LevelSearchable<V>, V> Descendant<K, V> {
                             unsafe { ptr::NonNull::new_unchecked(node.next) };
                     }
                 } else if ptr::eq(max.as_ptr(), node) {
-                    *max = unsafe { ptr::NonNull::new_unchecked(node.prev) };
+                    if alone {
+                        o.remove();
+                    } else {
+                        *max =
+                            unsafe { ptr::NonNull::new_unchecked(node.prev) };
+                    }
                 }
             }
-            _ => unreachable!(),
+            Entry::Vacant(_) => {
+                // `node` was never merged under `byte`, so there is nothing
+                // to unlink
+                debug_assert!(false, "removing an unknown node from a level")
+            }
         }
     }
 }
@@ -245,3 +398,46 @@ impl<V, K: LevelSearchable<V>> LNode<K, V> {
         }
     }
 }
+
+/// A shared borrow of a node, for walking a map's linked list without
+/// `unsafe` at each step
+///
+/// Every node is owned by its map, and its `prev` and `next` pointers are
+/// either null or point at other nodes of the same map. Borrowing one node
+/// for `'a` borrows the map, so its neighbours also live (unchanged) for
+/// `'a`.
+pub(crate) struct LNodeRef<'a, K: LevelSearchable<V>, V> {
+    node: &'a LNode<K, V>,
+}
+
+impl<'a, K: LevelSearchable<V>, V> Clone for LNodeRef<'a, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, K: LevelSearchable<V>, V> Copy for LNodeRef<'a, K, V> {}
+
+impl<'a, K: LevelSearchable<V>, V> LNodeRef<'a, K, V> {
+    pub(crate) fn new(node: &'a LNode<K, V>) -> LNodeRef<'a, K, V> {
+        LNodeRef { node }
+    }
+
+    pub(crate) fn key(self) -> K {
+        self.node.key
+    }
+
+    pub(crate) fn value(self) -> &'a V {
+        &self.node.value
+    }
+
+    /// The node with the next larger key, if any
+    pub(crate) fn next(self) -> Option<LNodeRef<'a, K, V>> {
+        unsafe { self.node.next.as_ref() }.map(LNodeRef::new)
+    }
+
+    /// The node with the next smaller key, if any
+    pub(crate) fn prev(self) -> Option<LNodeRef<'a, K, V>> {
+        unsafe { self.node.prev.as_ref() }.map(LNodeRef::new)
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::hash_map::Entry;
+use std::ptr;
+
+use fnv::FnvHashMap as HashMap;
+
+use super::{Descendant, LNode, LevelSearchable};
+
+/// A key that is searched by its 2 big-endian bytes
+pub trait Key16: Copy {
+    fn to_bytes(self) -> [u8; 2];
+    fn from_bytes(bytes: [u8; 2]) -> Self;
+}
+
+impl Key16 for u16 {
+    fn to_bytes(self) -> [u8; 2] {
+        self.to_be_bytes()
+    }
+
+    fn from_bytes(bytes: [u8; 2]) -> Self {
+        u16::from_be_bytes(bytes)
+    }
+}
+
+impl Key16 for [u8; 2] {
+    fn to_bytes(self) -> [u8; 2] {
+        self
+    }
+
+    fn from_bytes(bytes: [u8; 2]) -> Self {
+        bytes
+    }
+}
+
+impl_level_searchable!(u16, u16, Key16, LevelSearch, 0, u16::MAX);
+impl_level_searchable!([u8; 2], u16, Key16, LevelSearch, [0; 2], [0xFF; 2]);
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct LevelSearch<K: LevelSearchable<T>, T> {
+    l0: Descendant<K, T>,
+    l1: HashMap<[u8; 1], Descendant<K, T>>,
+}
+
+impl<K: LevelSearchable<T> + Key16, T> LevelSearch<K, T> {
+    fn new() -> LevelSearch<K, T> {
+        LevelSearch {
+            l0: Descendant::new(),
+            l1: HashMap::default(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.l0 = Descendant::new();
+        self.l1.clear();
+    }
+
+    fn level_sizes(&self) -> Vec<usize> {
+        vec![usize::from(!self.l0.is_empty()), self.l1.len()]
+    }
+
+    fn insert(&mut self, node: &mut LNode<K, T>) {
+        let bytes = node.key.to_bytes();
+        let b1 = [bytes[0]];
+
+        // Do not use longest_descendant so we can re-use the hash and
+        // entry
+        match self.l1.entry(b1) {
+            Entry::Occupied(mut o) => {
+                o.get_mut().set_links(bytes[1], node);
+                if !o.get_mut().merge(bytes[1], node) {
+                    return;
+                }
+            }
+            Entry::Vacant(v) => {
+                self.l0.set_links(bytes[0], node);
+
+                let mut desc = Descendant::new();
+                let ptr = ptr::NonNull::from(&mut *node);
+                desc.maxes.insert(bytes[1], ptr);
+                desc.min = Some((bytes[1], ptr));
+                v.insert(desc);
+            }
+        }
+        self.l0.merge(bytes[0], node);
+    }
+
+    fn remove(&mut self, node: &LNode<K, T>) {
+        let bytes = node.key.to_bytes();
+
+        self.l0.remove(bytes[0], node);
+        if let Entry::Occupied(mut o) = self.l1.entry([bytes[0]]) {
+            o.get_mut().remove(bytes[1], node);
+            if o.get().is_empty() {
+                o.remove();
+            }
+        }
+    }
+
+    fn longest_descendant(&self, key: K) -> (u8, &Descendant<K, T>) {
+        let bytes = key.to_bytes();
+        if let Some(desc) = self.l1.get(&[bytes[0]]) {
+            (bytes[1], desc)
+        } else {
+            (bytes[0], &self.l0)
+        }
+    }
+
+    fn longest_descendant_mut(
+        &mut self,
+        key: K,
+    ) -> (u8, &mut Descendant<K, T>) {
+        let bytes = key.to_bytes();
+        if let Some(desc) = self.l1.get_mut(&[bytes[0]]) {
+            (bytes[1], desc)
+        } else {
+            (bytes[0], &mut self.l0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::iter::FromIterator;
+
+    use bytemap::ByteMap;
+
+    #[test]
+    fn test_levelsearch_insert_3() {
+        let mut lss = LevelSearch::new();
+        let mut n1 = LNode::new(0xbaad, ());
+        let mut n2 = LNode::new(0xdead, ());
+        let mut n3 = LNode::new(0xdeef, ());
+
+        lss.insert(&mut n3);
+        lss.insert(&mut n1);
+        lss.insert(&mut n2);
+
+        let p1 = &mut n1 as *mut _;
+        let p2 = &mut n2 as *mut _;
+        let p3 = &mut n3 as *mut _;
+
+        assert!(n1.prev.is_null());
+        assert_eq!(n1.next, p2);
+        assert_eq!(n2.prev, p1);
+        assert_eq!(n2.next, p3);
+        assert_eq!(n3.prev, p2);
+        assert!(n3.next.is_null());
+
+        let nn1 = ptr::NonNull::new(p1).unwrap();
+        let nn2 = ptr::NonNull::new(p2).unwrap();
+        let nn3 = ptr::NonNull::new(p3).unwrap();
+
+        assert_eq!(
+            lss.l0,
+            Descendant {
+                min: Some((0xba, nn1)),
+                maxes: ByteMap::from_iter(vec![(0xba, nn1), (0xde, nn3)])
+            }
+        );
+        assert_eq!(
+            lss.l1,
+            HashMap::from_iter(vec![
+                (
+                    [0xba],
+                    Descendant {
+                        min: Some((0xad, nn1)),
+                        maxes: ByteMap::from_iter(vec![(0xad, nn1)])
+                    }
+                ),
+                (
+                    [0xde],
+                    Descendant {
+                        min: Some((0xad, nn2)),
+                        maxes: ByteMap::from_iter(vec![
+                            (0xad, nn2),
+                            (0xef, nn3)
+                        ])
+                    }
+                )
+            ])
+        );
+
+        lss.remove(&n2);
+        assert_eq!(lss.longest_descendant(0xdead).1.min, Some((0xef, nn3)));
+    }
+}
@@ -5,53 +5,45 @@ use fnv::FnvHashMap as HashMap;
 
 use super::{Descendant, LNode, LevelSearchable};
 
-impl<T> LevelSearchable<T> for u32 {
-    type LSS = LevelSearch<T>;
-    const MIN: u32 = 0;
-    const MAX: u32 = u32::max_value();
-    const LEN: usize = 32;
-
-    fn lss_new() -> LevelSearch<T> {
-        LevelSearch::new()
-    }
-
-    fn lss_clear(lss: &mut LevelSearch<T>) {
-        lss.clear();
-    }
+/// A key that is searched by its 4 big-endian bytes
+pub trait Key32: Copy {
+    fn to_bytes(self) -> [u8; 4];
+    fn from_bytes(bytes: [u8; 4]) -> Self;
+}
 
-    fn lss_insert(lss: &mut LevelSearch<T>, node: &mut LNode<u32, T>) {
-        lss.insert(node);
+impl Key32 for u32 {
+    fn to_bytes(self) -> [u8; 4] {
+        self.to_be_bytes()
     }
 
-    fn lss_remove(lss: &mut LevelSearch<T>, node: &LNode<u32, T>) {
-        lss.remove(node);
+    fn from_bytes(bytes: [u8; 4]) -> Self {
+        u32::from_be_bytes(bytes)
     }
+}
 
-    fn lss_longest_descendant(
-        lss: &LevelSearch<T>,
-        key: Self,
-    ) -> (u8, &Descendant<u32, T>) {
-        lss.longest_descendant(key)
+impl Key32 for [u8; 4] {
+    fn to_bytes(self) -> [u8; 4] {
+        self
     }
 
-    fn lss_longest_descendant_mut(
-        lss: &mut LevelSearch<T>,
-        key: Self,
-    ) -> (u8, &mut Descendant<u32, T>) {
-        lss.longest_descendant_mut(key)
+    fn from_bytes(bytes: [u8; 4]) -> Self {
+        bytes
     }
 }
 
+impl_level_searchable!(u32, u32, Key32, LevelSearch, 0, u32::MAX);
+impl_level_searchable!([u8; 4], u32, Key32, LevelSearch, [0; 4], [0xFF; 4]);
+
 #[derive(Debug, Default, Eq, PartialEq)]
-pub struct LevelSearch<T> {
-    l0: Descendant<u32, T>,
-    l1: HashMap<[u8; 1], Descendant<u32, T>>,
-    l2: HashMap<[u8; 2], Descendant<u32, T>>,
-    l3: HashMap<[u8; 3], Descendant<u32, T>>,
+pub struct LevelSearch<K: LevelSearchable<T>, T> {
+    l0: Descendant<K, T>,
+    l1: HashMap<[u8; 1], Descendant<K, T>>,
+    l2: HashMap<[u8; 2], Descendant<K, T>>,
+    l3: HashMap<[u8; 3], Descendant<K, T>>,
 }
 
-impl<T> LevelSearch<T> {
-    fn new() -> LevelSearch<T> {
+impl<K: LevelSearchable<T> + Key32, T> LevelSearch<K, T> {
+    fn new() -> LevelSearch<K, T> {
         LevelSearch {
             l0: Descendant::new(),
             l1: HashMap::default(),
@@ -67,8 +59,17 @@ impl<T> LevelSearch<T> {
         self.l3.clear();
     }
 
-    fn insert(&mut self, node: &mut LNode<u32, T>) {
-        let bytes = node.key.to_be_bytes();
+    fn level_sizes(&self) -> Vec<usize> {
+        vec![
+            usize::from(!self.l0.is_empty()),
+            self.l1.len(),
+            self.l2.len(),
+            self.l3.len(),
+        ]
+    }
+
+    fn insert(&mut self, node: &mut LNode<K, T>) {
+        let bytes = node.key.to_bytes();
         let b1 = [bytes[0]];
         let b2 = [bytes[0], bytes[1]];
         let b3 = [bytes[0], bytes[1], bytes[2]];
@@ -91,10 +92,10 @@ impl<T> LevelSearch<T> {
             self.l0.set_links(bytes[0], node);
         }
 
-        fn insert_into_entry<T, K>(
+        fn insert_into_entry<K: LevelSearchable<T>, T, B>(
             byte: u8,
-            node: &mut LNode<u32, T>,
-            entry: Entry<K, Descendant<u32, T>>,
+            node: &mut LNode<K, T>,
+            entry: Entry<B, Descendant<K, T>>,
         ) -> bool {
             match entry {
                 Entry::Vacant(v) => {
@@ -120,8 +121,8 @@ impl<T> LevelSearch<T> {
         self.l0.merge(bytes[0], node);
     }
 
-    fn remove(&mut self, node: &LNode<u32, T>) {
-        let bytes = node.key.to_be_bytes();
+    fn remove(&mut self, node: &LNode<K, T>) {
+        let bytes = node.key.to_bytes();
         let b1 = [bytes[0]];
         let b2 = [bytes[0], bytes[1]];
         let b3 = [bytes[0], bytes[1], bytes[2]];
@@ -147,8 +148,8 @@ impl<T> LevelSearch<T> {
         }
     }
 
-    fn longest_descendant(&self, key: u32) -> (u8, &Descendant<u32, T>) {
-        let bytes = key.to_be_bytes();
+    fn longest_descendant(&self, key: K) -> (u8, &Descendant<K, T>) {
+        let bytes = key.to_bytes();
         if let Some(desc) = self.l2.get(&[bytes[0], bytes[1]]) {
             if let Some(desc) = self.l3.get(&[bytes[0], bytes[1], bytes[2]]) {
                 (bytes[3], &desc)
@@ -164,9 +165,9 @@ impl<T> LevelSearch<T> {
 
     fn longest_descendant_mut(
         &mut self,
-        key: u32,
-    ) -> (u8, &mut Descendant<u32, T>) {
-        let bytes = key.to_be_bytes();
+        key: K,
+    ) -> (u8, &mut Descendant<K, T>) {
+        let bytes = key.to_bytes();
         if let Some(desc) = self.l2.get_mut(&[bytes[0], bytes[1]]) {
             if let Some(desc) = self.l3.get_mut(&[bytes[0], bytes[1], bytes[2]])
             {
@@ -5,57 +5,49 @@ use fnv::FnvHashMap as HashMap;
 
 use super::{Descendant, LNode, LevelSearchable};
 
-impl<T> LevelSearchable<T> for u64 {
-    type LSS = LevelSearch<T>;
-    const MIN: u64 = 0;
-    const MAX: u64 = u64::max_value();
-    const LEN: usize = 64;
-
-    fn lss_new() -> LevelSearch<T> {
-        LevelSearch::new()
-    }
-
-    fn lss_clear(lss: &mut LevelSearch<T>) {
-        lss.clear();
-    }
+/// A key that is searched by its 8 big-endian bytes
+pub trait Key64: Copy {
+    fn to_bytes(self) -> [u8; 8];
+    fn from_bytes(bytes: [u8; 8]) -> Self;
+}
 
-    fn lss_insert(lss: &mut LevelSearch<T>, node: &mut LNode<u64, T>) {
-        lss.insert(node);
+impl Key64 for u64 {
+    fn to_bytes(self) -> [u8; 8] {
+        self.to_be_bytes()
     }
 
-    fn lss_remove(lss: &mut LevelSearch<T>, node: &LNode<u64, T>) {
-        lss.remove(node);
+    fn from_bytes(bytes: [u8; 8]) -> Self {
+        u64::from_be_bytes(bytes)
     }
+}
 
-    fn lss_longest_descendant(
-        lss: &LevelSearch<T>,
-        key: Self,
-    ) -> (u8, &Descendant<u64, T>) {
-        lss.longest_descendant(key)
+impl Key64 for [u8; 8] {
+    fn to_bytes(self) -> [u8; 8] {
+        self
     }
 
-    fn lss_longest_descendant_mut(
-        lss: &mut LevelSearch<T>,
-        key: Self,
-    ) -> (u8, &mut Descendant<u64, T>) {
-        lss.longest_descendant_mut(key)
+    fn from_bytes(bytes: [u8; 8]) -> Self {
+        bytes
     }
 }
 
+impl_level_searchable!(u64, u64, Key64, LevelSearch, 0, u64::MAX);
+impl_level_searchable!([u8; 8], u64, Key64, LevelSearch, [0; 8], [0xFF; 8]);
+
 #[derive(Debug, Default, Eq, PartialEq)]
-pub struct LevelSearch<T> {
-    l0: Descendant<u64, T>,
-    l1: HashMap<[u8; 1], Descendant<u64, T>>,
-    l2: HashMap<[u8; 2], Descendant<u64, T>>,
-    l3: HashMap<[u8; 3], Descendant<u64, T>>,
-    l4: HashMap<[u8; 4], Descendant<u64, T>>,
-    l5: HashMap<[u8; 5], Descendant<u64, T>>,
-    l6: HashMap<[u8; 6], Descendant<u64, T>>,
-    l7: HashMap<[u8; 7], Descendant<u64, T>>,
+pub struct LevelSearch<K: LevelSearchable<T>, T> {
+    l0: Descendant<K, T>,
+    l1: HashMap<[u8; 1], Descendant<K, T>>,
+    l2: HashMap<[u8; 2], Descendant<K, T>>,
+    l3: HashMap<[u8; 3], Descendant<K, T>>,
+    l4: HashMap<[u8; 4], Descendant<K, T>>,
+    l5: HashMap<[u8; 5], Descendant<K, T>>,
+    l6: HashMap<[u8; 6], Descendant<K, T>>,
+    l7: HashMap<[u8; 7], Descendant<K, T>>,
 }
 
-impl<T> LevelSearch<T> {
-    fn new() -> LevelSearch<T> {
+impl<K: LevelSearchable<T> + Key64, T> LevelSearch<K, T> {
+    fn new() -> LevelSearch<K, T> {
         LevelSearch {
             l0: Descendant::new(),
             l1: HashMap::default(),
@@ -73,10 +65,27 @@ impl<T> LevelSearch<T> {
         self.l1.clear();
         self.l2.clear();
         self.l3.clear();
+        self.l4.clear();
+        self.l5.clear();
+        self.l6.clear();
+        self.l7.clear();
+    }
+
+    fn level_sizes(&self) -> Vec<usize> {
+        vec![
+            usize::from(!self.l0.is_empty()),
+            self.l1.len(),
+            self.l2.len(),
+            self.l3.len(),
+            self.l4.len(),
+            self.l5.len(),
+            self.l6.len(),
+            self.l7.len(),
+        ]
     }
 
-    fn insert(&mut self, node: &mut LNode<u64, T>) {
-        let bytes = node.key.to_be_bytes();
+    fn insert(&mut self, node: &mut LNode<K, T>) {
+        let bytes = node.key.to_bytes();
         let b1 = [bytes[0]];
         let b2 = [bytes[0], bytes[1]];
         let b3 = [bytes[0], bytes[1], bytes[2]];
@@ -126,10 +135,10 @@ impl<T> LevelSearch<T> {
             }
         }
 
-        fn insert_into_entry<T, K>(
+        fn insert_into_entry<K: LevelSearchable<T>, T, B>(
             byte: u8,
-            node: &mut LNode<u64, T>,
-            entry: Entry<K, Descendant<u64, T>>,
+            node: &mut LNode<K, T>,
+            entry: Entry<B, Descendant<K, T>>,
         ) -> bool {
             match entry {
                 Entry::Vacant(v) => {
@@ -167,8 +176,8 @@ impl<T> LevelSearch<T> {
         self.l0.merge(bytes[0], node);
     }
 
-    fn remove(&mut self, node: &LNode<u64, T>) {
-        let bytes = node.key.to_be_bytes();
+    fn remove(&mut self, node: &LNode<K, T>) {
+        let bytes = node.key.to_bytes();
         let b1 = [bytes[0]];
         let b2 = [bytes[0], bytes[1]];
         let b3 = [bytes[0], bytes[1], bytes[2]];
@@ -225,8 +234,8 @@ impl<T> LevelSearch<T> {
         }
     }
 
-    fn longest_descendant(&self, key: u64) -> (u8, &Descendant<u64, T>) {
-        let bytes = key.to_be_bytes();
+    fn longest_descendant(&self, key: K) -> (u8, &Descendant<K, T>) {
+        let bytes = key.to_bytes();
 
         let b1 = [bytes[0]];
         let b2 = [bytes[0], bytes[1]];
@@ -268,9 +277,9 @@ impl<T> LevelSearch<T> {
 
     fn longest_descendant_mut(
         &mut self,
-        key: u64,
-    ) -> (u8, &mut Descendant<u64, T>) {
-        let bytes = key.to_be_bytes();
+        key: K,
+    ) -> (u8, &mut Descendant<K, T>) {
+        let bytes = key.to_bytes();
 
         let b1 = [bytes[0]];
         let b2 = [bytes[0], bytes[1]];
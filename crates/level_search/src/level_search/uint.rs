@@ -0,0 +1,335 @@
+use std::collections::hash_map::Entry;
+use std::ptr;
+
+use fnv::FnvHashMap as HashMap;
+
+use super::{Descendant, LNode, LevelSearchable};
+
+/// An unsigned integer of `BITS` bits, stored in a `u64`
+///
+/// `BITS` must be a multiple of 8 between 8 and 64. Keys are searched a
+/// byte at a time, so a `UInt<24>` only needs 3 levels where a `u32` needs
+/// 4, and a `UInt<40>` needs 5 where a `u64` needs 8.
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct UInt<const BITS: usize>(u64);
+
+impl<const BITS: usize> UInt<BITS> {
+    const BYTES: usize = {
+        assert!(BITS.is_multiple_of(8) && BITS >= 8 && BITS <= 64);
+        BITS / 8
+    };
+    const MASK: u64 = u64::MAX >> (64 - 8 * Self::BYTES);
+
+    /// Panics if `value` does not fit in `BITS` bits
+    pub fn new(value: u64) -> UInt<BITS> {
+        UInt::try_new(value).expect("value does not fit")
+    }
+
+    /// Return `None` if `value` does not fit in `BITS` bits
+    pub fn try_new(value: u64) -> Option<UInt<BITS>> {
+        if value & !Self::MASK == 0 {
+            Some(UInt(value))
+        } else {
+            None
+        }
+    }
+
+    /// Keep only the low `BITS` bits of `value`
+    pub fn truncate(value: u64) -> UInt<BITS> {
+        UInt(value & Self::MASK)
+    }
+
+    pub fn get(self) -> u64 {
+        self.0
+    }
+
+    /// The `i`th byte, counting from the most significant
+    fn byte(self, i: usize) -> u8 {
+        (self.0 >> (BITS - 8 * (i + 1))) as u8
+    }
+
+    /// The leading `i` bytes
+    fn prefix(self, i: usize) -> u64 {
+        self.0 >> (BITS - 8 * i)
+    }
+}
+
+impl<const BITS: usize> From<UInt<BITS>> for u64 {
+    fn from(value: UInt<BITS>) -> u64 {
+        value.0
+    }
+}
+
+impl<T, const BITS: usize> LevelSearchable<T> for UInt<BITS> {
+    type LSS = LevelSearch<BITS, T>;
+    const MIN: Self = UInt(0);
+    const MAX: Self = UInt(Self::MASK);
+    const LEN: usize = BITS;
+
+    fn common_prefix_bits(self, other: Self) -> u32 {
+        ((self.0 ^ other.0) << (64 - BITS))
+            .leading_zeros()
+            .min(BITS as u32)
+    }
+
+    fn distance(self, other: Self) -> u64 {
+        self.0.abs_diff(other.0)
+    }
+
+    fn checked_increment(self) -> Option<Self> {
+        if self.0 == Self::MASK {
+            None
+        } else {
+            Some(UInt(self.0 + 1))
+        }
+    }
+
+    fn checked_decrement(self) -> Option<Self> {
+        self.0.checked_sub(1).map(UInt)
+    }
+
+    fn lss_new() -> LevelSearch<BITS, T> {
+        LevelSearch::new()
+    }
+
+    fn lss_clear(lss: &mut LevelSearch<BITS, T>) {
+        lss.clear();
+    }
+
+    fn lss_insert(lss: &mut LevelSearch<BITS, T>, node: &mut LNode<Self, T>) {
+        lss.insert(node);
+    }
+
+    fn lss_remove(lss: &mut LevelSearch<BITS, T>, node: &LNode<Self, T>) {
+        lss.remove(node);
+    }
+
+    fn lss_level_sizes(lss: &LevelSearch<BITS, T>) -> Vec<usize> {
+        lss.level_sizes()
+    }
+
+    fn lss_longest_descendant(
+        lss: &LevelSearch<BITS, T>,
+        key: Self,
+    ) -> (u8, &Descendant<Self, T>) {
+        lss.longest_descendant(key)
+    }
+
+    fn lss_longest_descendant_mut(
+        lss: &mut LevelSearch<BITS, T>,
+        key: Self,
+    ) -> (u8, &mut Descendant<Self, T>) {
+        lss.longest_descendant_mut(key)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct LevelSearch<const BITS: usize, T> {
+    l0: Descendant<UInt<BITS>, T>,
+    // `levels[i - 1]` is keyed by the leading `i` bytes of a key
+    levels: Vec<HashMap<u64, Descendant<UInt<BITS>, T>>>,
+}
+
+impl<const BITS: usize, T> Default for LevelSearch<BITS, T> {
+    fn default() -> Self {
+        LevelSearch::new()
+    }
+}
+
+impl<const BITS: usize, T> LevelSearch<BITS, T> {
+    fn new() -> LevelSearch<BITS, T> {
+        LevelSearch {
+            l0: Descendant::new(),
+            levels: (1..UInt::<BITS>::BYTES)
+                .map(|_| HashMap::default())
+                .collect(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.l0 = Descendant::new();
+        for level in self.levels.iter_mut() {
+            level.clear();
+        }
+    }
+
+    fn level_sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![usize::from(!self.l0.is_empty())];
+        sizes.extend(self.levels.iter().map(|level| level.len()));
+        sizes
+    }
+
+    /// The number of leading bytes of `key` with a `Descendant`, found by
+    /// binary search since a prefix's shorter prefixes are always present
+    fn depth(&self, key: UInt<BITS>) -> usize {
+        let (mut low, mut high) = (0, self.levels.len());
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if self.levels[mid - 1].contains_key(&key.prefix(mid)) {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        low
+    }
+
+    fn insert(&mut self, node: &mut LNode<UInt<BITS>, T>) {
+        let key = node.key;
+        match self.depth(key) {
+            0 => self.l0.set_links(key.byte(0), node),
+            depth => self.levels[depth - 1]
+                .get_mut(&key.prefix(depth))
+                .expect("the prefixes up to `depth` have a Descendant")
+                .set_links(key.byte(depth), node),
+        }
+
+        for i in (1..=self.levels.len()).rev() {
+            let merged = match self.levels[i - 1].entry(key.prefix(i)) {
+                Entry::Vacant(v) => {
+                    let mut desc = Descendant::new();
+                    let ptr = ptr::NonNull::from(&mut *node);
+                    desc.maxes.insert(key.byte(i), ptr);
+                    desc.min = Some((key.byte(i), ptr));
+                    v.insert(desc);
+                    true
+                }
+                Entry::Occupied(mut o) => o.get_mut().merge(key.byte(i), node),
+            };
+            if !merged {
+                return;
+            }
+        }
+        self.l0.merge(key.byte(0), node);
+    }
+
+    fn remove(&mut self, node: &LNode<UInt<BITS>, T>) {
+        let key = node.key;
+        self.l0.remove(key.byte(0), node);
+        for i in 1..=self.levels.len() {
+            if let Entry::Occupied(mut o) =
+                self.levels[i - 1].entry(key.prefix(i))
+            {
+                o.get_mut().remove(key.byte(i), node);
+                if o.get().is_empty() {
+                    o.remove();
+                }
+            }
+        }
+    }
+
+    fn longest_descendant(
+        &self,
+        key: UInt<BITS>,
+    ) -> (u8, &Descendant<UInt<BITS>, T>) {
+        match self.depth(key) {
+            0 => (key.byte(0), &self.l0),
+            depth => {
+                (key.byte(depth), &self.levels[depth - 1][&key.prefix(depth)])
+            }
+        }
+    }
+
+    fn longest_descendant_mut(
+        &mut self,
+        key: UInt<BITS>,
+    ) -> (u8, &mut Descendant<UInt<BITS>, T>) {
+        match self.depth(key) {
+            0 => (key.byte(0), &mut self.l0),
+            depth => (
+                key.byte(depth),
+                self.levels[depth - 1]
+                    .get_mut(&key.prefix(depth))
+                    .expect("the prefixes up to `depth` have a Descendant"),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    use crate::xfast::XFastMap;
+    use crate::yfast::YFastMap;
+
+    #[test]
+    fn test_uint_bounds() {
+        assert_eq!(UInt::<24>::try_new(1 << 24), None);
+        assert_eq!(UInt::<24>::truncate(0x1234_5678).get(), 0x34_5678);
+        assert_eq!(<UInt<24> as LevelSearchable<()>>::MAX.get(), 0xFF_FFFF);
+        assert_eq!(
+            <UInt<40> as LevelSearchable<()>>::checked_increment(UInt::new(
+                0xFF_FFFF_FFFF
+            )),
+            None
+        );
+        assert_eq!(
+            <UInt<24> as LevelSearchable<()>>::common_prefix_bits(
+                UInt::new(0x80_0000),
+                UInt::new(0x00_0000)
+            ),
+            0
+        );
+        assert_eq!(
+            <UInt<24> as LevelSearchable<()>>::common_prefix_bits(
+                UInt::new(7),
+                UInt::new(7)
+            ),
+            24
+        );
+    }
+
+    #[test]
+    fn test_uint_maps() {
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let keys = (0..5000)
+            .map(|_| rng.gen_range(0, 1u64 << 24))
+            .collect::<Vec<_>>();
+        let reference = keys
+            .iter()
+            .map(|&k| (k as u32, k))
+            .collect::<BTreeMap<_, _>>();
+        let xfast = keys
+            .iter()
+            .map(|&k| (UInt::<24>::new(k), k))
+            .collect::<BTreeMap<_, _>>();
+        let xfast = XFastMap::from(xfast);
+        let mut yfast = YFastMap::new();
+        for &k in keys.iter() {
+            yfast.insert(UInt::<24>::new(k), k);
+        }
+        assert_eq!(xfast.level_sizes().len(), 3);
+
+        for probe in (0..1 << 24).step_by(997) {
+            let key = UInt::new(probe);
+            let pred = reference.range(..=probe as u32).next_back();
+            let pred = pred.map(|(_, &v)| (UInt::new(v), v));
+            let succ = reference.range(probe as u32..).next();
+            let succ = succ.map(|(_, &v)| (UInt::new(v), v));
+
+            assert_eq!(xfast.predecessor(key).map(|(k, &v)| (k, v)), pred);
+            assert_eq!(xfast.successor(key).map(|(k, &v)| (k, v)), succ);
+            assert_eq!(yfast.predecessor(key).map(|(k, &v)| (k, v)), pred);
+            assert_eq!(yfast.successor(key).map(|(k, &v)| (k, v)), succ);
+        }
+
+        let mut wide = YFastMap::new();
+        for &k in keys.iter() {
+            wide.insert(UInt::<40>::new(k << 16), k);
+        }
+        for &k in keys.iter().step_by(7) {
+            assert_eq!(wide.remove(UInt::new(k << 16)), Some(k));
+        }
+        let expected = reference
+            .keys()
+            .map(|&k| u64::from(k))
+            .filter(|k| !keys.iter().step_by(7).any(|r| r == k))
+            .collect::<Vec<_>>();
+        let actual =
+            wide.iter().map(|(k, _)| k.get() >> 16).collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+}
@@ -2,3 +2,5 @@ pub mod xfast;
 pub mod yfast;
 
 mod level_search;
+
+pub use crate::level_search::UInt;
@@ -1,10 +1,12 @@
 use std::collections::hash_map::Entry as HashEntry;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
 use std::ops::{Bound, RangeBounds};
 
 use fnv::FnvHashMap as HashMap;
 
 use super::LevelSearchable;
-use crate::level_search::LNode;
+use crate::level_search::{self, LNode, LNodeRef};
 
 #[derive(Default)]
 pub struct XFastMap<K: LevelSearchable<V>, V> {
@@ -12,18 +14,27 @@ pub struct XFastMap<K: LevelSearchable<V>, V> {
     map: HashMap<K, Box<LNode<K, V>>>,
 }
 
-pub(super) struct Iter<'a, K: LevelSearchable<V>, V>(Option<&'a LNode<K, V>>);
+pub(super) struct Iter<'a, K: LevelSearchable<V>, V> {
+    lss: &'a K::LSS,
+    node: Option<LNodeRef<'a, K, V>>,
+}
 impl<'a, K: LevelSearchable<V>, V> Iterator for Iter<'a, K, V> {
     type Item = (K, &'a V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.0 {
-            self.0 = unsafe { node.next.as_ref() };
-            Some((node.key, &node.value))
+        if let Some(node) = self.node {
+            self.node = node.next();
+            Some((node.key(), node.value()))
         } else {
             None
         }
     }
+
+    fn last(self) -> Option<Self::Item> {
+        // The iterator always runs to the end of the list
+        self.node?;
+        K::lss_max(self.lss).map(|node| (node.key, &node.value))
+    }
 }
 
 pub(super) struct Range<'a, K: LevelSearchable<V>, V, R>
@@ -31,7 +42,7 @@ where
     R: RangeBounds<K>,
 {
     range: R,
-    node: Option<&'a LNode<K, V>>,
+    node: Option<LNodeRef<'a, K, V>>,
 }
 impl<'a, K: LevelSearchable<V>, V, R> Iterator for Range<'a, K, V, R>
 where
@@ -43,13 +54,13 @@ where
         if let Some(node) = self.node {
             let contains = match self.range.end_bound() {
                 Bound::Unbounded => true,
-                Bound::Excluded(upper) => node.key < *upper,
-                Bound::Included(upper) => node.key <= *upper,
+                Bound::Excluded(upper) => node.key() < *upper,
+                Bound::Included(upper) => node.key() <= *upper,
             };
 
             if contains {
-                self.node = unsafe { node.next.as_ref() };
-                Some((node.key, &node.value))
+                self.node = node.next();
+                Some((node.key(), node.value()))
             } else {
                 self.node = None;
                 None
@@ -76,6 +87,12 @@ impl<K: LevelSearchable<V>, V> XFastMap<K, V> {
         self.map.len()
     }
 
+    /// The number of distinct key prefixes stored at each level of the
+    /// level search, from the empty prefix to those one byte short of a key
+    pub fn level_sizes(&self) -> Vec<usize> {
+        K::lss_level_sizes(&self.lss)
+    }
+
     /// Clear the map, removing all keys and values
     pub fn clear(&mut self) {
         K::lss_clear(&mut self.lss);
@@ -91,6 +108,24 @@ impl<K: LevelSearchable<V>, V> XFastMap<K, V> {
         self.map.contains_key(&key)
     }
 
+    /// Return the value for `key`, along with the next key in the map
+    pub fn get_with_next(&self, key: K) -> Option<(&V, Option<K>)> {
+        self.map.get(&key).map(|node| {
+            let next = unsafe { node.next.as_ref() }.map(|next| next.key);
+            (&node.value, next)
+        })
+    }
+
+    /// Return the smallest key strictly greater than `key`
+    pub fn successor_of(&self, key: K) -> Option<K> {
+        self.successor_strict(key).map(|(k, _)| k)
+    }
+
+    /// Return the largest key strictly less than `key`
+    pub fn predecessor_of(&self, key: K) -> Option<K> {
+        self.predecessor_strict(key).map(|(k, _)| k)
+    }
+
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
         match self.map.entry(key) {
             HashEntry::Occupied(mut o) => {
@@ -106,6 +141,25 @@ impl<K: LevelSearchable<V>, V> XFastMap<K, V> {
         }
     }
 
+    /// Return the value for `key`, inserting `f()` if it is missing
+    ///
+    /// This only hashes `key` once and only touches the level-search
+    /// structure when inserting.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(
+        &mut self,
+        key: K,
+        f: F,
+    ) -> &mut V {
+        match self.map.entry(key) {
+            HashEntry::Occupied(o) => &mut o.into_mut().value,
+            HashEntry::Vacant(v) => {
+                let mut node = Box::new(LNode::new(key, f()));
+                K::lss_insert(&mut self.lss, &mut node);
+                &mut v.insert(node).value
+            }
+        }
+    }
+
     pub fn remove(&mut self, key: K) -> Option<V> {
         match self.map.entry(key) {
             HashEntry::Vacant(_) => None,
@@ -126,7 +180,31 @@ impl<K: LevelSearchable<V>, V> XFastMap<K, V> {
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (K, &V)> {
-        Iter(K::lss_min(&self.lss))
+        Iter {
+            lss: &self.lss,
+            node: K::lss_min(&self.lss).map(LNodeRef::new),
+        }
+    }
+
+    /// Iterate over the inclusive ranges of keys missing from the map
+    pub fn gaps(&self) -> impl Iterator<Item = (K, K)> + '_ {
+        level_search::gaps::<K, V>(self.iter().map(|(k, _)| k))
+    }
+
+    /// Iterate over the entries with keys greater than `key`
+    ///
+    /// Because `key` itself is excluded, a paginated scan can resume with
+    /// `iter_from(last_key)` using the last key it saw.
+    pub fn iter_from(&self, key: K) -> impl Iterator<Item = (K, &V)> {
+        let node = if self.is_empty() {
+            None
+        } else {
+            self.successor_node(key).map(LNodeRef::new)
+        };
+        Iter {
+            lss: &self.lss,
+            node,
+        }
     }
 
     pub fn range(
@@ -140,31 +218,195 @@ impl<K: LevelSearchable<V>, V> XFastMap<K, V> {
         let node = match range.start_bound() {
             Bound::Unbounded => K::lss_min(&self.lss),
             Bound::Included(&key) => K::lss_successor(&self.lss, key),
-            Bound::Excluded(&key) => {
-                if key == K::MAX {
-                    None
-                } else {
-                    unimplemented!();
-                    // self.lss.successor(key + 1)
-                }
-            }
+            Bound::Excluded(&key) => self.successor_node(key),
         };
-        Range { range, node }
+        Range {
+            range,
+            node: node.map(LNodeRef::new),
+        }
+    }
+
+    /// Remove every key in `range`, returning how many were removed
+    ///
+    /// This finds the start of the range once and then removes each key
+    /// individually, so it costs one `remove` per removed key.
+    pub fn remove_range(&mut self, range: impl RangeBounds<K>) -> usize {
+        let keys = self.range(range).map(|(k, _)| k).collect::<Vec<_>>();
+        for key in keys.iter() {
+            self.remove(*key);
+        }
+        keys.len()
     }
 
+    /// Return the entry with the largest key less than or equal to `key`
     pub fn predecessor(&self, key: K) -> Option<(K, &V)> {
         K::lss_predecessor(&self.lss, key).map(|node| (node.key, &node.value))
     }
 
+    /// Return `predecessor(key)` for each of `keys`
+    ///
+    /// If `keys` is sorted, this searches for the first key's predecessor
+    /// and then walks the linked list forward alongside the rest, so it
+    /// costs one search plus a pass over the entries spanned by `keys`.
+    /// Otherwise it searches for each key separately.
+    pub fn predecessor_batch(&self, keys: &[K]) -> Vec<Option<(K, &V)>> {
+        if !keys.windows(2).all(|w| w[0] <= w[1]) {
+            return keys.iter().map(|&key| self.predecessor(key)).collect();
+        }
+
+        let mut batch = Vec::with_capacity(keys.len());
+        let mut node = match keys.first() {
+            Some(&key) => K::lss_predecessor(&self.lss, key),
+            None => return batch,
+        };
+        let mut next = match node {
+            Some(node) => unsafe { node.next.as_ref() },
+            None => K::lss_min(&self.lss),
+        };
+        for &key in keys {
+            while let Some(n) = next.filter(|n| n.key <= key) {
+                node = Some(n);
+                next = unsafe { n.next.as_ref() };
+            }
+            batch.push(node.map(|node| (node.key, &node.value)));
+        }
+        batch
+    }
+
+    /// Return the entry with the smallest key greater than or equal to `key`
     pub fn successor(&self, key: K) -> Option<(K, &V)> {
         K::lss_successor(&self.lss, key).map(|node| (node.key, &node.value))
     }
+
+    /// Return the entry with the largest key strictly less than `key`
+    pub fn predecessor_strict(&self, key: K) -> Option<(K, &V)> {
+        self.predecessor(key.checked_decrement()?)
+    }
+
+    /// Return the entry with the smallest key strictly greater than `key`
+    pub fn successor_strict(&self, key: K) -> Option<(K, &V)> {
+        let node = self.successor_node(key)?;
+        Some((node.key, &node.value))
+    }
+
+    /// The node with the smallest key strictly greater than `key`, which
+    /// is `None` at `K::MAX` rather than wrapping around
+    fn successor_node(&self, key: K) -> Option<&LNode<K, V>> {
+        K::lss_successor(&self.lss, key.checked_increment()?)
+    }
+
+    /// Return the entry whose key is closest to `key`, along with its
+    /// distance from `key`, preferring the smaller key on ties
+    pub fn nearest(&self, key: K) -> Option<(K, &V, u64)> {
+        let nearest = match (self.predecessor(key), self.successor(key)) {
+            (Some(pred), Some(succ)) => {
+                if key.distance(succ.0) < key.distance(pred.0) {
+                    succ
+                } else {
+                    pred
+                }
+            }
+            (Some(entry), None) | (None, Some(entry)) => entry,
+            (None, None) => return None,
+        };
+        Some((nearest.0, nearest.1, key.distance(nearest.0)))
+    }
+
+    /// Return how many leading bits `key` shares with its nearest key
+    ///
+    /// This is the bit-length of the longest common prefix between `key`
+    /// and its predecessor or successor, and is 0 for an empty map.
+    pub fn longest_common_prefix_bits(&self, key: K) -> u32 {
+        let prefix = |(other, _)| key.common_prefix_bits(other);
+        let pred = self.predecessor(key).map(prefix);
+        let succ = self.successor(key).map(prefix);
+        pred.max(succ).unwrap_or(0)
+    }
+}
+
+impl<K: LevelSearchable<V>, V> From<BTreeMap<K, V>> for XFastMap<K, V> {
+    fn from(btree: BTreeMap<K, V>) -> XFastMap<K, V> {
+        let mut xfast = XFastMap::new();
+        for (key, value) in btree {
+            xfast.insert(key, value);
+        }
+        xfast
+    }
+}
+
+impl<K: LevelSearchable<V>, V> From<XFastMap<K, V>> for BTreeMap<K, V> {
+    fn from(xfast: XFastMap<K, V>) -> BTreeMap<K, V> {
+        let XFastMap { lss, mut map } = xfast;
+
+        // Walk the linked list so the entries come out in sorted order
+        let mut keys = Vec::with_capacity(map.len());
+        let mut node = K::lss_min(&lss);
+        while let Some(n) = node {
+            keys.push(n.key);
+            node = unsafe { n.next.as_ref() };
+        }
+
+        keys.into_iter()
+            .map(|key| {
+                let node =
+                    map.remove(&key).expect("listed nodes are in the map");
+                (key, node.value)
+            })
+            .collect()
+    }
+}
+
+// Compare the entries in key order, since the hash maps iterate in an order
+// that depends on how the map was built
+impl<K: LevelSearchable<V>, V: PartialEq> PartialEq for XFastMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: LevelSearchable<V>, V: Eq> Eq for XFastMap<K, V> {}
+
+impl<K: LevelSearchable<V>, V: Hash> Hash for XFastMap<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for entry in self.iter() {
+            entry.hash(state);
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::level_search::LevelSearchable;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_xfast_eq() {
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let keys = (0..500).map(|_| rng.gen()).collect::<Vec<u32>>();
+        let forward = keys.iter().map(|&k| (k, k)).collect::<BTreeMap<_, _>>();
+        let forward = XFastMap::from(forward);
+        let mut backward = XFastMap::new();
+        for &k in keys.iter().rev() {
+            backward.insert(k, k);
+        }
+        assert!(forward == backward);
+
+        let hash = |map: &XFastMap<u32, u32>| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            map.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&forward), hash(&backward));
+
+        backward.insert(keys[7], 0);
+        assert!(forward != backward);
+        backward.insert(keys[7], keys[7]);
+        assert!(forward == backward);
+        backward.remove(keys[7]);
+        assert!(forward != backward);
+    }
 
     #[test]
     fn test_xfast_iter() {
@@ -188,6 +430,128 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_xfast_iter_from() {
+        let mut xfast = XFastMap::new();
+        let mut rng = SmallRng::from_seed([5; 16]);
+        for i in 0..1000u32 {
+            xfast.insert(rng.gen(), i);
+        }
+        let all = xfast.iter().collect::<Vec<_>>();
+
+        for &split in &[0, 1, 0x1234_5678, all[500].0, u32::MAX] {
+            let mut scan = xfast
+                .iter()
+                .take_while(|&(k, _)| k <= split)
+                .collect::<Vec<_>>();
+            scan.extend(xfast.iter_from(split));
+            assert_eq!(scan, all);
+        }
+
+        // Resume a paginated scan from the last key of each page
+        let mut pages = xfast.iter().take(64).collect::<Vec<_>>();
+        while let Some(&(last, _)) = pages.last() {
+            let page = xfast.iter_from(last).take(64).collect::<Vec<_>>();
+            if page.is_empty() {
+                break;
+            }
+            pages.extend(page);
+        }
+        assert_eq!(pages, all);
+
+        assert_eq!(XFastMap::<u32, ()>::new().iter_from(3).next(), None);
+    }
+
+    #[test]
+    fn test_xfast_btreemap_round_trip() {
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let btree = (0..2000u64)
+            .map(|i| (rng.gen::<u64>(), i.to_string()))
+            .collect::<BTreeMap<_, _>>();
+
+        let xfast = XFastMap::from(btree.clone());
+        assert_eq!(xfast.len(), btree.len());
+        assert_eq!(
+            xfast
+                .iter()
+                .map(|(k, v)| (k, v.clone()))
+                .collect::<Vec<_>>(),
+            btree.clone().into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(BTreeMap::from(xfast), btree);
+
+        let empty = XFastMap::<u32, ()>::new();
+        assert_eq!(BTreeMap::from(empty), BTreeMap::new());
+    }
+
+    #[test]
+    fn test_xfast_strict_neighbors() {
+        let mut xfast = XFastMap::new();
+        for &key in &[0, 1, 0x8000_0000, u32::MAX - 1, u32::MAX] {
+            xfast.insert(key, key);
+        }
+
+        assert_eq!(xfast.predecessor(0), Some((0, &0)));
+        assert_eq!(xfast.predecessor_strict(0), None);
+        assert_eq!(xfast.predecessor_strict(1), Some((0, &0)));
+        assert_eq!(xfast.successor_strict(0), Some((1, &1)));
+        assert_eq!(
+            xfast.successor_strict(2),
+            Some((0x8000_0000, &0x8000_0000))
+        );
+        assert_eq!(xfast.predecessor_strict(0x8000_0000), Some((1, &1)));
+
+        assert_eq!(xfast.successor(u32::MAX), Some((u32::MAX, &u32::MAX)));
+        assert_eq!(xfast.successor_strict(u32::MAX), None);
+        assert_eq!(
+            xfast.successor_strict(u32::MAX - 1),
+            Some((u32::MAX, &u32::MAX))
+        );
+        assert_eq!(
+            xfast.predecessor_strict(u32::MAX),
+            Some((u32::MAX - 1, &(u32::MAX - 1)))
+        );
+
+        // Without keys at the extremes there is still nothing to wrap to
+        xfast.remove(0);
+        xfast.remove(u32::MAX);
+        assert_eq!(xfast.predecessor_strict(1), None);
+        assert_eq!(xfast.successor_strict(u32::MAX - 1), None);
+        assert_eq!(XFastMap::<u32, ()>::new().predecessor_strict(5), None);
+    }
+
+    #[test]
+    fn test_xfast_nearest() {
+        let mut xfast = XFastMap::new();
+        assert_eq!(xfast.nearest(5u64), None);
+        for &key in &[10u64, 20, 1 << 40, u64::MAX - 1] {
+            xfast.insert(key, key);
+        }
+
+        assert_eq!(xfast.nearest(10), Some((10, &10, 0)));
+        assert_eq!(xfast.nearest(13), Some((10, &10, 3)));
+        assert_eq!(xfast.nearest(17), Some((20, &20, 3)));
+        // Ties go to the smaller key
+        assert_eq!(xfast.nearest(15), Some((10, &10, 5)));
+
+        // Only a successor or only a predecessor
+        assert_eq!(xfast.nearest(0), Some((10, &10, 10)));
+        assert_eq!(
+            xfast.nearest(u64::MAX),
+            Some((u64::MAX - 1, &(u64::MAX - 1), 1))
+        );
+
+        // Distances that do not fit in a smaller integer
+        assert_eq!(
+            xfast.nearest(1 << 63),
+            Some((1 << 40, &(1 << 40), (1 << 63) - (1 << 40)))
+        );
+
+        let mut xfast = XFastMap::new();
+        xfast.insert([0x80, 0], ());
+        assert_eq!(xfast.nearest([0, 0]), Some(([0x80, 0], &(), 0x8000)));
+    }
+
     #[test]
     fn test_xfast_range() {
         let mut keys: [u32; 34] = [
@@ -246,6 +610,34 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_xfast_range_excluded_near_max() {
+        let mut xfast = XFastMap::new();
+        for &key in &[0, 7, u32::MAX - 2, u32::MAX - 1] {
+            xfast.insert(key, ());
+        }
+        let excluded = |xfast: &XFastMap<u32, ()>, start| {
+            xfast
+                .range((Bound::Excluded(start), Bound::Unbounded))
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(excluded(&xfast, u32::MAX - 2), vec![u32::MAX - 1]);
+        assert_eq!(excluded(&xfast, u32::MAX - 1), vec![]);
+        assert_eq!(excluded(&xfast, u32::MAX), vec![]);
+        assert_eq!(xfast.iter_from(u32::MAX).count(), 0);
+        assert_eq!(xfast.successor_of(u32::MAX - 1), None);
+
+        xfast.insert(u32::MAX, ());
+        assert_eq!(excluded(&xfast, u32::MAX - 1), vec![u32::MAX]);
+        assert_eq!(excluded(&xfast, u32::MAX), vec![]);
+        let range = (Bound::Excluded(u32::MAX - 1), Bound::Included(u32::MAX));
+        assert_eq!(xfast.range(range).count(), 1);
+        assert_eq!(xfast.successor_of(u32::MAX), None);
+        assert_eq!(xfast.predecessor_of(0), None);
+        assert_eq!(xfast.predecessor_of(7), Some(0));
+    }
+
     #[test]
     fn test_xfast_insert_preserves_linked_list() {
         let keys: [u32; 34] = [
@@ -314,6 +706,33 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_xfast_predecessor_batch() {
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let mut xfast = (1..200u32)
+            .map(|i| (rng.gen_range(0, 1 << 24), i))
+            .collect::<BTreeMap<_, _>>();
+        xfast.insert(0, 0);
+        let xfast = XFastMap::from(xfast);
+        assert_eq!(xfast.predecessor_batch(&[]), vec![]);
+
+        let mut queries = (0..500)
+            .map(|_| rng.gen_range(0, 1 << 25))
+            .chain(vec![0, 0, u32::MAX, 1 << 24])
+            .collect::<Vec<_>>();
+        let expected = |queries: &[u32]| {
+            queries
+                .iter()
+                .map(|&q| xfast.predecessor(q))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(xfast.predecessor_batch(&queries), expected(&queries));
+
+        queries.sort();
+        assert_eq!(xfast.predecessor_batch(&queries), expected(&queries));
+        assert_eq!(xfast.predecessor_batch(&queries[..1]), vec![Some((0, &0))]);
+    }
+
     #[test]
     fn test_xfast_integration_remove() {
         let keys: [u32; 34] = [
@@ -364,4 +783,184 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_xfast_get_or_insert_with() {
+        let mut xfast = XFastMap::new();
+        let mut calls = 0;
+        for i in (0..100u32).chain(0..100) {
+            let value = xfast.get_or_insert_with(i * 7, || {
+                calls += 1;
+                0
+            });
+            *value += 1;
+        }
+        assert_eq!(calls, 100);
+        assert_eq!(xfast.len(), 100);
+
+        for i in 0..100u32 {
+            assert_eq!(xfast.get(i * 7), Some(&2));
+            assert_eq!(
+                xfast.successor(i * 7 + 1).map(|(k, _)| k),
+                if i < 99 { Some(i * 7 + 7) } else { None }
+            );
+        }
+        assert_eq!(
+            xfast.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            (0..100).map(|i| i * 7).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_xfast_longest_common_prefix_bits() {
+        let keys: [u32; 6] = [
+            0xcd59c9de, 0x856cb188, 0x6eaaa008, 0xde8db9a9, 0xac3c6ef9,
+            0x0e6c8517,
+        ];
+        let mut xfast = XFastMap::new();
+        assert_eq!(xfast.longest_common_prefix_bits(0), 0);
+        for key in keys.iter() {
+            xfast.insert(*key, ());
+        }
+
+        let queries = [0, 0xcd59c9df, 0x856cb188, 0x70000000, 0xFFFFFFFF];
+        for query in queries.iter().cloned() {
+            let expected = keys
+                .iter()
+                .map(|key| (key ^ query).leading_zeros())
+                .max()
+                .unwrap();
+            assert_eq!(xfast.longest_common_prefix_bits(query), expected);
+        }
+        assert_eq!(xfast.longest_common_prefix_bits(0x856cb188), 32);
+        assert_eq!(xfast.longest_common_prefix_bits(0xcd59c9df), 31);
+    }
+
+    #[test]
+    fn test_xfast_iter_last() {
+        let mut xfast = XFastMap::new();
+        assert_eq!(xfast.iter().last(), None);
+
+        let keys: [u32; 5] =
+            [0xcd59c9de, 0x856cb188, 0x6eaaa008, 0xde8db9a9, 0x0e6c8517];
+        for key in keys.iter() {
+            xfast.insert(*key, ());
+        }
+        assert_eq!(xfast.iter().last(), Some((0xde8db9a9, &())));
+        assert_eq!(xfast.iter().nth(1), Some((0x6eaaa008, &())));
+
+        let mut iter = xfast.iter();
+        iter.nth(4);
+        assert_eq!(iter.last(), None);
+    }
+
+    #[test]
+    fn test_xfast_remove_last_key_under_byte() {
+        let mut xfast = XFastMap::new();
+        xfast.insert(8u32, ());
+        xfast.insert(0, ());
+        assert_eq!(xfast.remove(8), Some(()));
+
+        assert_eq!(xfast.successor(8), None);
+        assert_eq!(xfast.predecessor(8), Some((0, &())));
+        xfast.insert(4, ());
+        assert_eq!(xfast.iter().map(|(k, _)| k).collect::<Vec<_>>(), [0, 4]);
+    }
+
+    #[test]
+    fn test_xfast_remove_range() {
+        let ranges: [(Bound<u32>, Bound<u32>); 6] = [
+            (Bound::Included(100), Bound::Excluded(2000)),
+            (Bound::Unbounded, Bound::Included(500)),
+            (Bound::Excluded(7000), Bound::Unbounded),
+            (Bound::Excluded(3003), Bound::Included(3010)),
+            (Bound::Included(50), Bound::Included(10)),
+            (Bound::Unbounded, Bound::Unbounded),
+        ];
+        let mut xfast = XFastMap::new();
+        let mut expected = std::collections::BTreeMap::new();
+        for i in 0..5000u32 {
+            xfast.insert(i * 3 / 2, i);
+            expected.insert(i * 3 / 2, i);
+        }
+
+        for range in ranges.iter().cloned() {
+            let removed = if range.0 == Bound::Included(50) {
+                Vec::new()
+            } else {
+                expected.range(range).map(|(&k, _)| k).collect::<Vec<_>>()
+            };
+            for key in removed.iter() {
+                expected.remove(key);
+            }
+
+            assert_eq!(xfast.remove_range(range), removed.len());
+            assert_eq!(xfast.len(), expected.len());
+            assert_eq!(
+                xfast.iter().collect::<Vec<_>>(),
+                expected.iter().map(|(&k, v)| (k, v)).collect::<Vec<_>>()
+            );
+        }
+        assert!(xfast.is_empty());
+    }
+
+    #[test]
+    fn test_xfast_neighbors() {
+        let mut xfast = XFastMap::new();
+        assert_eq!(xfast.successor_of(3), None);
+        for i in 0..50u32 {
+            xfast.insert(2 * i, i);
+        }
+
+        assert_eq!(xfast.successor_of(3), Some(4));
+        assert_eq!(xfast.predecessor_of(3), Some(2));
+        assert_eq!(xfast.successor_of(4), Some(6));
+        assert_eq!(xfast.predecessor_of(4), Some(2));
+        assert_eq!(xfast.predecessor_of(0), None);
+        assert_eq!(xfast.successor_of(98), None);
+        assert_eq!(xfast.successor_of(1000), None);
+        assert_eq!(xfast.predecessor_of(1000), Some(98));
+
+        assert_eq!(xfast.get_with_next(4), Some((&2, Some(6))));
+        assert_eq!(xfast.get_with_next(98), Some((&49, None)));
+        assert_eq!(xfast.get_with_next(3), None);
+    }
+
+    #[test]
+    fn test_xfast_gaps() {
+        let mut xfast = XFastMap::new();
+        assert_eq!(xfast.gaps().collect::<Vec<_>>(), vec![(0, u32::MAX)]);
+
+        for key in (0..=10u32).step_by(2) {
+            xfast.insert(key, ());
+        }
+        assert_eq!(
+            xfast.gaps().collect::<Vec<_>>(),
+            vec![(1, 1), (3, 3), (5, 5), (7, 7), (9, 9), (11, u32::MAX)]
+        );
+
+        xfast.insert(u32::MAX, ());
+        xfast.insert(u32::MAX - 1, ());
+        assert_eq!(xfast.gaps().last(), Some((11, u32::MAX - 2)));
+    }
+
+    #[test]
+    fn test_xfast_level_sizes() {
+        let mut xfast = XFastMap::new();
+        assert_eq!(xfast.level_sizes(), vec![0, 0, 0, 0]);
+
+        for i in 0..5u32 {
+            xfast.insert(0x0102_0000 | (i << 8), ());
+        }
+        assert_eq!(xfast.level_sizes(), vec![1, 1, 1, 5]);
+        xfast.insert(0x0903_0000, ());
+        assert_eq!(xfast.level_sizes(), vec![1, 2, 2, 6]);
+
+        let mut xfast = XFastMap::new();
+        for i in 0..5u64 {
+            xfast.insert(i << 8, ());
+        }
+        assert_eq!(xfast.level_sizes(), vec![1, 1, 1, 1, 1, 1, 1, 5]);
+        xfast.clear();
+        assert_eq!(xfast.level_sizes(), vec![0; 8]);
+    }
 }
@@ -5,5 +5,10 @@ pub use map::XFastMap;
 pub use set::XFastSet;
 
 pub trait LevelSearchable<T>: crate::level_search::LevelSearchable<T> {}
+impl<T> LevelSearchable<T> for u16 {}
 impl<T> LevelSearchable<T> for u32 {}
 impl<T> LevelSearchable<T> for u64 {}
+impl<T> LevelSearchable<T> for [u8; 2] {}
+impl<T> LevelSearchable<T> for [u8; 4] {}
+impl<T> LevelSearchable<T> for [u8; 8] {}
+impl<T, const BITS: usize> LevelSearchable<T> for crate::UInt<BITS> {}
@@ -51,6 +51,11 @@ impl<K: LevelSearchable<()>> XFastSet<K> {
         self.map.iter().map(|k| k.0)
     }
 
+    /// Iterate over the inclusive ranges of keys missing from the set
+    pub fn gaps(&self) -> impl Iterator<Item = (K, K)> + '_ {
+        self.map.gaps()
+    }
+
     pub fn range<'a>(
         &'a self,
         range: impl RangeBounds<K> + 'a,
@@ -0,0 +1,262 @@
+/// The number of keys in each `BTreeRange`, in list order, kept in a treap
+/// so that the number of keys before a range can be found in O(log n)
+///
+/// Each range holds the slot of its count, which stays fixed while other
+/// ranges are added and removed around it.
+#[derive(Debug)]
+pub(super) struct RangeCounts {
+    nodes: Vec<Node>,
+    // Slots of removed nodes, to be reused
+    free: Vec<usize>,
+    root: usize,
+    // Advanced to draw each node's priority
+    seed: u64,
+}
+
+/// The slot of a missing node
+pub(super) const NIL: usize = usize::MAX;
+
+#[derive(Debug)]
+struct Node {
+    parent: usize,
+    left: usize,
+    right: usize,
+    priority: u64,
+    count: usize,
+    // The total count of this node's subtree
+    sum: usize,
+}
+
+impl Default for RangeCounts {
+    fn default() -> RangeCounts {
+        RangeCounts::new()
+    }
+}
+
+impl RangeCounts {
+    pub(super) fn new() -> RangeCounts {
+        RangeCounts {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NIL,
+            seed: 0,
+        }
+    }
+
+    pub(super) fn clear(&mut self) {
+        *self = RangeCounts::new();
+    }
+
+    fn sum(&self, slot: usize) -> usize {
+        if slot == NIL {
+            0
+        } else {
+            self.nodes[slot].sum
+        }
+    }
+
+    /// Add a range right after the one at `prev` (or first, for `None`),
+    /// returning its slot
+    pub(super) fn insert_after(
+        &mut self,
+        prev: Option<usize>,
+        count: usize,
+    ) -> usize {
+        // splitmix64, so priorities are well mixed without a random source
+        self.seed = self.seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        let node = Node {
+            parent: NIL,
+            left: NIL,
+            right: NIL,
+            priority: z ^ (z >> 31),
+            count,
+            sum: count,
+        };
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = node;
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+
+        // The new node goes leftmost in the subtree after `prev`
+        let (mut parent, left) = match prev {
+            Some(prev) if self.nodes[prev].right == NIL => (prev, false),
+            Some(prev) => (self.nodes[prev].right, true),
+            None => (self.root, true),
+        };
+        if parent == NIL {
+            self.root = slot;
+            return slot;
+        }
+        if left {
+            while self.nodes[parent].left != NIL {
+                parent = self.nodes[parent].left;
+            }
+            self.nodes[parent].left = slot;
+        } else {
+            self.nodes[parent].right = slot;
+        }
+        self.nodes[slot].parent = parent;
+        self.add_to_ancestors(slot, count, 0);
+
+        while parent != NIL
+            && self.nodes[parent].priority < self.nodes[slot].priority
+        {
+            self.rotate_up(slot);
+            parent = self.nodes[slot].parent;
+        }
+        slot
+    }
+
+    /// Remove the range at `slot`
+    pub(super) fn remove(&mut self, slot: usize) {
+        // Rotate the node down to a leaf, keeping the heap order
+        loop {
+            let Node { left, right, .. } = self.nodes[slot];
+            let child = match (left, right) {
+                (NIL, NIL) => break,
+                (child, NIL) | (NIL, child) => child,
+                _ if self.nodes[left].priority > self.nodes[right].priority => {
+                    left
+                }
+                _ => right,
+            };
+            self.rotate_up(child);
+        }
+
+        let Node { parent, count, .. } = self.nodes[slot];
+        self.add_to_ancestors(slot, 0, count);
+        if parent == NIL {
+            self.root = NIL;
+        } else if self.nodes[parent].left == slot {
+            self.nodes[parent].left = NIL;
+        } else {
+            self.nodes[parent].right = NIL;
+        }
+        self.free.push(slot);
+    }
+
+    /// Set the number of keys in the range at `slot`
+    pub(super) fn set(&mut self, slot: usize, count: usize) {
+        let old = std::mem::replace(&mut self.nodes[slot].count, count);
+        self.nodes[slot].sum = self.nodes[slot].sum + count - old;
+        self.add_to_ancestors(slot, count, old);
+    }
+
+    /// The total count of the ranges before the one at `slot`
+    pub(super) fn rank(&self, slot: usize) -> usize {
+        let mut rank = self.sum(self.nodes[slot].left);
+        let mut node = slot;
+        let mut parent = self.nodes[node].parent;
+        while parent != NIL {
+            if self.nodes[parent].right == node {
+                rank += self.sum(self.nodes[parent].left);
+                rank += self.nodes[parent].count;
+            }
+            node = parent;
+            parent = self.nodes[node].parent;
+        }
+        rank
+    }
+
+    /// Replace `old` with `new` in the sums above `slot`
+    fn add_to_ancestors(&mut self, slot: usize, new: usize, old: usize) {
+        let mut parent = self.nodes[slot].parent;
+        while parent != NIL {
+            let node = &mut self.nodes[parent];
+            node.sum = node.sum + new - old;
+            parent = node.parent;
+        }
+    }
+
+    /// Rotate `slot` above its parent, keeping the in-order sequence
+    fn rotate_up(&mut self, slot: usize) {
+        let parent = self.nodes[slot].parent;
+        let grandparent = self.nodes[parent].parent;
+        if self.nodes[parent].left == slot {
+            let moved = self.nodes[slot].right;
+            self.nodes[parent].left = moved;
+            self.nodes[slot].right = parent;
+            if moved != NIL {
+                self.nodes[moved].parent = parent;
+            }
+        } else {
+            let moved = self.nodes[slot].left;
+            self.nodes[parent].right = moved;
+            self.nodes[slot].left = parent;
+            if moved != NIL {
+                self.nodes[moved].parent = parent;
+            }
+        }
+
+        self.nodes[parent].parent = slot;
+        self.nodes[slot].parent = grandparent;
+        if grandparent == NIL {
+            self.root = slot;
+        } else if self.nodes[grandparent].left == parent {
+            self.nodes[grandparent].left = slot;
+        } else {
+            self.nodes[grandparent].right = slot;
+        }
+
+        for node in [parent, slot] {
+            let Node {
+                left, right, count, ..
+            } = self.nodes[node];
+            self.nodes[node].sum = count + self.sum(left) + self.sum(right);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_range_counts() {
+        // (slot, count) in list order
+        let mut reference: Vec<(usize, usize)> = Vec::new();
+        let mut counts = RangeCounts::new();
+        let mut rng = SmallRng::from_seed([5; 16]);
+        for _ in 0..3000 {
+            let r: usize = rng.gen();
+            match r % 4 {
+                0 if !reference.is_empty() => {
+                    let (slot, _) = reference.remove(r % reference.len());
+                    counts.remove(slot);
+                }
+                1 if !reference.is_empty() => {
+                    let index = r % reference.len();
+                    reference[index].1 = r % 100;
+                    counts.set(reference[index].0, r % 100);
+                }
+                _ => {
+                    let index = r % (reference.len() + 1);
+                    let prev = index.checked_sub(1).map(|i| reference[i].0);
+                    let slot = counts.insert_after(prev, r % 50);
+                    reference.insert(index, (slot, r % 50));
+                }
+            }
+
+            let mut rank = 0;
+            for &(slot, count) in reference.iter() {
+                assert_eq!(counts.rank(slot), rank);
+                rank += count;
+            }
+        }
+
+        counts.clear();
+        let first = counts.insert_after(None, 3);
+        let second = counts.insert_after(None, 4);
+        assert_eq!((counts.rank(second), counts.rank(first)), (0, 4));
+    }
+}
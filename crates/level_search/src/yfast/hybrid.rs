@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use super::{BTreeRange, LevelSearchable, YFastMap};
+use crate::xfast::{self, XFastMap};
+
+/// The number of keys past which a `HybridMap::new` becomes a `YFastMap`
+const DEFAULT_THRESHOLD: usize = 1024;
+
+enum Inner<K, V>
+where
+    K: xfast::LevelSearchable<V> + LevelSearchable<BTreeRange<K, V>>,
+{
+    XFast(XFastMap<K, V>),
+    YFast(YFastMap<K, V>),
+}
+
+/// An ordered map that starts as an `XFastMap` and rebuilds itself as a
+/// `YFastMap` once it grows past a threshold
+///
+/// Small maps get the faster x-fast lookups, while large maps avoid its
+/// per-key hash map entries at every level. The map never converts back,
+/// even if removals shrink it below the threshold.
+pub struct HybridMap<K, V>
+where
+    K: xfast::LevelSearchable<V> + LevelSearchable<BTreeRange<K, V>>,
+{
+    inner: Inner<K, V>,
+    threshold: usize,
+}
+
+impl<K, V> HybridMap<K, V>
+where
+    K: xfast::LevelSearchable<V> + LevelSearchable<BTreeRange<K, V>>,
+{
+    pub fn new() -> HybridMap<K, V> {
+        HybridMap::with_threshold(DEFAULT_THRESHOLD)
+    }
+
+    /// Create a map that converts once it holds more than `threshold` keys
+    pub fn with_threshold(threshold: usize) -> HybridMap<K, V> {
+        HybridMap {
+            inner: Inner::XFast(XFastMap::new()),
+            threshold,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.inner {
+            Inner::XFast(map) => map.len(),
+            Inner::YFast(map) => map.len(),
+        }
+    }
+
+    /// Return whether the map has been rebuilt as a `YFastMap`
+    pub fn is_yfast(&self) -> bool {
+        matches!(self.inner, Inner::YFast(_))
+    }
+
+    pub fn get(&self, key: K) -> Option<&V> {
+        match &self.inner {
+            Inner::XFast(map) => map.get(key),
+            Inner::YFast(map) => map.get(key),
+        }
+    }
+
+    pub fn contains_key(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Insert a key into the map, returning the value it replaced
+    ///
+    /// This rebuilds the map as a `YFastMap` if it grows past the threshold.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = match &mut self.inner {
+            Inner::XFast(map) => map.insert(key, value),
+            Inner::YFast(map) => return map.insert(key, value),
+        };
+        if self.len() > self.threshold {
+            let empty = Inner::YFast(YFastMap::new());
+            if let Inner::XFast(map) = std::mem::replace(&mut self.inner, empty)
+            {
+                let yfast = BTreeMap::from(map).into_iter().collect();
+                self.inner = Inner::YFast(yfast);
+            }
+        }
+        old
+    }
+
+    /// Remove a key from the map, returning its value
+    ///
+    /// A map that has been rebuilt as a `YFastMap` stays one, even if this
+    /// shrinks it below the threshold.
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        match &mut self.inner {
+            Inner::XFast(map) => map.remove(key),
+            Inner::YFast(map) => map.remove(key),
+        }
+    }
+
+    pub fn predecessor(&self, key: K) -> Option<(K, &V)> {
+        match &self.inner {
+            Inner::XFast(map) => map.predecessor(key),
+            Inner::YFast(map) => map.predecessor(key),
+        }
+    }
+
+    pub fn successor(&self, key: K) -> Option<(K, &V)> {
+        match &self.inner {
+            Inner::XFast(map) => map.successor(key),
+            Inner::YFast(map) => map.successor(key),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (K, &V)> {
+        let (xfast, yfast) = match &self.inner {
+            Inner::XFast(map) => (Some(map.iter()), None),
+            Inner::YFast(map) => (None, Some(map.iter())),
+        };
+        xfast
+            .into_iter()
+            .flatten()
+            .chain(yfast.into_iter().flatten())
+    }
+}
+
+impl<K, V> Default for HybridMap<K, V>
+where
+    K: xfast::LevelSearchable<V> + LevelSearchable<BTreeRange<K, V>>,
+{
+    fn default() -> HybridMap<K, V> {
+        HybridMap::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_hybrid_conversion() {
+        let mut hybrid = HybridMap::with_threshold(200);
+        let mut reference = BTreeMap::new();
+        let mut rng = SmallRng::from_seed([5; 16]);
+        for i in 0..400u32 {
+            let key = rng.gen_range(0, 1 << 20);
+            assert_eq!(hybrid.insert(key, i), reference.insert(key, i));
+            assert_eq!(hybrid.is_yfast(), reference.len() > 200);
+            assert_eq!(hybrid.len(), reference.len());
+
+            for probe in (0..1 << 20).step_by(4999).chain(Some(key)) {
+                let pred = reference.range(..=probe).next_back();
+                let succ = reference.range(probe..).next();
+                let pred = pred.map(|(&k, v)| (k, v));
+                let succ = succ.map(|(&k, v)| (k, v));
+                assert_eq!(hybrid.predecessor(probe), pred);
+                assert_eq!(hybrid.successor(probe), succ);
+            }
+        }
+
+        assert!(hybrid.iter().eq(reference.iter().map(|(&k, v)| (k, v))));
+        assert_eq!(hybrid.insert(u32::MAX, 0), None);
+        assert_eq!(hybrid.insert(u32::MAX, 1), Some(0));
+        assert_eq!(hybrid.get(u32::MAX), Some(&1));
+    }
+
+    #[test]
+    fn test_hybrid_remove() {
+        let mut small = HybridMap::new();
+        small.insert(3u32, 'a');
+        assert_eq!(small.remove(3), Some('a'));
+        assert_eq!(small.remove(3), None);
+        assert!(small.is_empty() && !small.is_yfast());
+
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let mut hybrid = HybridMap::with_threshold(100);
+        let mut reference = BTreeMap::new();
+        for i in 0..300u32 {
+            let key = rng.gen_range(0, 1u32 << 20);
+            hybrid.insert(key, i);
+            reference.insert(key, i);
+        }
+        assert!(hybrid.is_yfast());
+
+        // Shrinking below the threshold does not convert back
+        let keys = reference.keys().cloned().collect::<Vec<_>>();
+        for key in keys {
+            assert_eq!(hybrid.remove(key), reference.remove(&key));
+            assert_eq!(hybrid.len(), reference.len());
+            assert!(hybrid.is_yfast());
+        }
+        assert!(hybrid.is_empty());
+    }
+}
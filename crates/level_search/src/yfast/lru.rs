@@ -0,0 +1,144 @@
+use super::{BTreeRange, LevelSearchable, YFastMap};
+
+/// A capacity-bounded least-recently-used cache with ordered keys
+///
+/// Each entry is stamped with the tick of its last access, and a second
+/// `YFastMap` from tick to key finds the least recently used entry.
+pub struct LruYFast<K, V>
+where
+    K: LevelSearchable<BTreeRange<K, (V, u64)>>,
+{
+    map: YFastMap<K, (V, u64)>,
+    ticks: YFastMap<u64, K>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl<K, V> LruYFast<K, V>
+where
+    K: LevelSearchable<BTreeRange<K, (V, u64)>>,
+{
+    pub fn new(capacity: usize) -> LruYFast<K, V> {
+        assert!(capacity > 0);
+        LruYFast {
+            map: YFastMap::new(),
+            ticks: YFastMap::new(),
+            tick: 0,
+            capacity,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn contains_key(&self, key: K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Return the value for `key` without marking it as used
+    pub fn peek(&self, key: K) -> Option<&V> {
+        self.map.get(key).map(|(value, _)| value)
+    }
+
+    /// Return the value for `key`, marking it as the most recently used
+    pub fn get(&mut self, key: K) -> Option<&V> {
+        let (value, tick) = self.map.remove(key)?;
+        self.ticks.remove(tick);
+        self.touch(key, value);
+        self.peek(key)
+    }
+
+    /// Insert a key as the most recently used entry, returning the least
+    /// recently used entry if it was evicted to make room
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if let Some((_, tick)) = self.map.remove(key) {
+            self.ticks.remove(tick);
+        }
+        self.touch(key, value);
+
+        if self.len() > self.capacity {
+            self.pop_lru()
+        } else {
+            None
+        }
+    }
+
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let (value, tick) = self.map.remove(key)?;
+        self.ticks.remove(tick);
+        Some(value)
+    }
+
+    /// Remove and return the least recently used entry
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let (tick, key) = self.ticks.iter().next().map(|(t, &k)| (t, k))?;
+        self.ticks.remove(tick);
+        self.map.remove(key).map(|(value, _)| (key, value))
+    }
+
+    /// Iterate over the entries in key order, without marking them as used
+    pub fn iter(&self) -> impl Iterator<Item = (K, &V)> {
+        self.map.iter().map(|(key, (value, _))| (key, value))
+    }
+
+    fn touch(&mut self, key: K, value: V) {
+        self.tick += 1;
+        self.map.insert(key, (value, self.tick));
+        self.ticks.insert(self.tick, key);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction_order() {
+        let mut lru = LruYFast::new(3);
+        assert_eq!(lru.insert(30u32, "a"), None);
+        assert_eq!(lru.insert(10, "b"), None);
+        assert_eq!(lru.insert(20, "c"), None);
+
+        // 30 is the least recently used, until we touch it
+        assert_eq!(lru.get(30), Some(&"a"));
+        assert_eq!(lru.insert(40, "d"), Some((10, "b")));
+        assert_eq!(lru.peek(20), Some(&"c"));
+        assert_eq!(lru.insert(50, "e"), Some((20, "c")));
+
+        // Overwriting counts as a use and never evicts
+        assert_eq!(lru.insert(30, "f"), None);
+        assert_eq!(lru.pop_lru(), Some((40, "d")));
+        assert_eq!(lru.pop_lru(), Some((50, "e")));
+        assert_eq!(lru.pop_lru(), Some((30, "f")));
+        assert_eq!(lru.pop_lru(), None);
+    }
+
+    #[test]
+    fn test_lru_capacity() {
+        let mut lru = LruYFast::new(100);
+        for i in 0..1000u64 {
+            let evicted = lru.insert(i * 7 % 1000, i);
+            assert_eq!(lru.len(), (i as usize + 1).min(100));
+            if i >= 100 {
+                let key = (i - 100) * 7 % 1000;
+                assert_eq!(evicted, Some((key, i - 100)));
+            }
+        }
+
+        let mut keys = (900..1000).map(|i| i * 7 % 1000).collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(lru.iter().map(|(k, _)| k).collect::<Vec<_>>(), keys);
+        assert_eq!(lru.remove(keys[0]), Some(keys[0] * 143 % 1000));
+        assert!(!lru.contains_key(keys[0]));
+        assert_eq!(lru.len(), 99);
+    }
+}
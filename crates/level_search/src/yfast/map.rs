@@ -1,17 +1,117 @@
+use std::cmp::Ordering;
 use std::collections::{
-    btree_map::Iter as BTreeIter, hash_map::Entry as HashEntry,
+    btree_map::Iter as BTreeIter, hash_map::Entry as HashEntry, BTreeMap,
 };
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::iter::FromIterator;
+use std::ops::{Bound, RangeBounds};
+use std::ptr;
 
+use bytemuck::Pod;
 use fnv::FnvHashMap as HashMap;
 
+use super::counts::RangeCounts;
 use super::{BTreeRange, LevelSearchable, LinkedBTree};
-use crate::level_search::LNode;
+use crate::level_search::{self, LNode, LNodeRef};
+
+/// The error returned by `try_insert` when the key already exists
+#[derive(Debug, Eq, PartialEq)]
+pub struct OccupiedError<T> {
+    /// The value that was not inserted
+    pub value: T,
+}
+
+impl<T> fmt::Display for OccupiedError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "key already exists")
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for OccupiedError<T> {}
 
 #[derive(Default, Debug)]
 pub struct YFastMap<K: LevelSearchable<BTreeRange<K, V>>, V> {
     lss: K::LSS,
     map: HashMap<K, Box<LinkedBTree<K, V>>>,
     len: usize,
+    // Bumped by every insert and remove, to invalidate `DescentToken`s
+    generation: u64,
+    // The number of keys in each `BTreeRange`, for ranked queries
+    counts: RangeCounts,
+}
+
+/// The result of `YFastMap::descend`, which lets a following
+/// `insert_with_token` or `remove_with_token` of the same key skip the
+/// level search
+///
+/// A token is only reused if the map has not been modified since it was
+/// created, and its key still falls in the `BTreeRange` it found; otherwise
+/// the key is searched for again.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DescentToken<K> {
+    key: K,
+    // The key indexing the `BTreeRange` that holds (or would hold) `key`
+    bucket: Option<K>,
+    generation: u64,
+}
+
+impl<K: Copy> DescentToken<K> {
+    /// The key that was searched for
+    pub fn key(&self) -> K {
+        self.key
+    }
+}
+
+/// A cursor over the entries of a `YFastMap`, in increasing key order, that
+/// can change or remove the entry it points at
+///
+/// Returned by `YFastMap::cursor_mut_from`.
+pub struct CursorMut<'a, K: LevelSearchable<BTreeRange<K, V>>, V> {
+    map: &'a mut YFastMap<K, V>,
+    // The current key and the `BTreeRange` holding it, or `None` once the
+    // cursor has moved past the largest key
+    current: Option<(K, ptr::NonNull<LinkedBTree<K, V>>)>,
+}
+
+impl<'a, K: LevelSearchable<BTreeRange<K, V>>, V> CursorMut<'a, K, V> {
+    /// The key the cursor points at
+    pub fn key(&self) -> Option<K> {
+        self.current.map(|(key, _)| key)
+    }
+
+    /// A mutable reference to the value the cursor points at
+    pub fn value_mut(&mut self) -> Option<&mut V> {
+        let (key, mut bucket) = self.current?;
+        unsafe { bucket.as_mut() }.value.get_mut(key)
+    }
+
+    /// Move to the next key, returning it
+    pub fn move_next(&mut self) -> Option<K> {
+        let (key, bucket) = self.current?;
+        let node = unsafe { bucket.as_ref() };
+        self.current = match node.value.next_key(key) {
+            Some(next) => Some((next, bucket)),
+            None => unsafe { node.next.as_mut() }.map(|next| {
+                let min = next.value.keys().next();
+                let min = *min.expect("ranges in the map are not empty");
+                (min, ptr::NonNull::from(next))
+            }),
+        };
+        self.key()
+    }
+
+    /// Remove the entry the cursor points at, returning its value, and move
+    /// to the next key
+    pub fn remove_current(&mut self) -> Option<V> {
+        let (key, bucket) = self.current?;
+        let next = YFastMap::next_key(unsafe { bucket.as_ref() }, key);
+        let value = self.map.remove_from(Some(bucket), key);
+        // Removing can merge or re-index `BTreeRange`s, so search again
+        self.current = next.and_then(|next| self.map.locate(next));
+        value
+    }
 }
 
 impl<K: LevelSearchable<BTreeRange<K, V>>, V> YFastMap<K, V> {
@@ -20,6 +120,8 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> YFastMap<K, V> {
             lss: K::lss_new(),
             map: HashMap::default(),
             len: 0,
+            generation: 0,
+            counts: RangeCounts::new(),
         }
     }
 
@@ -35,57 +137,169 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> YFastMap<K, V> {
     pub fn clear(&mut self) {
         K::lss_clear(&mut self.lss);
         self.map.clear();
+        self.len = 0;
+        self.counts.clear();
+        self.generation = self.generation.wrapping_add(1);
     }
 
     pub fn get(&self, key: K) -> Option<&V> {
+        self.node_of(key)?.value.get(key)
+    }
+
+    /// Return mutable references to the values of `N` different keys, or
+    /// `None` if any key is missing or appears twice
+    pub fn get_many_mut<const N: usize>(
+        &mut self,
+        keys: [K; N],
+    ) -> Option<[&mut V; N]> {
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].contains(key) {
+                return None;
+            }
+        }
+
+        let mut values = [ptr::null_mut::<V>(); N];
+        for (value, &key) in values.iter_mut().zip(keys.iter()) {
+            let mut bucket = self.insert_bucket(key)?;
+            *value = unsafe { bucket.as_mut() }.value.get_mut(key)?;
+        }
+        // Different keys are different entries (of the same or different
+        // `BTreeRange`s), so the references do not alias
+        Some(values.map(|value| unsafe { &mut *value }))
+    }
+
+    /// Return the value for `key`, along with the next key in the map
+    pub fn get_with_next(&self, key: K) -> Option<(&V, Option<K>)> {
+        let node = self.node_of(key)?;
+        let value = node.value.get(key)?;
+        Some((value, Self::next_key(node, key)))
+    }
+
+    /// Return the smallest key strictly greater than `key`
+    pub fn successor_of(&self, key: K) -> Option<K> {
+        match self.node_of(key) {
+            Some(node) => Self::next_key(node, key),
+            None => self.successor(key).map(|(k, _)| k),
+        }
+    }
+
+    /// Return the largest key strictly less than `key`
+    pub fn predecessor_of(&self, key: K) -> Option<K> {
+        match self.node_of(key) {
+            Some(node) => node.value.prev_key(key).or_else(|| {
+                LNodeRef::new(node)
+                    .prev()
+                    .and_then(|prev| prev.value().keys().next_back().cloned())
+            }),
+            None => self.predecessor(key).map(|(k, _)| k),
+        }
+    }
+
+    /// Find the `BTreeRange` containing `key`, if `key` is in the map
+    fn node_of(&self, key: K) -> Option<&LinkedBTree<K, V>> {
         let (byte, desc) = K::lss_longest_descendant(&self.lss, key);
-        if let Some(pred) = desc.predecessor(byte) {
-            pred.value.get(key).or_else(|| {
-                unsafe { pred.next.as_ref() }
-                    .and_then(|next| next.value.get(key))
-            })
-        } else if let Some(succ) = desc.successor(byte) {
-            succ.value.get(key).or_else(|| {
-                unsafe { succ.prev.as_ref() }
-                    .and_then(|prev| prev.value.get(key))
-            })
+        let (node, other) = if let Some(pred) = desc.predecessor(byte) {
+            (pred, unsafe { pred.next.as_ref() })
         } else {
-            None
+            let succ = desc.successor(byte)?;
+            (succ, unsafe { succ.prev.as_ref() })
+        };
+
+        if node.value.get(key).is_some() {
+            Some(node)
+        } else {
+            other.filter(|other| other.value.get(key).is_some())
         }
     }
 
+    fn next_key(node: &LinkedBTree<K, V>, key: K) -> Option<K> {
+        node.value.next_key(key).or_else(|| {
+            LNodeRef::new(node)
+                .next()
+                .and_then(|next| next.value().keys().next().cloned())
+        })
+    }
+
+    /// Return the entry with the largest key less than or equal to `key`
     pub fn predecessor(&self, key: K) -> Option<(K, &V)> {
         let (byte, desc) = K::lss_longest_descendant(&self.lss, key);
         if let Some(pred) = desc.predecessor(byte) {
-            unsafe { pred.next.as_ref() }
-                .and_then(|next| next.value.predecessor(key))
+            LNodeRef::new(pred)
+                .next()
+                .and_then(|next| next.value().predecessor(key))
                 .or_else(|| pred.value.predecessor(key))
         } else if let Some(succ) = desc.successor(byte) {
             succ.value.predecessor(key).or_else(|| {
-                unsafe { succ.prev.as_ref() }
-                    .and_then(|prev| prev.value.predecessor(key))
+                LNodeRef::new(succ)
+                    .prev()
+                    .and_then(|prev| prev.value().predecessor(key))
             })
         } else {
             None
         }
     }
 
+    /// Return the entry with the smallest key greater than or equal to `key`
     pub fn successor(&self, key: K) -> Option<(K, &V)> {
         let (byte, desc) = K::lss_longest_descendant(&self.lss, key);
         if let Some(pred) = desc.predecessor(byte) {
             pred.value.successor(key).or_else(|| {
-                unsafe { pred.next.as_ref() }
-                    .and_then(|next| next.value.successor(key))
+                LNodeRef::new(pred)
+                    .next()
+                    .and_then(|next| next.value().successor(key))
             })
         } else if let Some(succ) = desc.successor(byte) {
-            unsafe { succ.prev.as_ref() }
-                .and_then(|prev| prev.value.successor(key))
+            LNodeRef::new(succ)
+                .prev()
+                .and_then(|prev| prev.value().successor(key))
                 .or_else(|| succ.value.successor(key))
         } else {
             None
         }
     }
 
+    /// Like `predecessor`, but also return the number of smaller keys
+    pub fn predecessor_with_rank(&self, key: K) -> Option<(usize, K, &V)> {
+        let (pred, value) = self.predecessor(key)?;
+        Some((self.rank_of(pred), pred, value))
+    }
+
+    /// Like `successor`, but also return the number of smaller keys
+    ///
+    /// The number of keys before each `BTreeRange` is kept up to date by
+    /// inserts and removes, so this costs O(log n) more than `successor`.
+    pub fn successor_with_rank(&self, key: K) -> Option<(usize, K, &V)> {
+        let (succ, value) = self.successor(key)?;
+        Some((self.rank_of(succ), succ, value))
+    }
+
+    /// The number of keys less than `key`, which must be in the map
+    fn rank_of(&self, key: K) -> usize {
+        let node = self.node_of(key).expect("key is in the map");
+        self.counts.rank(node.value.slot) + node.value.count_below(key)
+    }
+
+    /// Return the entry with the largest key strictly less than `key`
+    pub fn predecessor_strict(&self, key: K) -> Option<(K, &V)> {
+        self.predecessor(key.checked_decrement()?)
+    }
+
+    /// Return the entry with the smallest key strictly greater than `key`
+    pub fn successor_strict(&self, key: K) -> Option<(K, &V)> {
+        self.successor(key.checked_increment()?)
+    }
+
+    /// Return how many leading bits `key` shares with its nearest key
+    ///
+    /// This is the bit-length of the longest common prefix between `key`
+    /// and its predecessor or successor, and is 0 for an empty map.
+    pub fn longest_common_prefix_bits(&self, key: K) -> u32 {
+        let prefix = |(other, _)| key.common_prefix_bits(other);
+        let pred = self.predecessor(key).map(prefix);
+        let succ = self.successor(key).map(prefix);
+        pred.max(succ).unwrap_or(0)
+    }
+
     pub fn contains_key(&self, key: K) -> bool {
         let (byte, desc) = K::lss_longest_descendant(&self.lss, key);
         if let Some(pred) = desc.predecessor(byte) {
@@ -110,54 +324,71 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> YFastMap<K, V> {
     }
 
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let bucket = self.insert_bucket(key);
+        self.insert_into(bucket, key, value)
+    }
+
+    /// Find the `BTreeRange` that `key` belongs in, or `None` if the map is
+    /// empty
+    fn insert_bucket(
+        &mut self,
+        key: K,
+    ) -> Option<ptr::NonNull<LinkedBTree<K, V>>> {
         let (byte, desc) = K::lss_longest_descendant_mut(&mut self.lss, key);
 
-        let node_with_successor = if let Some(succ) = desc.successor_mut(byte) {
-            if succ.value.min <= key || succ.prev.is_null() {
-                Some(succ)
-            } else if let Some(prev) = unsafe { succ.prev.as_mut() } {
-                debug_assert!(
-                    prev.value.keys().next_back().unwrap() < &succ.value.min
-                );
-                debug_assert!(prev.key <= key);
-                debug_assert!(succ.key > key);
-                Some(prev)
-            } else {
-                unreachable!();
+        // `key` goes in the range before (or after) the one found, if there
+        // is one and `key` falls short of (or past) the range found
+        let node = if let Some(succ) = desc.successor_mut(byte) {
+            match unsafe { succ.prev.as_mut() } {
+                Some(prev) if key < succ.value.min => {
+                    debug_assert!(prev.value.max < succ.value.min);
+                    debug_assert!(prev.key <= key);
+                    debug_assert!(succ.key > key);
+                    Some(prev)
+                }
+                _ => Some(succ),
             }
         } else if let Some(pred) = desc.predecessor_mut(byte) {
-            if pred.value.max >= key || pred.next.is_null() {
-                Some(pred)
-            } else if let Some(next) = unsafe { pred.next.as_mut() } {
-                debug_assert!(
-                    next.value.keys().next().unwrap() > &pred.value.max
-                );
-                debug_assert!(next.key >= key);
-                debug_assert!(pred.key < key);
-                Some(next)
-            } else {
-                unreachable!();
+            match unsafe { pred.next.as_mut() } {
+                Some(next) if key > pred.value.max => {
+                    debug_assert!(next.value.min > pred.value.max);
+                    debug_assert!(next.key >= key);
+                    debug_assert!(pred.key < key);
+                    Some(next)
+                }
+                _ => Some(pred),
             }
         } else {
             None
         };
+        node.map(ptr::NonNull::from)
+    }
 
-        if let Some(node) = node_with_successor {
+    fn insert_into(
+        &mut self,
+        bucket: Option<ptr::NonNull<LinkedBTree<K, V>>>,
+        key: K,
+        value: V,
+    ) -> Option<V> {
+        self.generation = self.generation.wrapping_add(1);
+        if let Some(mut node) = bucket {
+            let node = unsafe { node.as_mut() };
             let output = node.value.insert(key, value);
             if output.is_none() {
                 self.len += 1;
             }
             if node.is_full() {
                 let new = node.split();
-                self.insert_lss(new);
+                self.insert_new(new);
             }
+            self.counts.set(node.value.slot, node.len());
             return output;
         }
 
         let mut node = Box::new(LNode::new(key, BTreeRange::new(key)));
         node.value.insert(key, value);
         self.len += 1;
-        self.insert_lss(node);
+        self.insert_new(node);
 
         None
     }
@@ -174,62 +405,266 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> YFastMap<K, V> {
         }
     }
 
+    /// Link a new `BTreeRange` into the map, and give it a count after the
+    /// range before it
+    fn insert_new(&mut self, mut node: Box<LinkedBTree<K, V>>) {
+        let node_ptr = ptr::NonNull::from(node.as_mut());
+        self.insert_lss(node);
+        let node = unsafe { &mut *node_ptr.as_ptr() };
+        let prev = unsafe { node.prev.as_ref() }.map(|prev| prev.value.slot);
+        node.value.slot = self.counts.insert_after(prev, node.len());
+    }
+
+    /// Insert a key into the map, unless it is already present
+    pub fn try_insert(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<(), OccupiedError<V>> {
+        if self.contains_key(key) {
+            Err(OccupiedError { value })
+        } else {
+            self.insert(key, value);
+            Ok(())
+        }
+    }
+
+    /// Like `from_iter`, but also return the number of overwritten keys
+    pub fn from_iter_reporting<I>(input: I) -> (YFastMap<K, V>, usize)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut map = YFastMap::new();
+        let mut overwritten = 0;
+        for (key, value) in input.into_iter() {
+            if map.insert(key, value).is_some() {
+                overwritten += 1;
+            }
+        }
+        (map, overwritten)
+    }
+
     pub fn remove(&mut self, key: K) -> Option<V> {
         let (byte, desc) = K::lss_longest_descendant_mut(&mut self.lss, key);
         let node_with_successor = if let Some(succ) = desc.successor_mut(byte) {
-            let min = succ.value.keys().next()?;
-            if *min <= key || succ.prev.is_null() || succ.key == key {
-                Some(succ)
-            } else if let Some(prev) = unsafe { succ.prev.as_mut() } {
-                debug_assert!(prev.value.keys().next_back().unwrap() < min);
-                debug_assert!(prev.key <= key);
-                debug_assert!(succ.key > key);
-                Some(prev)
-            } else {
-                unreachable!();
+            let min = *succ.value.keys().next()?;
+            match unsafe { succ.prev.as_mut() } {
+                Some(prev) if key < min && succ.key != key => {
+                    debug_assert!(prev.value.max < min);
+                    debug_assert!(prev.key <= key);
+                    debug_assert!(succ.key > key);
+                    Some(prev)
+                }
+                _ => Some(succ),
             }
         } else if let Some(pred) = desc.predecessor_mut(byte) {
-            let max = pred.value.keys().next_back()?;
-            if *max >= key || pred.next.is_null() || pred.key == key {
-                Some(pred)
-            } else if let Some(next) = unsafe { pred.next.as_mut() } {
-                debug_assert!(next.value.keys().next().unwrap() > max);
-                debug_assert!(next.key >= key);
-                debug_assert!(pred.key < key);
-                Some(next)
-            } else {
-                unreachable!();
+            let max = *pred.value.keys().next_back()?;
+            match unsafe { pred.next.as_mut() } {
+                Some(next) if key > max && pred.key != key => {
+                    debug_assert!(next.value.min > max);
+                    debug_assert!(next.key >= key);
+                    debug_assert!(pred.key < key);
+                    Some(next)
+                }
+                _ => Some(pred),
             }
         } else {
             None
         };
+        let bucket = node_with_successor.map(ptr::NonNull::from);
+        self.remove_from(bucket, key)
+    }
 
+    fn remove_from(
+        &mut self,
+        bucket: Option<ptr::NonNull<LinkedBTree<K, V>>>,
+        key: K,
+    ) -> Option<V> {
+        self.generation = self.generation.wrapping_add(1);
         let mut output = None;
         let mut to_remove = None;
-        if let Some(node) = node_with_successor {
+        let mut to_rekey = None;
+        if let Some(mut node) = bucket {
+            let node = unsafe { node.as_mut() };
             output = node.value.remove(key, node.key);
             if output.is_some() {
                 self.len -= 1;
             }
+            self.counts.set(node.value.slot, node.len());
             if node.is_small() {
                 to_remove = Some(node.key);
                 let other = node.remove();
                 if other.is_small() {
                     let new = other.split();
-                    self.insert_lss(new);
+                    self.insert_new(new);
                 }
+                self.counts.set(other.value.slot, other.len());
+            } else if node.value.keys().next().is_none() {
+                // We just removed the last key in the map
+                to_remove = Some(node.key);
+            } else if node.key == key {
+                to_rekey = Some(key);
             }
         }
 
         if let Some(key) = to_remove {
             self.remove_lss(key);
         }
+        if let Some(key) = to_rekey {
+            self.rekey_lss(key);
+        }
 
         output
     }
 
+    /// Look up `key`, returning a mutable reference to its value along with
+    /// a token that lets a following insert or remove of `key` skip the
+    /// level search
+    pub fn descend(&mut self, key: K) -> (Option<&mut V>, DescentToken<K>) {
+        let bucket = self.insert_bucket(key);
+        let token = DescentToken {
+            key,
+            bucket: bucket.map(|node| unsafe { node.as_ref() }.key),
+            generation: self.generation,
+        };
+        let value = bucket
+            .and_then(|mut node| unsafe { node.as_mut() }.value.get_mut(key));
+        (value, token)
+    }
+
+    /// Like `insert`, reusing the search from `descend`
+    pub fn insert_with_token(
+        &mut self,
+        token: DescentToken<K>,
+        value: V,
+    ) -> Option<V> {
+        let bucket = self.token_bucket(token);
+        self.insert_into(bucket, token.key, value)
+    }
+
+    /// Like `remove`, reusing the search from `descend`
+    pub fn remove_with_token(&mut self, token: DescentToken<K>) -> Option<V> {
+        let bucket = self.token_bucket(token);
+        self.remove_from(bucket, token.key)
+    }
+
+    /// Return a cursor pointing at the smallest key greater than or equal
+    /// to `key`
+    pub fn cursor_mut_from(&mut self, key: K) -> CursorMut<'_, K, V> {
+        let current = self.locate(key);
+        CursorMut { map: self, current }
+    }
+
+    /// Find the smallest key greater than or equal to `key`, along with the
+    /// `BTreeRange` holding it
+    fn locate(
+        &mut self,
+        key: K,
+    ) -> Option<(K, ptr::NonNull<LinkedBTree<K, V>>)> {
+        let bucket = self.insert_bucket(key)?;
+        let node = unsafe { bucket.as_ref() };
+        match node.value.successor(key) {
+            Some((succ, _)) => Some((succ, bucket)),
+            None => unsafe { node.next.as_mut() }.map(|next| {
+                let min = next.value.keys().next();
+                let min = *min.expect("ranges in the map are not empty");
+                (min, ptr::NonNull::from(next))
+            }),
+        }
+    }
+
+    /// The `BTreeRange` for the token's key, searching again if the map
+    /// changed since the token was created
+    fn token_bucket(
+        &mut self,
+        token: DescentToken<K>,
+    ) -> Option<ptr::NonNull<LinkedBTree<K, V>>> {
+        if token.generation != self.generation {
+            return self.insert_bucket(token.key);
+        }
+        match token.bucket {
+            // A token from a different map can name one of our ranges, so
+            // only trust it if the key falls in that range or the gaps
+            // next to it
+            Some(bucket) => match self.map.get_mut(&bucket) {
+                Some(node) if Self::borders(node, token.key) => {
+                    Some(ptr::NonNull::from(node.as_mut()))
+                }
+                _ => self.insert_bucket(token.key),
+            },
+            None if self.map.is_empty() => None,
+            None => self.insert_bucket(token.key),
+        }
+    }
+
+    /// Whether `key` lies after the range before `node` and before the
+    /// range after it
+    fn borders(node: &LinkedBTree<K, V>, key: K) -> bool {
+        let node = LNodeRef::new(node);
+        node.prev().is_none_or(|prev| prev.value().max < key)
+            && node.next().is_none_or(|next| key < next.value().min)
+    }
+
+    /// Remove every key in `range`, returning how many were removed
+    ///
+    /// This walks the `BTreeRange`s from the smallest key to the end of the
+    /// range (skipping those that end before it starts), and then removes
+    /// each key individually, so it costs O(n / log U) plus one `remove`
+    /// per removed key.
+    pub fn remove_range(&mut self, range: impl RangeBounds<K>) -> usize {
+        let mut keys = Vec::new();
+        let mut node = K::lss_min(&self.lss);
+        while let Some(btree) = node {
+            let past_end = match range.end_bound() {
+                Bound::Included(end) => btree.value.min > *end,
+                Bound::Excluded(end) => btree.value.min >= *end,
+                Bound::Unbounded => false,
+            };
+            if past_end {
+                break;
+            }
+            let before_start = match range.start_bound() {
+                Bound::Included(start) => btree.value.max < *start,
+                Bound::Excluded(start) => btree.value.max <= *start,
+                Bound::Unbounded => false,
+            };
+            if !before_start {
+                keys.extend(
+                    btree.value.keys().cloned().filter(|k| range.contains(k)),
+                );
+            }
+            node = unsafe { btree.next.as_ref() };
+        }
+
+        for key in keys.iter() {
+            self.remove(*key);
+        }
+        keys.len()
+    }
+
+    /// Re-insert the node for `key` under its smallest key, so that the
+    /// key it is indexed by stays within its range
+    fn rekey_lss(&mut self, key: K) {
+        if let Some(mut node) = self.map.remove(&key) {
+            K::lss_remove(&mut self.lss, &node);
+            unsafe {
+                if let Some(prev) = node.prev.as_mut() {
+                    prev.next = node.next;
+                }
+                if let Some(next) = node.next.as_mut() {
+                    next.prev = node.prev;
+                }
+            }
+            node.prev = ptr::null_mut();
+            node.next = ptr::null_mut();
+            node.key = node.value.min;
+            self.insert_lss(node);
+        }
+    }
+
     fn remove_lss(&mut self, key: K) {
         if let Some(node) = self.map.remove(&key) {
+            self.counts.remove(node.value.slot);
             K::lss_remove(&mut self.lss, &node);
             unsafe {
                 if let Some(prev) = node.prev.as_mut() {
@@ -242,20 +677,218 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> YFastMap<K, V> {
         }
     }
 
+    /// Merge two maps in O(N + M) time, combining the values of keys in
+    /// both maps with `resolve(self_value, other_value)`
+    pub fn merge_sorted(
+        self,
+        other: Self,
+        resolve: impl Fn(V, V) -> V,
+    ) -> Self {
+        let mut left = self.into_sorted().peekable();
+        let mut right = other.into_sorted().peekable();
+        let merged = std::iter::from_fn(|| {
+            let ordering = match (left.peek(), right.peek()) {
+                (Some((l, _)), Some((r, _))) => l.cmp(r),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return None,
+            };
+            match ordering {
+                Ordering::Less => left.next(),
+                Ordering::Greater => right.next(),
+                Ordering::Equal => {
+                    let (key, l) = left.next().unwrap();
+                    let (_, r) = right.next().unwrap();
+                    Some((key, resolve(l, r)))
+                }
+            }
+        });
+        YFastMap::from_sorted(merged)
+    }
+
+    /// Consume the map, yielding its keys in sorted order
+    pub fn into_keys(self) -> impl Iterator<Item = K> {
+        self.into_sorted().map(|(key, _)| key)
+    }
+
+    /// Consume the map, yielding its values in key order
+    pub fn into_values(self) -> impl Iterator<Item = V> {
+        self.into_sorted().map(|(_, value)| value)
+    }
+
+    /// Consume the map, yielding its entries in sorted order
+    fn into_sorted(self) -> impl Iterator<Item = (K, V)> {
+        let YFastMap { lss, mut map, .. } = self;
+
+        let mut keys = Vec::with_capacity(map.len());
+        let mut node = K::lss_min(&lss);
+        while let Some(n) = node {
+            keys.push(n.key);
+            node = unsafe { n.next.as_ref() };
+        }
+
+        keys.into_iter().flat_map(move |key| {
+            let node = map.remove(&key).expect("listed nodes are in the map");
+            node.value.into_btree()
+        })
+    }
+
+    /// Build a map from entries in strictly increasing key order, packing
+    /// `K::LEN` entries into each btree
+    pub(super) fn from_sorted(input: impl Iterator<Item = (K, V)>) -> Self {
+        fn to_node<K, V>(chunk: BTreeMap<K, V>) -> Box<LinkedBTree<K, V>>
+        where
+            K: LevelSearchable<BTreeRange<K, V>>,
+        {
+            let range = BTreeRange::from_btree(chunk);
+            Box::new(LNode::new(range.min, range))
+        }
+
+        let mut map = YFastMap::new();
+        let mut pending = None;
+        let mut chunk = BTreeMap::new();
+        let mut last = None;
+        for (key, value) in input {
+            debug_assert!(last < Some(key));
+            last = Some(key);
+            chunk.insert(key, value);
+            map.len += 1;
+
+            if chunk.len() == K::LEN {
+                if let Some(node) = pending.take() {
+                    map.insert_new(node);
+                }
+                pending = Some(to_node(std::mem::take(&mut chunk)));
+            }
+        }
+
+        // Fold a short final chunk into the previous btree
+        match pending {
+            Some(ref mut node) if chunk.len() < K::LEN / 2 => {
+                for (key, value) in chunk {
+                    node.value.insert(key, value);
+                }
+            }
+            _ if !chunk.is_empty() => {
+                if let Some(node) = pending.take() {
+                    map.insert_new(node);
+                }
+                pending = Some(to_node(chunk));
+            }
+            _ => {}
+        }
+        if let Some(node) = pending {
+            map.insert_new(node);
+        }
+        map
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (K, &V)> {
-        let min = K::lss_min(&self.lss);
+        let min = K::lss_min(&self.lss).map(LNodeRef::new);
         Iter {
+            lss: &self.lss,
             btree: min,
-            iter: min.map(|m| m.value.iter()),
+            iter: min.map(|m| m.value().iter()),
+        }
+    }
+
+    /// Iterate over the inclusive ranges of keys missing from the map
+    pub fn gaps(&self) -> impl Iterator<Item = (K, K)> + '_ {
+        let keys = self.iter().map(|(k, _)| k);
+        level_search::gaps::<K, BTreeRange<K, V>>(keys)
+    }
+}
+
+impl<K: LevelSearchable<BTreeRange<K, V>>, V> FromIterator<(K, V)>
+    for YFastMap<K, V>
+{
+    fn from_iter<I>(input: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        YFastMap::from_iter_reporting(input).0
+    }
+}
+
+// How keys are split into `BTreeRange`s depends on the insertion order, so
+// maps are equal when their entries are
+impl<K: LevelSearchable<BTreeRange<K, V>>, V: PartialEq> PartialEq
+    for YFastMap<K, V>
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: LevelSearchable<BTreeRange<K, V>>, V: Eq> Eq for YFastMap<K, V> {}
+
+impl<K: LevelSearchable<BTreeRange<K, V>>, V: Hash> Hash for YFastMap<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for entry in self.iter() {
+            entry.hash(state);
         }
     }
 }
 
+impl<K, V> YFastMap<K, V>
+where
+    K: LevelSearchable<BTreeRange<K, V>> + Pod,
+    V: Pod,
+{
+    /// Write the length (as a little-endian `u64`), followed by the raw
+    /// bytes of each key and value in sorted order.
+    ///
+    /// Keys and values are written in native byte order, so `read_from`
+    /// must run on a machine of the same endianness.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&(self.len as u64).to_le_bytes())?;
+        for (key, value) in self.iter() {
+            w.write_all(bytemuck::bytes_of(&key))?;
+            w.write_all(bytemuck::bytes_of(value))?;
+        }
+        Ok(())
+    }
+
+    /// Read a map written by `write_to` on a machine of the same endianness
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<YFastMap<K, V>> {
+        let mut len = [0; 8];
+        r.read_exact(&mut len)?;
+
+        let mut map = YFastMap::new();
+        let mut prev: Option<K> = None;
+        for _ in 0..u64::from_le_bytes(len) {
+            let mut key = K::zeroed();
+            let mut value = V::zeroed();
+            r.read_exact(bytemuck::bytes_of_mut(&mut key))?;
+            r.read_exact(bytemuck::bytes_of_mut(&mut value))?;
+
+            if matches!(prev, Some(prev) if prev >= key) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "keys are not sorted",
+                ));
+            }
+            prev = Some(key);
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
 struct Iter<'a, K: LevelSearchable<BTreeRange<K, V>>, V> {
-    btree: Option<&'a LinkedBTree<K, V>>,
+    lss: &'a K::LSS,
+    btree: Option<LNodeRef<'a, K, BTreeRange<K, V>>>,
     iter: Option<BTreeIter<'a, K, V>>,
 }
 
+impl<'a, K: LevelSearchable<BTreeRange<K, V>>, V> Iter<'a, K, V> {
+    fn next_btree(&mut self) {
+        self.btree = self.btree.and_then(LNodeRef::next);
+        self.iter = self.btree.map(|btree| btree.value().iter());
+    }
+}
+
 impl<'a, K: LevelSearchable<BTreeRange<K, V>>, V> Iterator for Iter<'a, K, V> {
     type Item = (K, &'a V);
 
@@ -264,9 +897,7 @@ impl<'a, K: LevelSearchable<BTreeRange<K, V>>, V> Iterator for Iter<'a, K, V> {
             let output = iter.next();
 
             if output.is_none() {
-                self.btree =
-                    self.btree.and_then(|btree| unsafe { btree.next.as_ref() });
-                self.iter = self.btree.map(|btree| btree.value.iter());
+                self.next_btree();
                 return self.next();
             }
 
@@ -275,11 +906,61 @@ impl<'a, K: LevelSearchable<BTreeRange<K, V>>, V> Iterator for Iter<'a, K, V> {
             None
         }
     }
+
+    fn nth(&mut self, mut n: usize) -> Option<Self::Item> {
+        // Skip over whole BTreeRanges at a time
+        loop {
+            let iter = self.iter.as_mut()?;
+            if n < iter.len() {
+                return iter.nth(n).map(|(k, v)| (*k, v));
+            }
+            n -= iter.len();
+            self.next_btree();
+        }
+    }
+
+    fn last(self) -> Option<Self::Item> {
+        // The iterator always runs to the end of the list
+        let btree = self.btree?;
+        let max = K::lss_max(self.lss)?;
+        let output = if btree.key() == max.key {
+            self.iter?.last()
+        } else {
+            max.value.iter().next_back()
+        };
+        output.map(|(k, v)| (*k, v))
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_yfast_eq() {
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let keys = (0..5000).map(|_| rng.gen()).collect::<Vec<u32>>();
+        let forward = keys.iter().map(|&k| (k, k)).collect::<YFastMap<_, _>>();
+        let mut backward = YFastMap::new();
+        for &k in keys.iter().rev() {
+            backward.insert(k, k);
+        }
+        assert!(forward == backward);
+
+        let hash = |map: &YFastMap<u32, u32>| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            map.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&forward), hash(&backward));
+
+        backward.insert(keys[7], 0);
+        assert!(forward != backward);
+        backward.remove(keys[7]);
+        assert!(forward != backward);
+        assert!(YFastMap::<u32, ()>::new() == YFastMap::new());
+    }
 
     #[test]
     fn test_yfast_get() {
@@ -371,4 +1052,532 @@ mod test {
             (0..1000u32).map(|k| (2 * k, 2 * k)).collect::<Vec<_>>()
         );
     }
+
+    #[test]
+    fn test_yfast_interleaved_insert_remove() {
+        // Keys from a small range, so ranges are repeatedly split, merged
+        // and re-keyed
+        let mut yfast = YFastMap::new();
+        let mut expected = BTreeMap::new();
+        let mut rng = SmallRng::from_seed([5; 16]);
+        for i in 0..20000u32 {
+            let key = rng.gen_range(0, 1u32 << 10);
+            if rng.gen() {
+                assert_eq!(yfast.insert(key, i), expected.insert(key, i));
+            } else {
+                assert_eq!(yfast.remove(key), expected.remove(&key));
+            }
+            assert_eq!(yfast.len(), expected.len());
+        }
+        assert!(yfast.iter().map(|(k, v)| (k, *v)).eq(expected.clone()));
+        for key in 0..1 << 10 {
+            assert_eq!(yfast.get(key), expected.get(&key));
+            let pred = expected.range(..=key).next_back();
+            assert_eq!(yfast.predecessor(key), pred.map(|(&k, v)| (k, v)));
+        }
+    }
+
+    #[test]
+    fn test_yfast_get_many_mut() {
+        let mut yfast =
+            (0..1000u32).map(|i| (i * 3, i)).collect::<YFastMap<_, _>>();
+
+        let [a, b] = yfast.get_many_mut([6, 2997]).unwrap();
+        std::mem::swap(a, b);
+        assert_eq!(yfast.get(6), Some(&999));
+        assert_eq!(yfast.get(2997), Some(&2));
+
+        // Keys in the same `BTreeRange`
+        let [a, b, c] = yfast.get_many_mut([9, 12, 15]).unwrap();
+        *a += *b + *c;
+        assert_eq!(yfast.get(9), Some(&12));
+
+        assert!(yfast.get_many_mut([9, 12, 9]).is_none());
+        assert!(yfast.get_many_mut([9, 10]).is_none());
+        assert_eq!(yfast.get_many_mut::<0>([]), Some([]));
+        assert!(YFastMap::<u32, u32>::new().get_many_mut([1]).is_none());
+    }
+
+    #[test]
+    fn test_yfast_write_read() {
+        let mut yfast = YFastMap::new();
+        let mut rng = SmallRng::from_seed([5; 16]);
+        for i in 0..1000u32 {
+            yfast.insert(rng.gen::<u32>(), u64::from(i));
+        }
+
+        let mut buffer = Vec::new();
+        yfast.write_to(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), 8 + yfast.len() * (4 + 8));
+
+        let read = YFastMap::<u32, u64>::read_from(&mut &buffer[..]).unwrap();
+        assert_eq!(read.len(), yfast.len());
+        assert_eq!(
+            read.iter().collect::<Vec<_>>(),
+            yfast.iter().collect::<Vec<_>>()
+        );
+
+        // Truncated input
+        let mut truncated = &buffer[..buffer.len() - 1];
+        assert!(YFastMap::<u32, u64>::read_from(&mut truncated).is_err());
+
+        // Empty map
+        let mut buffer = Vec::new();
+        YFastMap::<u32, u64>::new().write_to(&mut buffer).unwrap();
+        assert_eq!(buffer, vec![0; 8]);
+        let read = YFastMap::<u32, u64>::read_from(&mut &buffer[..]).unwrap();
+        assert!(read.is_empty());
+    }
+
+    #[test]
+    fn test_yfast_duplicates() {
+        let keys = [5u32, 1000, 5, 7, 1000, 5];
+        let (yfast, overwritten) = YFastMap::from_iter_reporting(
+            keys.iter().cloned().enumerate().map(|(i, k)| (k, i)),
+        );
+        assert_eq!(overwritten, 3);
+        assert_eq!(yfast.len(), 3);
+        assert_eq!(yfast.get(5), Some(&5));
+        assert_eq!(yfast.get(7), Some(&3));
+        assert_eq!(yfast.get(1000), Some(&4));
+
+        let mut yfast = YFastMap::new();
+        assert_eq!(yfast.try_insert(3u32, 0), Ok(()));
+        assert_eq!(yfast.try_insert(4, 1), Ok(()));
+        assert_eq!(yfast.try_insert(3, 2), Err(OccupiedError { value: 2 }));
+        assert_eq!(yfast.get(3), Some(&0));
+        assert_eq!(yfast.len(), 2);
+    }
+
+    #[test]
+    fn test_yfast_longest_common_prefix_bits() {
+        let mut yfast = YFastMap::new();
+        assert_eq!(yfast.longest_common_prefix_bits(0u64), 0);
+        for i in 0..1000u64 {
+            yfast.insert(i * 0x0123_4567_89ab, ());
+        }
+
+        for &query in &[0, 5, 0x0123_4567_89ab, 0xdead_beef, u64::MAX] {
+            let pred = yfast.predecessor(query).map(|(k, _)| k);
+            let succ = yfast.successor(query).map(|(k, _)| k);
+            let expected = pred
+                .into_iter()
+                .chain(succ)
+                .map(|k| (k ^ query).leading_zeros())
+                .max()
+                .unwrap();
+            assert_eq!(yfast.longest_common_prefix_bits(query), expected);
+        }
+        assert_eq!(yfast.longest_common_prefix_bits(0x0123_4567_89ab), 64);
+        assert_eq!(yfast.longest_common_prefix_bits(1), 63);
+    }
+
+    #[test]
+    fn test_yfast_iter_nth_last() {
+        let mut yfast = YFastMap::new();
+        assert_eq!(yfast.iter().last(), None);
+        assert_eq!(yfast.iter().nth(3), None);
+
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let mut sorted = (0..1000)
+            .map(|_| rng.gen_range(0, 1u32 << 28))
+            .collect::<Vec<_>>();
+        for &key in sorted.iter() {
+            yfast.insert(key, key);
+        }
+        sorted.sort();
+        sorted.dedup();
+
+        let n = sorted.len();
+        let max = sorted[n - 1];
+        assert_eq!(yfast.iter().last(), Some((max, &max)));
+        for k in [0, 1, 63, 64, 65, 500, n - 1].iter().cloned() {
+            let key = sorted[k];
+            assert_eq!(yfast.iter().nth(k), Some((key, &key)));
+
+            let mut iter = yfast.iter();
+            assert_eq!(iter.nth(k), Some((key, &key)));
+            assert_eq!(iter.next().map(|(k, _)| k), sorted.get(k + 1).cloned());
+            assert_eq!(
+                iter.last(),
+                if k < n - 1 { Some((max, &max)) } else { None }
+            );
+        }
+        assert_eq!(yfast.iter().nth(n), None);
+    }
+
+    /// Remove the key each `BTreeRange` is indexed by (leaving the range
+    /// non-empty), then check every neighbour query against a `BTreeMap`
+    fn check_remove_index_keys<K>()
+    where
+        K: LevelSearchable<BTreeRange<K, u32>> + From<u32>,
+    {
+        let mut yfast = YFastMap::new();
+        let mut expected = std::collections::BTreeMap::new();
+        for i in 0..2000u32 {
+            yfast.insert(K::from(4 * i), i);
+            expected.insert(K::from(4 * i), i);
+        }
+        let index_keys = yfast.map.keys().cloned().collect::<Vec<_>>();
+        assert!(index_keys.len() > 1);
+        for key in index_keys {
+            assert_eq!(yfast.remove(key), expected.remove(&key));
+        }
+
+        for key in (0..8004).map(K::from) {
+            let pred = expected.range(..=key).next_back();
+            let succ = expected.range(key..).next();
+            assert_eq!(yfast.predecessor(key), pred.map(|(&k, v)| (k, v)));
+            assert_eq!(yfast.successor(key), succ.map(|(&k, v)| (k, v)));
+            assert_eq!(yfast.contains_key(key), expected.contains_key(&key));
+        }
+    }
+
+    #[test]
+    fn test_yfast_remove_index_key() {
+        check_remove_index_keys::<u32>();
+        check_remove_index_keys::<u64>();
+    }
+
+    #[test]
+    fn test_yfast_remove_range() {
+        let ranges: [(Bound<u32>, Bound<u32>); 6] = [
+            (Bound::Included(100), Bound::Excluded(2000)),
+            (Bound::Unbounded, Bound::Included(500)),
+            (Bound::Excluded(7000), Bound::Unbounded),
+            (Bound::Excluded(3003), Bound::Included(3010)),
+            (Bound::Included(50), Bound::Included(10)),
+            (Bound::Unbounded, Bound::Unbounded),
+        ];
+        let mut yfast = YFastMap::new();
+        let mut expected = std::collections::BTreeMap::new();
+        for i in 0..5000u32 {
+            yfast.insert(i * 3 / 2, i);
+            expected.insert(i * 3 / 2, i);
+        }
+
+        for range in ranges.iter().cloned() {
+            let removed = if range.0 == Bound::Included(50) {
+                Vec::new()
+            } else {
+                expected.range(range).map(|(&k, _)| k).collect::<Vec<_>>()
+            };
+            for key in removed.iter() {
+                expected.remove(key);
+            }
+
+            assert_eq!(yfast.remove_range(range), removed.len());
+            assert_eq!(yfast.len(), expected.len());
+            assert_eq!(
+                yfast.iter().collect::<Vec<_>>(),
+                expected.iter().map(|(&k, v)| (k, v)).collect::<Vec<_>>()
+            );
+        }
+        assert!(yfast.is_empty());
+    }
+
+    #[test]
+    fn test_yfast_merge_sorted() {
+        use std::cell::RefCell;
+
+        let a = (0..3000u32).step_by(3).map(|k| (k, k)).collect::<Vec<_>>();
+        let b = (0..3000u32).step_by(5).map(|k| (k, 1)).collect::<Vec<_>>();
+        let left = YFastMap::from_iter(a.iter().cloned());
+        let right = YFastMap::from_iter(b.iter().cloned());
+
+        let collisions = RefCell::new(Vec::new());
+        let mut merged = left.merge_sorted(right, |l, r| {
+            collisions.borrow_mut().push(l);
+            l + r
+        });
+
+        let mut expected = BTreeMap::new();
+        for &(k, v) in b.iter() {
+            expected.insert(k, v);
+        }
+        for &(k, v) in a.iter() {
+            *expected.entry(k).or_insert(0) += v;
+        }
+        assert_eq!(merged.len(), expected.len());
+        assert_eq!(
+            merged.iter().map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+            expected.into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            collisions.into_inner(),
+            (0..3000).step_by(15).collect::<Vec<_>>()
+        );
+
+        // The merged map stays usable
+        assert_eq!(merged.insert(1, 7), None);
+        assert_eq!(merged.remove(3), Some(3));
+        assert_eq!(merged.get(1), Some(&7));
+        assert_eq!(merged.successor_of(1), Some(5));
+
+        let empty = YFastMap::<u32, u32>::new();
+        let merged = empty.merge_sorted(YFastMap::new(), |l, _| l);
+        assert!(merged.is_empty());
+    }
+
+    #[test]
+    fn test_yfast_into_keys_values() {
+        use std::rc::Rc;
+
+        let mut keys = (0..2000u32).map(|k| k * 7 % 2000).collect::<Vec<_>>();
+        let yfast = YFastMap::from_iter(keys.iter().map(|&k| (k, k * 2)));
+        keys.sort();
+        assert_eq!(
+            yfast.into_values().collect::<Vec<_>>(),
+            keys.iter().map(|k| k * 2).collect::<Vec<_>>()
+        );
+
+        let yfast = YFastMap::from_iter(keys.iter().map(|&k| (k, k * 2)));
+        assert_eq!(yfast.into_keys().collect::<Vec<_>>(), keys);
+
+        // Values left in a partially consumed iterator are dropped with it
+        let value = Rc::new(());
+        let yfast =
+            YFastMap::from_iter(keys.iter().map(|&k| (k, Rc::clone(&value))));
+        assert_eq!(Rc::strong_count(&value), 2001);
+        let mut values = yfast.into_values();
+        let first = values.next().unwrap();
+        drop(values);
+        assert_eq!(Rc::strong_count(&value), 2);
+        drop(first);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn test_yfast_strict_neighbors() {
+        let mut yfast = YFastMap::new();
+        for key in (0..1000u64).chain((u64::MAX - 1000)..=u64::MAX) {
+            yfast.insert(key, key);
+        }
+
+        assert_eq!(yfast.predecessor(0), Some((0, &0)));
+        assert_eq!(yfast.predecessor_strict(0), None);
+        assert_eq!(yfast.predecessor_strict(1), Some((0, &0)));
+        assert_eq!(
+            yfast.successor_strict(999),
+            Some((u64::MAX - 1000, &(u64::MAX - 1000)))
+        );
+        assert_eq!(
+            yfast.predecessor_strict(u64::MAX - 1000),
+            Some((999, &999))
+        );
+        assert_eq!(yfast.predecessor_strict(5000), Some((999, &999)));
+
+        assert_eq!(yfast.successor(u64::MAX), Some((u64::MAX, &u64::MAX)));
+        assert_eq!(yfast.successor_strict(u64::MAX), None);
+        assert_eq!(
+            yfast.successor_strict(u64::MAX - 1),
+            Some((u64::MAX, &u64::MAX))
+        );
+        assert_eq!(
+            yfast.predecessor_strict(u64::MAX),
+            Some((u64::MAX - 1, &(u64::MAX - 1)))
+        );
+
+        yfast.remove(0);
+        yfast.remove(u64::MAX);
+        assert_eq!(yfast.predecessor_strict(1), None);
+        assert_eq!(yfast.successor_strict(u64::MAX - 1), None);
+        assert_eq!(YFastMap::<u32, ()>::new().successor_strict(5), None);
+    }
+
+    #[test]
+    fn test_yfast_neighbors() {
+        let mut yfast = YFastMap::new();
+        assert_eq!(yfast.successor_of(3), None);
+        for i in 0..1000u32 {
+            yfast.insert(2 * i, i);
+        }
+
+        assert_eq!(yfast.successor_of(3), Some(4));
+        assert_eq!(yfast.predecessor_of(3), Some(2));
+        assert_eq!(yfast.predecessor_of(0), None);
+        assert_eq!(yfast.successor_of(1998), None);
+        assert_eq!(yfast.predecessor_of(5000), Some(1998));
+        assert_eq!(yfast.get_with_next(1998), Some((&999, None)));
+        assert_eq!(yfast.get_with_next(3), None);
+
+        // Crosses the boundaries between BTreeRanges
+        for i in 0..999 {
+            assert_eq!(yfast.successor_of(2 * i), Some(2 * i + 2));
+            assert_eq!(yfast.predecessor_of(2 * i + 2), Some(2 * i));
+            assert_eq!(yfast.get_with_next(2 * i), Some((&i, Some(2 * i + 2))));
+        }
+    }
+
+    #[test]
+    fn test_yfast_gaps() {
+        let mut yfast = YFastMap::new();
+        assert_eq!(yfast.gaps().collect::<Vec<_>>(), vec![(0, u64::MAX)]);
+
+        for key in (0..1000u64).chain((u64::MAX - 1000)..=u64::MAX) {
+            yfast.insert(key, key);
+        }
+        assert_eq!(
+            yfast.gaps().collect::<Vec<_>>(),
+            vec![(1000, u64::MAX - 1001)]
+        );
+
+        yfast.remove(500);
+        assert_eq!(
+            yfast.gaps().collect::<Vec<_>>(),
+            vec![(500, 500), (1000, u64::MAX - 1001)]
+        );
+    }
+
+    #[test]
+    fn test_yfast_with_rank() {
+        let mut map = YFastMap::<u32, u32>::new();
+        assert_eq!(map.successor_with_rank(0), None);
+
+        for round in 0..4u32 {
+            for i in 0..500u32 {
+                let key = (i * 7919 + round * 104_729) % 50_000;
+                if round % 2 == 1 && i % 3 == 0 {
+                    map.remove(key);
+                } else {
+                    map.insert(key, key * 2);
+                }
+            }
+            let keys = map.iter().map(|(k, _)| k).collect::<Vec<_>>();
+            for probe in (0..50_001).step_by(97) {
+                let succ = keys.iter().position(|&k| k >= probe);
+                let expected = succ.map(|i| (i, keys[i], keys[i] * 2));
+                let actual =
+                    map.successor_with_rank(probe).map(|(i, k, &v)| (i, k, v));
+                assert_eq!(actual, expected);
+
+                let pred = keys.iter().rposition(|&k| k <= probe);
+                let expected = pred.map(|i| (i, keys[i], keys[i] * 2));
+                let actual = map
+                    .predecessor_with_rank(probe)
+                    .map(|(i, k, &v)| (i, k, v));
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_yfast_rank_between_updates() {
+        let mut map =
+            (0..2000u32).map(|k| (k * 5, k)).collect::<YFastMap<_, _>>();
+        let mut reference =
+            map.iter().map(|(k, &v)| (k, v)).collect::<BTreeMap<_, _>>();
+        let mut rng = SmallRng::from_seed([5; 16]);
+        for i in 0..3000u32 {
+            let key = rng.gen_range(0, 1 << 12);
+            if i % 3 == 0 {
+                assert_eq!(map.remove(key), reference.remove(&key));
+            } else {
+                assert_eq!(map.insert(key, i), reference.insert(key, i));
+            }
+
+            for probe in [key, key / 2, key.wrapping_mul(3) % 10_000] {
+                let expected = reference
+                    .range(probe..)
+                    .next()
+                    .map(|(&k, &v)| (reference.range(..k).count(), k, v));
+                let actual =
+                    map.successor_with_rank(probe).map(|(i, k, &v)| (i, k, v));
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_yfast_cursor_mut() {
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let keys = (0..3000)
+            .map(|_| rng.gen_range(0, 1u32 << 20))
+            .collect::<Vec<_>>();
+        let mut yfast =
+            keys.iter().map(|&k| (k, k)).collect::<YFastMap<_, _>>();
+        let mut expected =
+            keys.iter().map(|&k| (k, k)).collect::<BTreeMap<_, _>>();
+
+        // Remove every third key and double the rest
+        let mut cursor = yfast.cursor_mut_from(0);
+        let mut i = 0;
+        while let Some(key) = cursor.key() {
+            if i % 3 == 0 {
+                assert_eq!(cursor.remove_current(), Some(key));
+                expected.remove(&key);
+            } else {
+                *cursor.value_mut().unwrap() *= 2;
+                *expected.get_mut(&key).unwrap() *= 2;
+                cursor.move_next();
+            }
+            i += 1;
+        }
+        assert_eq!(cursor.value_mut(), None);
+        assert_eq!(cursor.move_next(), None);
+        assert_eq!(yfast.len(), expected.len());
+        assert!(yfast.iter().map(|(k, v)| (k, *v)).eq(expected.clone()));
+
+        // Start in the middle and stop early
+        let start = *expected.keys().nth(100).unwrap();
+        let mut cursor = yfast.cursor_mut_from(start - 1);
+        assert_eq!(cursor.key(), Some(start));
+        for _ in 0..50 {
+            let key = cursor.key().unwrap();
+            assert_eq!(cursor.remove_current(), expected.remove(&key));
+        }
+        assert_eq!(cursor.key(), expected.keys().nth(100).cloned());
+        assert!(yfast.iter().map(|(k, v)| (k, *v)).eq(expected.clone()));
+        for (&key, value) in expected.iter() {
+            assert_eq!(yfast.get(key), Some(value));
+        }
+        assert!(yfast.cursor_mut_from(u32::MAX).key().is_none());
+    }
+
+    #[test]
+    fn test_yfast_descent_token() {
+        let mut yfast = YFastMap::new();
+        let mut expected = BTreeMap::new();
+        let mut rng = SmallRng::from_seed([5; 16]);
+        for _ in 0..5000 {
+            let key = rng.gen_range(0, 1000u64);
+            *expected.entry(key).or_insert(0) += 1;
+            match yfast.descend(key) {
+                (Some(count), _) => *count += 1,
+                (None, token) => {
+                    assert_eq!(yfast.insert_with_token(token, 1), None);
+                }
+            }
+        }
+        assert!(yfast.iter().map(|(k, v)| (k, *v)).eq(expected.into_iter()));
+
+        // A stale token searches again
+        let (_, token) = yfast.descend(5000);
+        yfast.insert(5001, 1);
+        assert_eq!(yfast.insert_with_token(token, 2), None);
+        assert_eq!(yfast.get(5000), Some(&2));
+
+        let (value, token) = yfast.descend(5000);
+        assert_eq!(value, Some(&mut 2));
+        assert_eq!(yfast.remove_with_token(token), Some(2));
+        assert_eq!(yfast.get(5000), None);
+        assert_eq!(yfast.get(5001), Some(&1));
+    }
+
+    #[test]
+    fn test_yfast_descent_token_other_map() {
+        // Both maps are at the same generation, and index their ranges by
+        // some of the same keys
+        let mut sparse =
+            (0..1000u32).map(|k| (k * 2, k)).collect::<YFastMap<_, _>>();
+        for key in (0..2000).step_by(13) {
+            let mut dense =
+                (0..1000u32).map(|k| (k, k)).collect::<YFastMap<_, _>>();
+            let (_, token) = sparse.descend(key);
+            let expected = Some(key).filter(|&k| k < 1000);
+            assert_eq!(dense.remove_with_token(token), expected);
+            assert_eq!(dense.get(key), None);
+            assert_eq!(dense.len(), 1000 - expected.iter().count());
+        }
+    }
 }
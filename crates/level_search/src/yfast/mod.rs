@@ -1,13 +1,23 @@
+mod counts;
+mod hybrid;
+mod lru;
 mod map;
 mod set;
 mod tree;
 
-pub use map::YFastMap;
+pub use hybrid::HybridMap;
+pub use lru::LruYFast;
+pub use map::{CursorMut, DescentToken, OccupiedError, YFastMap};
 pub use set::YFastSet;
 use tree::BTreeRange;
 
 pub trait LevelSearchable<T>: crate::level_search::LevelSearchable<T> {}
+impl<T> LevelSearchable<T> for u16 {}
 impl<T> LevelSearchable<T> for u32 {}
 impl<T> LevelSearchable<T> for u64 {}
+impl<T> LevelSearchable<T> for [u8; 2] {}
+impl<T> LevelSearchable<T> for [u8; 4] {}
+impl<T> LevelSearchable<T> for [u8; 8] {}
+impl<T, const BITS: usize> LevelSearchable<T> for crate::UInt<BITS> {}
 
 type LinkedBTree<K, V> = crate::level_search::LNode<K, BTreeRange<K, V>>;
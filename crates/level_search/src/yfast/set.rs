@@ -48,6 +48,11 @@ impl<K: LevelSearchable<BTreeRange<K, ()>>> YFastSet<K> {
         self.map.iter().map(|k| k.0)
     }
 
+    /// Iterate over the inclusive ranges of keys missing from the set
+    pub fn gaps(&self) -> impl Iterator<Item = (K, K)> + '_ {
+        self.map.gaps()
+    }
+
     pub fn predecessor(&self, key: K) -> Option<K> {
         self.map.predecessor(key).map(|x| x.0)
     }
@@ -56,3 +61,53 @@ impl<K: LevelSearchable<BTreeRange<K, ()>>> YFastSet<K> {
         self.map.successor(key).map(|x| x.0)
     }
 }
+
+#[cfg(feature = "rayon")]
+impl<K> rayon::iter::FromParallelIterator<K> for YFastSet<K>
+where
+    K: LevelSearchable<BTreeRange<K, ()>> + Send,
+{
+    /// Sort and deduplicate the keys in parallel, and then build the set
+    /// from the sorted keys on one thread
+    fn from_par_iter<I>(input: I) -> Self
+    where
+        I: rayon::iter::IntoParallelIterator<Item = K>,
+    {
+        use rayon::prelude::*;
+
+        let mut keys = input.into_par_iter().collect::<Vec<_>>();
+        keys.par_sort_unstable();
+        keys.dedup();
+        YFastSet {
+            map: YFastMap::from_sorted(keys.into_iter().map(|key| (key, ()))),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rayon"))]
+mod test {
+    use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_yfast_set_from_par_iter() {
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let keys = (0..200_000)
+            .map(|_| rng.gen_range(0, 100_000u64))
+            .collect::<Vec<_>>();
+
+        let parallel = keys.par_iter().cloned().collect::<YFastSet<u64>>();
+        let mut sequential = YFastSet::new();
+        for &key in keys.iter() {
+            sequential.insert(key);
+        }
+
+        assert_eq!(parallel.len(), sequential.len());
+        assert!(parallel.iter().eq(sequential.iter()));
+        for key in (0..100_000).step_by(97) {
+            assert_eq!(parallel.contains(key), sequential.contains(key));
+            assert_eq!(parallel.successor(key), sequential.successor(key));
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::{btree_map, BTreeMap};
+use std::ops::Bound;
 use std::ptr;
 
+use super::counts::NIL;
 use crate::level_search::{LNode, LevelSearchable};
 
 type LinkedBTree<K, V> = LNode<K, BTreeRange<K, V>>;
@@ -10,6 +12,9 @@ pub struct BTreeRange<K: LevelSearchable<BTreeRange<K, V>>, V> {
     btree: BTreeMap<K, V>,
     pub(super) max: K,
     pub(super) min: K,
+    // The slot of this range's count in `YFastMap::counts`, or `NIL` until
+    // the range is linked into the map
+    pub(super) slot: usize,
 }
 
 impl<K: LevelSearchable<BTreeRange<K, V>>, V> BTreeRange<K, V> {
@@ -18,9 +23,24 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> BTreeRange<K, V> {
             max: key,
             min: key,
             btree: BTreeMap::new(),
+            slot: NIL,
         }
     }
 
+    /// Build a range holding all of a non-empty `btree`
+    pub(super) fn from_btree(btree: BTreeMap<K, V>) -> BTreeRange<K, V> {
+        BTreeRange {
+            min: *btree.keys().next().unwrap(),
+            max: *btree.keys().next_back().unwrap(),
+            btree,
+            slot: NIL,
+        }
+    }
+
+    pub(super) fn into_btree(self) -> BTreeMap<K, V> {
+        self.btree
+    }
+
     pub(super) fn insert(&mut self, key: K, value: V) -> Option<V> {
         if key > self.max {
             self.max = key;
@@ -36,6 +56,10 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> BTreeRange<K, V> {
         self.btree.get(&key)
     }
 
+    pub(super) fn get_mut(&mut self, key: K) -> Option<&mut V> {
+        self.btree.get_mut(&key)
+    }
+
     pub(super) fn remove(&mut self, key: K, default: K) -> Option<V> {
         let output = self.btree.remove(&key);
         if key == self.max {
@@ -67,6 +91,24 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> BTreeRange<K, V> {
         self.btree.range(..=key).next_back().map(|(k, v)| (*k, v))
     }
 
+    /// Return the smallest key strictly greater than `key`
+    pub(super) fn next_key(&self, key: K) -> Option<K> {
+        self.btree
+            .range((Bound::Excluded(key), Bound::Unbounded))
+            .next()
+            .map(|(k, _)| *k)
+    }
+
+    /// The number of keys in this range strictly less than `key`
+    pub(super) fn count_below(&self, key: K) -> usize {
+        self.btree.range(..key).count()
+    }
+
+    /// Return the largest key strictly less than `key`
+    pub(super) fn prev_key(&self, key: K) -> Option<K> {
+        self.btree.range(..key).next_back().map(|(k, _)| *k)
+    }
+
     pub(super) fn successor(&self, key: K) -> Option<(K, &V)> {
         if key > self.max {
             return None;
@@ -76,6 +118,10 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> BTreeRange<K, V> {
 }
 
 impl<K: LevelSearchable<BTreeRange<K, V>>, V> LinkedBTree<K, V> {
+    pub(super) fn len(&self) -> usize {
+        self.value.btree.len()
+    }
+
     pub(super) fn is_full(&self) -> bool {
         self.value.btree.len() == K::LEN * 2
     }
@@ -112,6 +158,7 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> LinkedBTree<K, V> {
                     btree: self.value.btree.split_off(&high),
                     min: high,
                     max: max,
+                    slot: NIL,
                 },
                 prev: ptr::null_mut(),
                 next: ptr::null_mut(),
@@ -129,6 +176,7 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> LinkedBTree<K, V> {
                     ),
                     max: low,
                     min: min,
+                    slot: NIL,
                 },
                 prev: ptr::null_mut(),
                 next: ptr::null_mut(),
@@ -148,7 +196,7 @@ impl<K: LevelSearchable<BTreeRange<K, V>>, V> LinkedBTree<K, V> {
             prev.value.btree.append(&mut self.value.btree);
             return prev;
         }
-        unreachable!();
+        panic!("a small range always has a neighbor to merge into");
     }
 
     pub(super) fn within_range(&self, key: K) -> bool {
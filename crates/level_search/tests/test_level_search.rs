@@ -1,7 +1,12 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
 
-use level_search::{xfast::XFastSet, yfast::YFastSet};
-use proptest::prelude::*;
+use level_search::{
+    xfast::{XFastMap, XFastSet},
+    yfast::{YFastMap, YFastSet},
+};
+use proptest::{prelude::*, test_runner::TestRunner};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 
 proptest! {
     #[test]
@@ -130,3 +135,286 @@ proptest! {
         }
     }
 }
+
+#[test]
+fn test_byte_array_keys() {
+    let numbers: [u32; 25] = [
+        9424, 12398, 54780, 51835, 63026, 8401, 63521, 49588, 14290, 60102,
+        12443, 35584, 11924, 55247, 770, 20443, 1862, 11155, 25753, 7685, 1900,
+        7743, 43659, 63103, 3614,
+    ];
+    let mut sorted = numbers.to_vec();
+    sorted.sort();
+
+    let mut xfast4 = XFastMap::new();
+    let mut xfast8 = XFastMap::new();
+    let mut yfast4 = YFastMap::new();
+    let mut yfast8 = YFastMap::new();
+    for k in numbers.iter() {
+        xfast4.insert(k.to_be_bytes(), k);
+        xfast8.insert(u64::from(*k).to_be_bytes(), k);
+        yfast4.insert(k.to_be_bytes(), k);
+        yfast8.insert(u64::from(*k).to_be_bytes(), k);
+    }
+
+    for k in numbers.iter() {
+        assert_eq!(xfast4.get(k.to_be_bytes()), Some(&k));
+        assert_eq!(xfast8.get(u64::from(*k).to_be_bytes()), Some(&k));
+        assert_eq!(yfast4.get(k.to_be_bytes()), Some(&k));
+        assert_eq!(yfast8.get(u64::from(*k).to_be_bytes()), Some(&k));
+    }
+
+    // Keys are ordered as big-endian integers
+    assert_eq!(xfast4.iter().map(|(_, &k)| *k).collect::<Vec<_>>(), sorted);
+    assert_eq!(yfast8.iter().map(|(_, &k)| *k).collect::<Vec<_>>(), sorted);
+    assert_eq!(
+        xfast4.successor([0; 4]),
+        Some((770u32.to_be_bytes(), &&770))
+    );
+    assert_eq!(
+        yfast4.predecessor([0xFF; 4]),
+        Some((63521u32.to_be_bytes(), &&63521))
+    );
+    assert_eq!(
+        xfast8.predecessor(9000u64.to_be_bytes()),
+        Some((8401u64.to_be_bytes(), &&8401))
+    );
+    assert_eq!(
+        yfast8.successor(9000u64.to_be_bytes()),
+        Some((9424u64.to_be_bytes(), &&9424))
+    );
+    assert_eq!(xfast4.get(9000u32.to_be_bytes()), None);
+    assert_eq!(yfast8.get(9000u64.to_be_bytes()), None);
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    Insert(u32, u32),
+    Remove(u32),
+    Get(u32),
+    Predecessor(u32),
+    Successor(u32),
+    Range(Bound<u32>, Bound<u32>),
+}
+
+/// Keys biased towards the extremes and towards bit-prefix boundaries
+fn edge_key() -> impl Strategy<Value = u32> {
+    prop_oneof![
+        any::<u32>(),
+        0..16u32,
+        (u32::MAX - 16)..=u32::MAX,
+        (0..32u32, -2..=2i32)
+            .prop_map(|(bit, delta)| (1u32 << bit).wrapping_add(delta as u32)),
+    ]
+}
+
+fn edge_bound() -> impl Strategy<Value = Bound<u32>> {
+    prop_oneof![
+        Just(Bound::Unbounded),
+        edge_key().prop_map(Bound::Included),
+        edge_key().prop_map(Bound::Excluded),
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (edge_key(), any::<u32>()).prop_map(|(k, v)| Op::Insert(k, v)),
+        edge_key().prop_map(Op::Remove),
+        edge_key().prop_map(Op::Get),
+        edge_key().prop_map(Op::Predecessor),
+        edge_key().prop_map(Op::Successor),
+        (edge_bound(), edge_bound()).prop_map(|(start, end)| {
+            // Keep the bounds in an order BTreeMap::range accepts
+            match (start, end) {
+                (Bound::Included(s), Bound::Included(e))
+                | (Bound::Included(s), Bound::Excluded(e))
+                | (Bound::Excluded(s), Bound::Included(e))
+                    if s > e =>
+                {
+                    Op::Range(end, start)
+                }
+                (Bound::Excluded(s), Bound::Excluded(e)) if s >= e => {
+                    Op::Range(Bound::Included(e), Bound::Included(s))
+                }
+                _ => Op::Range(start, end),
+            }
+        }),
+    ]
+}
+
+#[test]
+#[ignore]
+fn proptest_xfast_matches_btreemap() {
+    // Use a fixed seed so that failures are reproducible
+    let mut runner = TestRunner::deterministic();
+    let ops = prop::collection::vec(op(), 1..1000);
+    runner
+        .run(&ops, |ops| {
+            let mut xfast = XFastMap::new();
+            let mut expected = BTreeMap::new();
+            for op in ops {
+                match op {
+                    Op::Insert(k, v) => {
+                        prop_assert_eq!(
+                            xfast.insert(k, v),
+                            expected.insert(k, v)
+                        )
+                    }
+                    Op::Remove(k) => {
+                        prop_assert_eq!(xfast.remove(k), expected.remove(&k))
+                    }
+                    Op::Get(k) => {
+                        prop_assert_eq!(xfast.get(k), expected.get(&k))
+                    }
+                    Op::Predecessor(k) => prop_assert_eq!(
+                        xfast.predecessor(k),
+                        expected.range(..=k).next_back().map(|(&k, v)| (k, v))
+                    ),
+                    Op::Successor(k) => prop_assert_eq!(
+                        xfast.successor(k),
+                        expected.range(k..).next().map(|(&k, v)| (k, v))
+                    ),
+                    Op::Range(start, end) => prop_assert_eq!(
+                        xfast.range((start, end)).collect::<Vec<_>>(),
+                        expected
+                            .range((start, end))
+                            .map(|(&k, v)| (k, v))
+                            .collect::<Vec<_>>()
+                    ),
+                }
+                prop_assert_eq!(xfast.len(), expected.len());
+            }
+            prop_assert_eq!(
+                xfast.iter().collect::<Vec<_>>(),
+                expected.iter().map(|(&k, v)| (k, v)).collect::<Vec<_>>()
+            );
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_u16_keys() {
+    let mut xfast = XFastSet::new();
+    let mut yfast = YFastSet::new();
+    let mut expected = BTreeSet::new();
+    let mut rng = SmallRng::from_seed([5; 16]);
+    for _ in 0..3000 {
+        let key = rng.gen::<u16>();
+        xfast.insert(key);
+        yfast.insert(key);
+        expected.insert(key);
+    }
+    for key in expected.iter().cloned().step_by(3).collect::<Vec<_>>() {
+        xfast.remove(key);
+        yfast.remove(key);
+        expected.remove(&key);
+    }
+
+    let sorted = expected.iter().cloned().collect::<Vec<_>>();
+    assert_eq!(xfast.iter().collect::<Vec<_>>(), sorted);
+    assert_eq!(yfast.iter().collect::<Vec<_>>(), sorted);
+    for key in (0..=u16::MAX).step_by(7) {
+        let predecessor = expected.range(..=key).next_back().cloned();
+        let successor = expected.range(key..).next().cloned();
+        assert_eq!(xfast.predecessor(key), predecessor);
+        assert_eq!(xfast.successor(key), successor);
+        assert_eq!(yfast.predecessor(key), predecessor);
+        assert_eq!(yfast.successor(key), successor);
+    }
+}
+
+/// Keys from a few clusters, so that many keys share 1, 2 or 3 byte prefixes
+fn clustered_key() -> impl Strategy<Value = u32> {
+    (0..4u32, 0..4u32, 0..4u32, 0..8u32).prop_map(|(a, b, c, d)| {
+        (a << 30) | (b * 0x9e) << 16 | (c * 0x5b) << 8 | (d * 37)
+    })
+}
+
+proptest! {
+    #[test]
+    #[ignore]
+    fn proptest_xfast_remove_shared_prefixes(
+        ops in prop::collection::vec(
+            (any::<bool>(), clustered_key()), 1..2000
+        )
+    ) {
+        let mut xfast = XFastSet::new();
+        let mut expected = BTreeSet::new();
+        for (insert, key) in ops {
+            if insert {
+                prop_assert_eq!(xfast.insert(key), expected.insert(key));
+            } else {
+                prop_assert_eq!(xfast.remove(key), expected.remove(&key));
+            }
+            prop_assert_eq!(xfast.len(), expected.len());
+
+            for probe in [key.wrapping_sub(1), key, key.wrapping_add(1)] {
+                prop_assert_eq!(
+                    xfast.predecessor(probe),
+                    expected.range(..=probe).next_back().cloned()
+                );
+                prop_assert_eq!(
+                    xfast.successor(probe),
+                    expected.range(probe..).next().cloned()
+                );
+            }
+        }
+        prop_assert_eq!(
+            xfast.iter().collect::<Vec<_>>(),
+            expected.iter().cloned().collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn test_empty_containers() {
+    let xmap = XFastMap::<u32, ()>::new();
+    assert!(xmap.is_empty());
+    assert_eq!(xmap.len(), 0);
+
+    let xset = XFastSet::<u64>::new();
+    assert!(xset.is_empty());
+    assert_eq!(xset.len(), 0);
+
+    let ymap = YFastMap::<u32, ()>::new();
+    assert!(ymap.is_empty());
+    assert_eq!(ymap.len(), 0);
+
+    let yset = YFastSet::<u64>::new();
+    assert!(yset.is_empty());
+    assert_eq!(yset.len(), 0);
+
+    // Cleared containers are empty too
+    let mut xmap = XFastMap::new();
+    for i in 0..100u32 {
+        xmap.insert(i, ());
+    }
+    xmap.clear();
+    assert!(xmap.is_empty());
+    assert_eq!(xmap.len(), 0);
+
+    let mut xset = XFastSet::<u64>::new();
+    for i in 0..100 {
+        xset.insert(i);
+    }
+    xset.clear();
+    assert!(xset.is_empty());
+    assert_eq!(xset.len(), 0);
+
+    let mut ymap = (0..1000u32).map(|i| (i, ())).collect::<YFastMap<_, _>>();
+    ymap.clear();
+    assert!(ymap.is_empty());
+    assert_eq!(ymap.len(), 0);
+    assert_eq!(ymap.iter().count(), 0);
+    ymap.insert(7, ());
+    assert_eq!(ymap.len(), 1);
+
+    let mut yset = YFastSet::<u64>::new();
+    for i in 0..1000 {
+        yset.insert(i);
+    }
+    yset.clear();
+    assert!(yset.is_empty());
+    assert_eq!(yset.len(), 0);
+}
@@ -1,23 +1,114 @@
+//! Benchmarks for the bit-vectors in `succinct::select_rank`
+//!
+//! Besides insertion (and removal for `Bits256`), this measures `rank0`,
+//! `rank1`, `select0`, and `select1` on random indices for `BitVec` and `SBitVec`,
+//! for every combination of `SIZES` and `DENSITIES`, and the word shifts
+//! behind `Bits256` for a bit in each word. Run everything with
+//!
+//! ```text
+//! cargo bench -p succinct --bench select_rank
+//! ```
+//!
+//! or pass a filter to run a subset, e.g. `-- select1_50%` or `-- rank1`.
+//! The shifts use AVX2 when it's enabled at compile time, so to compare
+//! against the scalar fallback, run `-- shift_bits256` again with
+//! `RUSTFLAGS="-C target-cpu=x86-64"`.
+
 use std::iter::FromIterator;
 use std::rc::Rc;
 
 use criterion::{
-    black_box, criterion_group, criterion_main, Criterion,
+    black_box, criterion_group, criterion_main, Bencher, Criterion,
     ParameterizedBenchmark,
 };
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 
-use succinct::select_rank::{BitVec, Bits512, SBitVec, SelectRank};
+use succinct::select_rank::{BitVec, Bits256, Bits512, SBitVec, SelectRank};
+
+/// Number of bits in each bit-vector
+const SIZES: [usize; 4] = [1000, 100_000, 1_000_000, 10_000_000];
+/// Percentage of bits that are 1
+const DENSITIES: [u32; 3] = [1, 50, 99];
+
+struct Input<B> {
+    bits: B,
+    ones: usize,
+    zeros: usize,
+}
+
+/// Generate one random bit-vector for each of `SIZES`
+fn inputs<B: FromIterator<bool>>(density: u32) -> Vec<Input<B>> {
+    let mut rng = SmallRng::from_seed([5; 16]);
+    SIZES
+        .iter()
+        .map(|&n| {
+            let bits = (0..n)
+                .map(|_| rng.gen_range(0, 100) < density)
+                .collect::<Vec<_>>();
+            let ones = bits.iter().filter(|&&bit| bit).count();
+            Input {
+                bits: B::from_iter(bits),
+                ones,
+                zeros: n - ones,
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Rank0,
+    Rank1,
+    Select0,
+    Select1,
+}
+
+impl Op {
+    fn name(self) -> &'static str {
+        match self {
+            Op::Rank0 => "rank0",
+            Op::Rank1 => "rank1",
+            Op::Select0 => "select0",
+            Op::Select1 => "select1",
+        }
+    }
+}
+
+/// Benchmark `op` on random (valid) indices of the `n`-bit input
+fn harness<B: SelectRank + 'static>(
+    inputs: Rc<Vec<Input<B>>>,
+    op: Op,
+) -> impl FnMut(&mut Bencher, &usize) + 'static {
+    move |b, &n| {
+        let index = SIZES.iter().position(|&size| size == n).unwrap();
+        let input = &inputs[index];
+        let mut rng = SmallRng::from_seed([7; 16]);
+        match op {
+            Op::Rank0 => {
+                b.iter(|| black_box(input.bits.rank0(rng.gen_range(0, n))))
+            }
+            Op::Rank1 => {
+                b.iter(|| black_box(input.bits.rank1(rng.gen_range(0, n))))
+            }
+            Op::Select0 => b.iter(|| {
+                black_box(input.bits.select0(rng.gen_range(0, input.zeros)))
+            }),
+            Op::Select1 => b.iter(|| {
+                black_box(input.bits.select1(rng.gen_range(0, input.ones)))
+            }),
+        }
+    }
+}
 
 fn criterion_benchmark(c: &mut Criterion) {
     macro_rules! random_insert {
-        ($ty: ty) => {{
+        ($ty: ty, $insert: ident) => {{
             |b, &n| {
                 let mut rng = SmallRng::from_seed([5; 16]);
                 b.iter(|| {
                     let mut bits = <$ty>::new();
                     for _ in 0..n {
-                        bits.insert(
+                        bits.$insert(
                             rng.gen::<usize>() % (bits.len() + 1),
                             rng.gen::<bool>(),
                         );
@@ -30,7 +121,7 @@ fn criterion_benchmark(c: &mut Criterion) {
         "insert_bits",
         ParameterizedBenchmark::new(
             "BitVec",
-            random_insert!(BitVec),
+            random_insert!(BitVec, insert),
             vec![100, 1000, 10000, 100000, 1000000],
         ),
     );
@@ -38,83 +129,63 @@ fn criterion_benchmark(c: &mut Criterion) {
         "insert_small_bits",
         ParameterizedBenchmark::new(
             "Bit512",
-            random_insert!(Bits512),
+            random_insert!(Bits512, insert_bit),
             vec![10, 100, 512],
         ),
     );
-
-    macro_rules! gen {
-        ($ty: ty, $n: expr) => {{
-            let mut rng = SmallRng::from_seed([5; 16]);
-            let mut bits = [0u8; $n / 8 + 1];
-            rng.fill(&mut bits as &mut [u8]);
-
-            <$ty>::from_iter(
-                bits.iter()
-                    .flat_map(|b| (0..8).map(move |i| b & (1 << i) != 0))
-                    .take($n),
-            )
-        }};
-    }
-    let bitvecs = Rc::new([
-        gen!(BitVec, 1000),
-        gen!(BitVec, 10000),
-        gen!(BitVec, 100000),
-        gen!(BitVec, 1000000),
-        gen!(BitVec, 10000000),
-    ]);
-    let sbitvecs = Rc::new([
-        gen!(SBitVec, 1000),
-        gen!(SBitVec, 10000),
-        gen!(SBitVec, 100000),
-        gen!(SBitVec, 1000000),
-        gen!(SBitVec, 10000000),
-    ]);
-
-    macro_rules! func {
-        ($ty: ident, ($n: ident, $rng: ident, $name: ident) => $e: expr) => {{
-            let inputs = Rc::clone(&$ty);
-            move |b, &$n| {
-                let $name = match $n {
-                    1000 => &inputs[0],
-                    10000 => &inputs[1],
-                    100000 => &inputs[2],
-                    1000000 => &inputs[3],
-                    10000000 => &inputs[4],
-                    _ => unreachable!(),
-                };
-                let mut $rng = SmallRng::from_seed([7; 16]);
-                b.iter(|| black_box($e));
+    c.bench_function("insert_remove_bits256", |b| {
+        let mut rng = SmallRng::from_seed([5; 16]);
+        b.iter(|| {
+            let mut bits = Bits256::new();
+            for _ in 0..255 {
+                bits.insert_bit(
+                    rng.gen::<usize>() % (bits.len() + 1),
+                    rng.gen::<bool>(),
+                );
             }
-        }};
-    }
+            while !bits.is_empty() {
+                bits.remove_bit(rng.gen::<usize>() % bits.len());
+            }
+            black_box(bits)
+        });
+    });
 
     c.bench(
-        "rank0",
-        ParameterizedBenchmark::new(
-            "BitVec",
-            func!(bitvecs, (n, rng, bits) => bits.rank0(rng.gen::<usize>() % n)),
-            vec![1000, 10000, 100000, 1000000, 10000000],
-        )
-        .with_function(
-            "SBitVec",
-            func!(sbitvecs, (n, rng, bits) => bits.rank0(rng.gen::<usize>() % n)),
-        ),
-    );
-    c.bench(
-        "select0",
+        "shift_bits256",
         ParameterizedBenchmark::new(
-            "BitVec",
-            func!(bitvecs, (n, rng, bits) =>
-                  bits.select1(rng.gen::<usize>() % (n / 10))),
-            vec![1000, 10000, 100000, 1000000, 10000000],
-        )
-        .with_function(
-            "SBitVec",
-            func!(sbitvecs, (n, rng, bits) =>
-                  bits.select1(rng.gen::<usize>() % (n / 10))),
+            "Bits256",
+            |b, &i| {
+                let mut bits = Bits256::new();
+                for j in 0..255 {
+                    bits.insert_bit(j, j % 3 == 0);
+                }
+                b.iter(|| {
+                    let bit = bits.remove_bit(i);
+                    bits.insert_bit(i, bit);
+                    black_box(&mut bits);
+                });
+            },
+            vec![0, 64, 128, 192],
         ),
     );
+
+    for &density in DENSITIES.iter() {
+        let bitvecs = Rc::new(inputs::<BitVec>(density));
+        let sbitvecs = Rc::new(inputs::<SBitVec>(density));
+
+        let ops = [Op::Rank0, Op::Rank1, Op::Select0, Op::Select1];
+        for &op in ops.iter() {
+            c.bench(
+                &format!("{}_{}%_ones", op.name(), density),
+                ParameterizedBenchmark::new(
+                    "BitVec",
+                    harness(Rc::clone(&bitvecs), op),
+                    SIZES.to_vec(),
+                )
+                .with_function("SBitVec", harness(Rc::clone(&sbitvecs), op)),
+            );
+        }
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
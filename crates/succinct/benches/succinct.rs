@@ -1,5 +1,6 @@
 use std::collections::{BTreeMap, HashMap};
 use std::io::Read;
+use std::iter::FromIterator;
 use std::rc::Rc;
 
 use bstr::BString;
@@ -9,7 +10,7 @@ use criterion::{
 };
 use rand::{rngs::SmallRng, seq::SliceRandom, Rng, SeedableRng};
 
-use succinct::louds::{LoudsTrie, SLoudsTrie};
+use succinct::louds::{LoudsTrie, RadixLoudsTrie, SLoudsTrie};
 
 fn criterion_benchmark(c: &mut Criterion) {
     macro_rules! sorted_insert {
@@ -301,6 +302,106 @@ fn criterion_benchmark(c: &mut Criterion) {
         SLoudsTrie::from(&louds_url[4]),
     ]);
 
+    macro_rules! url_from_iter {
+        ($map: ty, $n: expr) => {{
+            let mut urls = Vec::clone(&urls);
+            let mut rng = SmallRng::from_seed([5; 16]);
+            urls.shuffle(&mut rng);
+            <$map>::from_iter(
+                urls.iter().take($n).enumerate().map(|(i, key)| (key, i)),
+            )
+        }};
+    }
+    let radix_url = Rc::new([
+        url_from_iter!(RadixLoudsTrie<usize>, 100),
+        url_from_iter!(RadixLoudsTrie<usize>, 1000),
+        url_from_iter!(RadixLoudsTrie<usize>, 10000),
+        url_from_iter!(RadixLoudsTrie<usize>, 100000),
+        url_from_iter!(RadixLoudsTrie<usize>, 211708),
+    ]);
+    for (louds, radix) in louds_url.iter().zip(radix_url.iter()) {
+        println!(
+            "{} urls: LoudsTrie uses {} bytes, RadixLoudsTrie uses {} bytes",
+            louds.len(),
+            louds.total_size(),
+            radix.total_size(),
+        );
+    }
+
+    c.bench(
+        "iter_random_var_0_to_100_bytes",
+        ParameterizedBenchmark::new(
+            "iter",
+            {
+                let rc = Rc::clone(&louds_var);
+                move |b, &n| {
+                    let map = match n {
+                        100000 => &rc[3],
+                        1000000 => &rc[4],
+                        _ => unimplemented!(),
+                    };
+                    b.iter(|| {
+                        for (key, value) in map.iter() {
+                            black_box((key, value));
+                        }
+                    })
+                }
+            },
+            vec![100000, 1000000],
+        )
+        .with_function("iter_into", {
+            let rc = Rc::clone(&louds_var);
+            move |b, &n| {
+                let map = match n {
+                    100000 => &rc[3],
+                    1000000 => &rc[4],
+                    _ => unimplemented!(),
+                };
+                b.iter(|| {
+                    map.iter_into(|key, value| {
+                        black_box((key, value));
+                    })
+                })
+            }
+        }),
+    );
+
+    c.bench(
+        "build_random_var_0_to_100_bytes",
+        ParameterizedBenchmark::new(
+            "from_iter",
+            {
+                let rc = Rc::clone(&btree_var);
+                move |b, &n| {
+                    let map = match n {
+                        100000 => &rc[3],
+                        1000000 => &rc[4],
+                        _ => unimplemented!(),
+                    };
+                    b.iter(|| {
+                        SLoudsTrie::from_iter(map.iter().map(|(k, &v)| (k, v)))
+                    })
+                }
+            },
+            vec![100000, 1000000],
+        )
+        .with_function("from_sorted_unique", {
+            let rc = Rc::clone(&btree_var);
+            move |b, &n| {
+                let map = match n {
+                    100000 => &rc[3],
+                    1000000 => &rc[4],
+                    _ => unimplemented!(),
+                };
+                b.iter(|| {
+                    SLoudsTrie::from_sorted_unique(
+                        map.iter().map(|(k, &v)| (k, v)),
+                    )
+                })
+            }
+        }),
+    );
+
     macro_rules! url_get {
         ($name: ident, $e: expr) => {{
             let rc = Rc::clone(&$name);
@@ -334,7 +435,8 @@ fn criterion_benchmark(c: &mut Criterion) {
             vec![100, 1000, 10000, 100000],
         )
         .with_function("LoudsTrie", url_get!(louds_url, true))
-        .with_function("SLoudsTrie", url_get!(slouds_url, true)),
+        .with_function("SLoudsTrie", url_get!(slouds_url, true))
+        .with_function("RadixLoudsTrie", url_get!(radix_url, true)),
     );
     c.bench(
         "get_random_url_false",
@@ -344,7 +446,83 @@ fn criterion_benchmark(c: &mut Criterion) {
             vec![100, 1000, 10000, 100000],
         )
         .with_function("LoudsTrie", url_get!(louds_url, false))
-        .with_function("SLoudsTrie", url_get!(slouds_url, false)),
+        .with_function("SLoudsTrie", url_get!(slouds_url, false))
+        .with_function("RadixLoudsTrie", url_get!(radix_url, false)),
+    );
+
+    macro_rules! string_drop {
+        ($new: expr) => {{
+            |b, &n| {
+                b.iter_with_setup(
+                    || {
+                        let mut louds = $new;
+                        for i in 0..n as u64 {
+                            louds.insert(i.to_be_bytes(), i.to_string());
+                        }
+                        louds
+                    },
+                    drop,
+                )
+            }
+        }};
+    }
+    c.bench(
+        "drop_string_values",
+        ParameterizedBenchmark::new(
+            "LoudsTrie",
+            string_drop!(LoudsTrie::new()),
+            vec![1000000],
+        )
+        .with_function(
+            "ArenaLoudsTrie",
+            string_drop!(LoudsTrie::new_arena_values()),
+        )
+        .sample_size(10),
+    );
+
+    // A million keys with 12-bit values, stored whole and bit-packed
+    let keys = {
+        let mut rng = SmallRng::from_seed([5; 16]);
+        (0..1_000_000)
+            .map(|_| rng.gen::<[u8; 8]>())
+            .collect::<Vec<_>>()
+    };
+    let mut tree = LoudsTrie::new();
+    let mut packed = LoudsTrie::with_value_bits(12);
+    for (i, key) in keys.iter().enumerate() {
+        tree.insert(key, i as u16 & 0xFFF);
+        packed.insert(key, i as u16 & 0xFFF);
+    }
+    println!(
+        "{} keys with 12-bit values: LoudsTrie<u16> uses {} bytes ({} for \
+         values), with_value_bits(12) uses {} bytes ({} for values)",
+        tree.len(),
+        tree.total_size(),
+        tree.size_breakdown().values,
+        packed.total_size(),
+        packed.size_breakdown().values,
+    );
+    let keys = Rc::new(keys);
+    macro_rules! packed_get {
+        ($get: expr) => {{
+            let keys = Rc::clone(&keys);
+            move |b, &n| {
+                let mut rng = SmallRng::from_seed([6; 16]);
+                b.iter(|| {
+                    let key = keys[..n].choose(&mut rng).unwrap();
+                    black_box($get(key))
+                })
+            }
+        }};
+    }
+    c.bench(
+        "get_12_bit_values",
+        ParameterizedBenchmark::new(
+            "LoudsTrie",
+            packed_get!(|key| tree.get(key).copied()),
+            vec![keys.len()],
+        )
+        .with_function("with_value_bits", packed_get!(|key| packed.get(key))),
     );
 }
 
@@ -0,0 +1,61 @@
+//! The allocators that tree nodes can be placed in
+//!
+//! With the `allocator_api` feature (which needs a nightly compiler), these
+//! are `std::alloc::{Allocator, Global}`, so the nodes of a `BitVec` or
+//! `LoudsTrie` can come from an arena. Otherwise, a stand-in `Allocator`
+//! trait is only implemented by `Global`, so the same generic code builds
+//! on stable.
+//!
+//! Buffers owned by a single leaf, such as the `Vec` holding a leaf's
+//! values, still come from the global allocator.
+
+#[cfg(feature = "allocator_api")]
+pub use std::alloc::{Allocator, Global};
+
+#[cfg(not(feature = "allocator_api"))]
+pub use self::stable::{Allocator, Global};
+
+#[cfg(not(feature = "allocator_api"))]
+mod stable {
+    pub trait Sealed {}
+
+    /// A stand-in for `std::alloc::Allocator`, only implemented by `Global`
+    pub trait Allocator: Sealed {}
+
+    /// The global memory allocator
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct Global;
+
+    impl Sealed for Global {}
+    impl Allocator for Global {}
+}
+
+/// Move `value` into a new allocation from `alloc`
+#[cfg(feature = "allocator_api")]
+pub(crate) fn into_raw_in<T, A: Allocator>(value: T, alloc: &A) -> *mut T {
+    Box::into_raw_with_allocator(Box::new_in(value, alloc)).0
+}
+
+/// Move `value` into a new allocation from `alloc`
+#[cfg(not(feature = "allocator_api"))]
+pub(crate) fn into_raw_in<T, A: Allocator>(value: T, _: &A) -> *mut T {
+    Box::into_raw(Box::new(value))
+}
+
+/// Move the value out of an allocation made by `into_raw_in`, freeing it
+///
+/// `ptr` must have come from `into_raw_in` with the same allocator, and must
+/// not be used again.
+#[cfg(feature = "allocator_api")]
+pub(crate) unsafe fn from_raw_in<T, A: Allocator>(ptr: *mut T, alloc: &A) -> T {
+    *Box::from_raw_in(ptr, alloc)
+}
+
+/// Move the value out of an allocation made by `into_raw_in`, freeing it
+///
+/// `ptr` must have come from `into_raw_in` with the same allocator, and must
+/// not be used again.
+#[cfg(not(feature = "allocator_api"))]
+pub(crate) unsafe fn from_raw_in<T, A: Allocator>(ptr: *mut T, _: &A) -> T {
+    *Box::from_raw(ptr)
+}
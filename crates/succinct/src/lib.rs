@@ -1,3 +1,6 @@
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+pub mod alloc;
 pub mod louds;
 pub mod select_rank;
 
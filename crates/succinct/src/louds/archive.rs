@@ -0,0 +1,197 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use bytemuck::Pod;
+
+use super::SLoudsTrie;
+
+/// A set of named `SLoudsTrie`s, written out as a single file
+///
+/// The file starts with a directory giving each trie's name and where its
+/// bytes (in the format of `SLoudsTrie::write_to`) are, so that
+/// `SLoudsArchive::open` can later load any one trie without reading the
+/// others.
+///
+/// Like `SLoudsTrie::write_to`, the tries' values keep their native byte
+/// order, so an archive is only portable between machines of the same
+/// endianness.
+#[derive(Debug, Default)]
+pub struct SLoudsArchive {
+    segments: Vec<(String, Vec<u8>)>,
+}
+
+/// An archive opened by `SLoudsArchive::open`
+#[derive(Debug)]
+pub struct SLoudsArchiveReader<R> {
+    reader: R,
+    // The name, offset (from `start`) and length of each trie
+    directory: Vec<(String, u64, u64)>,
+    start: u64,
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+impl SLoudsArchive {
+    pub fn new() -> SLoudsArchive {
+        SLoudsArchive {
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Add `trie` under `name`, replacing any trie already added under it
+    pub fn add<T: Pod>(&mut self, name: &str, trie: &SLoudsTrie<T>) {
+        let mut bytes = Vec::new();
+        trie.write_to(&mut bytes)
+            .expect("writing to a Vec cannot fail");
+        match self.segments.iter_mut().find(|(n, _)| n == name) {
+            Some(segment) => segment.1 = bytes,
+            None => self.segments.push((name.to_string(), bytes)),
+        }
+    }
+
+    /// Write the number of tries, then each trie's name length, name,
+    /// offset and length, then the tries themselves (all integers are
+    /// little-endian `u64`s)
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&(self.segments.len() as u64).to_le_bytes())?;
+        let mut offset = 0u64;
+        for (name, bytes) in self.segments.iter() {
+            w.write_all(&(name.len() as u64).to_le_bytes())?;
+            w.write_all(name.as_bytes())?;
+            w.write_all(&offset.to_le_bytes())?;
+            w.write_all(&(bytes.len() as u64).to_le_bytes())?;
+            offset += bytes.len() as u64;
+        }
+        for (_, bytes) in self.segments.iter() {
+            w.write_all(bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Read the directory of an archive written by `write_to`, starting at
+    /// the current position of `reader`
+    pub fn open<R: Read + Seek>(
+        mut reader: R,
+    ) -> io::Result<SLoudsArchiveReader<R>> {
+        let mut directory = Vec::new();
+        for _ in 0..read_u64(&mut reader)? {
+            let name_len = read_u64(&mut reader)?;
+            let mut name = Vec::new();
+            reader.by_ref().take(name_len).read_to_end(&mut name)?;
+            if name.len() as u64 != name_len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let name = String::from_utf8(name).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData, "name is not UTF-8")
+            })?;
+            let offset = read_u64(&mut reader)?;
+            let len = read_u64(&mut reader)?;
+            directory.push((name, offset, len));
+        }
+
+        let start = reader.stream_position()?;
+        Ok(SLoudsArchiveReader {
+            reader,
+            directory,
+            start,
+        })
+    }
+}
+
+impl<R: Read + Seek> SLoudsArchiveReader<R> {
+    /// The names of the tries, in the order they were added
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.directory.iter().map(|(name, _, _)| name.as_str())
+    }
+
+    /// Read the trie stored under `name`, if there is one
+    ///
+    /// `T` must be the value type the trie was written with.
+    pub fn load<T: Pod>(
+        &mut self,
+        name: &str,
+    ) -> io::Result<Option<SLoudsTrie<T>>> {
+        let (offset, len) = match self.directory.iter().find(|d| d.0 == name) {
+            Some(&(_, offset, len)) => (offset, len),
+            None => return Ok(None),
+        };
+
+        self.reader.seek(SeekFrom::Start(self.start + offset))?;
+        let mut segment = self.reader.by_ref().take(len);
+        SLoudsTrie::read_from(&mut segment).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_slouds_archive_round_trip() {
+        let days = [
+            ("monday", vec![&b"apple"[..], b"apply", b"banana"]),
+            ("tuesday", vec![]),
+            ("wednesday", vec![&b""[..], b"a", b"apple", b"cherry"]),
+        ];
+        let mut archive = SLoudsArchive::new();
+        for (day, words) in days.iter() {
+            let trie = words
+                .iter()
+                .enumerate()
+                .map(|(i, w)| (w, i as u32 + day.len() as u32))
+                .collect::<SLoudsTrie<_>>();
+            archive.add(day, &trie);
+        }
+        assert_eq!(archive.len(), 3);
+
+        // Put the archive after some other data, to check offsets are
+        // relative to where it starts
+        let mut bytes = b"header".to_vec();
+        archive.write_to(&mut bytes).unwrap();
+        let mut file = Cursor::new(bytes);
+        file.set_position(6);
+        let mut reader = SLoudsArchive::open(file).unwrap();
+        assert_eq!(
+            reader.names().collect::<Vec<_>>(),
+            vec!["monday", "tuesday", "wednesday"]
+        );
+
+        for (day, words) in days.iter().rev() {
+            let trie = reader.load::<u32>(day).unwrap().unwrap();
+            assert_eq!(trie.len(), words.len());
+            for (i, word) in words.iter().enumerate() {
+                let expected = i as u32 + day.len() as u32;
+                assert_eq!(trie.get(word), Some(&expected));
+            }
+            assert_eq!(trie.get(b"appl"), None);
+        }
+        assert!(reader.load::<u32>("thursday").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_slouds_archive_truncated() {
+        let trie = (0..100u32)
+            .map(|i| (i.to_string(), i))
+            .collect::<SLoudsTrie<_>>();
+        let mut archive = SLoudsArchive::new();
+        archive.add("numbers", &trie);
+        let mut bytes = Vec::new();
+        archive.write_to(&mut bytes).unwrap();
+
+        bytes.truncate(bytes.len() - 10);
+        let mut reader = SLoudsArchive::open(Cursor::new(bytes)).unwrap();
+        assert!(reader.load::<u32>("numbers").is_err());
+    }
+}
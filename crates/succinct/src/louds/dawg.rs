@@ -0,0 +1,312 @@
+use std::collections::hash_map::{Entry, HashMap};
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryFrom;
+use std::hash::Hash;
+
+use crate::select_rank::{SBitVec, SelectRank};
+
+/// A static DAWG (a minimized acyclic automaton), which stores keys like an
+/// `SLoudsTrie` but shares the states of equal suffixes
+///
+/// Two states are merged when they have the same value and the same edges,
+/// so keys that share a suffix only share its states if the values under
+/// the suffix are also equal. Because a state can have several parents, the
+/// states are stored in breadth-first order with their out-degrees in unary
+/// (as in LOUDS), but each edge also stores the number of its target.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SDawg<T> {
+    /// `1^d 0` for each state with `d` edges
+    states: SBitVec,
+    is_final: SBitVec,
+    bytes: Vec<u8>,
+    targets: Vec<u32>,
+    values: Vec<T>,
+    len: usize,
+}
+
+/// A state while building, with edges to already-registered states
+#[derive(Hash, Eq, PartialEq)]
+struct State<T> {
+    value: Option<T>,
+    edges: Vec<(u8, usize)>,
+}
+
+impl<T> State<T> {
+    fn new() -> State<T> {
+        State {
+            value: None,
+            edges: Vec::new(),
+        }
+    }
+}
+
+/// The incremental construction of Daciuk et al. for sorted keys
+struct Builder<T> {
+    // Every distinct finished state, numbered in the order they finish
+    register: HashMap<State<T>, usize>,
+    // The unfinished states along the last key (starting at the root),
+    // along with the byte leading to each
+    path: Vec<(u8, State<T>)>,
+    last: Vec<u8>,
+    len: usize,
+}
+
+impl<T: Eq + Hash> Builder<T> {
+    fn new() -> Builder<T> {
+        Builder {
+            register: HashMap::new(),
+            path: vec![(0, State::new())],
+            last: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Add a key, which must be greater than every key added so far
+    fn insert(&mut self, key: &[u8], value: T) {
+        debug_assert!(self.len == 0 || key > &self.last[..]);
+        let common = key
+            .iter()
+            .zip(self.last.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        // No later key can pass through the states past the common prefix,
+        // so they are final and can be merged
+        self.finish_path(common);
+        for &byte in key[common..].iter() {
+            self.path.push((byte, State::new()));
+        }
+        self.path.last_mut().unwrap().1.value = Some(value);
+        self.last = key.to_vec();
+        self.len += 1;
+    }
+
+    /// Register the states on the path deeper than `depth`
+    fn finish_path(&mut self, depth: usize) {
+        while self.path.len() > depth + 1 {
+            let (byte, state) = self.path.pop().unwrap();
+            let id = self.register(state);
+            self.path.last_mut().unwrap().1.edges.push((byte, id));
+        }
+    }
+
+    fn register(&mut self, state: State<T>) -> usize {
+        let next = self.register.len();
+        match self.register.entry(state) {
+            Entry::Occupied(o) => *o.get(),
+            Entry::Vacant(v) => *v.insert(next),
+        }
+    }
+
+    fn finish(mut self) -> SDawg<T> {
+        self.finish_path(0);
+        let (_, root) = self.path.pop().unwrap();
+        let root = self.register(root);
+
+        let mut by_id = Vec::new();
+        by_id.resize_with(self.register.len(), || None);
+        for (state, id) in self.register.drain() {
+            by_id[id] = Some(state);
+        }
+
+        // Renumber the states breadth-first from the root
+        let mut renumbered = vec![None; by_id.len()];
+        renumbered[root] = Some(0);
+        let mut queue = VecDeque::from(vec![root]);
+        let mut num_states = 1;
+
+        let mut states = Vec::new();
+        let mut is_final = Vec::new();
+        let mut bytes = Vec::new();
+        let mut targets = Vec::new();
+        let mut values = Vec::new();
+        while let Some(id) = queue.pop_front() {
+            let state = by_id[id].take().unwrap();
+            for (byte, target) in state.edges {
+                let number = *renumbered[target].get_or_insert_with(|| {
+                    queue.push_back(target);
+                    num_states += 1;
+                    num_states - 1
+                });
+                states.push(true);
+                bytes.push(byte);
+                targets
+                    .push(u32::try_from(number).expect("too many DAWG states"));
+            }
+            states.push(false);
+
+            is_final.push(state.value.is_some());
+            values.extend(state.value);
+        }
+
+        SDawg {
+            states: states.into_iter().collect(),
+            is_final: is_final.into_iter().collect(),
+            bytes,
+            targets,
+            values,
+            len: self.len,
+        }
+    }
+}
+
+impl<T: Eq + Hash> SDawg<T> {
+    /// Build a minimized DAWG from (key, value) pairs in any order
+    ///
+    /// Like `SLoudsTrie::from_iter`, a repeated key keeps its last value.
+    pub fn from_iter_minimized<K, I>(input: I) -> SDawg<T>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, T)>,
+    {
+        let sorted = input
+            .into_iter()
+            .map(|(key, value)| (key.as_ref().to_vec(), value))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut builder = Builder::new();
+        for (key, value) in sorted {
+            builder.insert(&key, value);
+        }
+        builder.finish()
+    }
+}
+
+impl<T> SDawg<T> {
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of keys
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The number of states after minimization
+    pub fn num_states(&self) -> usize {
+        self.is_final.len()
+    }
+
+    pub fn total_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.states.total_size()
+            + self.is_final.total_size()
+            + self.bytes.capacity() * std::mem::size_of::<u8>()
+            + self.targets.capacity() * std::mem::size_of::<u32>()
+            + self.values.capacity() * std::mem::size_of::<T>()
+    }
+
+    /// Return whether `key` is stored, without reading its value
+    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> bool {
+        match self.find(key.as_ref()) {
+            Some(state) => self.is_final.get_bit(state),
+            None => false,
+        }
+    }
+
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<&T> {
+        let state = self.find(key.as_ref())?;
+        if self.is_final.get_bit(state) {
+            Some(&self.values[self.is_final.rank1(state)])
+        } else {
+            None
+        }
+    }
+
+    /// Find the state reached by `key`, if there is one
+    fn find(&self, key: &[u8]) -> Option<usize> {
+        let mut state = 0;
+        for byte in key.iter() {
+            let edges = self.edges(state);
+            let i = self.bytes[edges.clone()].binary_search(byte).ok()?;
+            state = self.targets[edges.start + i] as usize;
+        }
+        Some(state)
+    }
+
+    /// The indices (into `bytes` and `targets`) of `state`'s edges
+    fn edges(&self, state: usize) -> std::ops::Range<usize> {
+        // The `i`th zero ends state `i`, and every one before it is an edge
+        let start = match state {
+            0 => 0,
+            _ => self.states.select0(state - 1) + 1 - state,
+        };
+        start..self.states.select0(state) - state
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::louds::SLoudsTrie;
+
+    #[test]
+    fn test_dawg_get() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let dawg = SDawg::from_iter_minimized(keys.iter().map(|k| (k, k[0])));
+        assert_eq!(dawg.len(), keys.len());
+        for key in keys.iter() {
+            assert_eq!(dawg.get(key), Some(&key[0]));
+        }
+        assert_eq!(dawg.get(b"belarus"), None);
+        assert_eq!(dawg.get(b"dh"), None);
+        assert_eq!(dawg.get(b""), None);
+        assert!(!dawg.contains_key(b"dj"));
+
+        // Suffixes are only shared between equal values
+        let dawg = SDawg::from_iter_minimized(vec![
+            ("cats", 1),
+            ("dogs", 2),
+            ("hats", 1),
+            ("cats", 3),
+        ]);
+        assert_eq!(dawg.len(), 3);
+        assert_eq!(dawg.get("cats"), Some(&3));
+        assert_eq!(dawg.get("dogs"), Some(&2));
+        assert_eq!(dawg.get("hats"), Some(&1));
+
+        let empty = SDawg::from_iter_minimized(Vec::<(&[u8], ())>::new());
+        assert!(empty.is_empty());
+        assert_eq!(empty.num_states(), 1);
+        assert_eq!(empty.get(b""), None);
+    }
+
+    #[test]
+    fn test_dawg_inflections() {
+        let onsets = ["b", "d", "fl", "g", "k", "l", "m", "pr", "s", "st", "t"];
+        let vowels = ["a", "e", "i", "o", "u", "ea"];
+        let codas = ["ck", "l", "mp", "nd", "rk", "sh", "t"];
+        let suffixes = ["", "s", "ed", "ing", "er", "ers", "able"];
+
+        let mut words = Vec::new();
+        for onset in onsets.iter() {
+            for vowel in vowels.iter() {
+                for coda in codas.iter() {
+                    for (i, suffix) in suffixes.iter().enumerate() {
+                        let word = [*onset, vowel, coda, suffix].concat();
+                        words.push((word, i as u8));
+                    }
+                }
+            }
+        }
+        let slouds = words.iter().cloned().collect::<SLoudsTrie<_>>();
+        let dawg = SDawg::from_iter_minimized(words.iter().cloned());
+        assert_eq!(dawg.len(), slouds.len());
+        assert!(dawg.total_size() * 8 < slouds.total_size());
+
+        for (word, i) in words.iter() {
+            assert_eq!(dawg.get(word), Some(i));
+            let stem = &word[..word.len() - suffixes[*i as usize].len()];
+            for probe in [&stem[..1], stem, &format!("{}x", word)].iter() {
+                assert_eq!(dawg.get(probe), slouds.get(probe));
+                assert_eq!(
+                    dawg.contains_key(probe),
+                    slouds.contains_key(probe)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::convert::TryInto;
+use std::iter::FromIterator;
+
+use super::LoudsTrie;
+
+/// An integer that can key an `IntKeyTrie`
+pub trait IntKey: Copy + Ord {
+    /// The fixed-width encoding of the integer
+    type Bytes: AsRef<[u8]>;
+
+    /// Encode the integer so that byte-wise order matches numeric order
+    fn to_bytes(self) -> Self::Bytes;
+
+    /// Decode bytes written by `to_bytes`
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_unsigned_key {
+    ($($t:ty),*) => {$(
+        impl IntKey for $t {
+            type Bytes = [u8; std::mem::size_of::<$t>()];
+
+            fn to_bytes(self) -> Self::Bytes {
+                self.to_be_bytes()
+            }
+
+            fn from_bytes(bytes: &[u8]) -> Self {
+                <$t>::from_be_bytes(bytes.try_into().unwrap())
+            }
+        }
+    )*};
+}
+
+// Flipping the sign bit makes negative numbers sort before positive ones
+macro_rules! impl_signed_key {
+    ($($t:ty => $u:ty),*) => {$(
+        impl IntKey for $t {
+            type Bytes = [u8; std::mem::size_of::<$t>()];
+
+            fn to_bytes(self) -> Self::Bytes {
+                ((self as $u) ^ (1 << (<$u>::BITS - 1))).to_be_bytes()
+            }
+
+            fn from_bytes(bytes: &[u8]) -> Self {
+                let bits = <$u>::from_be_bytes(bytes.try_into().unwrap());
+                (bits ^ (1 << (<$u>::BITS - 1))) as $t
+            }
+        }
+    )*};
+}
+
+impl_unsigned_key!(u8, u16, u32, u64, u128, usize);
+impl_signed_key!(
+    i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize
+);
+
+/// A `LoudsTrie` keyed by integers
+///
+/// Keys are stored big-endian (with the sign bit flipped for signed
+/// integers), so the trie's byte order is the integers' numeric order.
+pub struct IntKeyTrie<I, T> {
+    trie: LoudsTrie<T>,
+    _key: std::marker::PhantomData<I>,
+}
+
+impl<I: IntKey, T> IntKeyTrie<I, T> {
+    pub fn new() -> IntKeyTrie<I, T> {
+        IntKeyTrie {
+            trie: LoudsTrie::new(),
+            _key: std::marker::PhantomData,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.trie.total_size()
+    }
+
+    /// Insert a key into the trie, returning the value it replaced
+    pub fn insert(&mut self, key: I, value: T) -> Option<T> {
+        self.trie.insert(key.to_bytes(), value)
+    }
+
+    pub fn contains_key(&self, key: I) -> bool {
+        self.trie.contains_key(key.to_bytes())
+    }
+
+    pub fn get(&self, key: I) -> Option<&T> {
+        self.trie.get(key.to_bytes())
+    }
+
+    pub fn get_mut(&mut self, key: I) -> Option<&mut T> {
+        self.trie.get_mut(key.to_bytes())
+    }
+
+    /// Iterate over the (key, value) pairs in increasing key order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (I, &T)> + '_ {
+        self.trie
+            .iter()
+            .map(|(key, value)| (I::from_bytes(&key), value))
+    }
+
+    /// The underlying byte-keyed trie
+    pub fn as_louds(&self) -> &LoudsTrie<T> {
+        &self.trie
+    }
+}
+
+impl<I: IntKey, T> Default for IntKeyTrie<I, T> {
+    fn default() -> IntKeyTrie<I, T> {
+        IntKeyTrie::new()
+    }
+}
+
+impl<I: IntKey, T> FromIterator<(I, T)> for IntKeyTrie<I, T> {
+    fn from_iter<It>(input: It) -> Self
+    where
+        It: IntoIterator<Item = (I, T)>,
+    {
+        let mut trie = IntKeyTrie::new();
+        for (key, value) in input.into_iter() {
+            trie.insert(key, value);
+        }
+        trie
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_int_key_trie_order() {
+        let numbers: [u32; 12] = [
+            9424, 16777216, 54780, 0, 255, 256, 63521, 4294967295, 770, 65536,
+            1, 20443,
+        ];
+        let trie = numbers
+            .iter()
+            .map(|&k| (k, k / 2))
+            .collect::<IntKeyTrie<_, _>>();
+        assert_eq!(trie.len(), numbers.len());
+
+        let mut sorted = numbers.to_vec();
+        sorted.sort();
+        let keys = trie.iter().map(|(k, _)| k).collect::<Vec<_>>();
+        assert_eq!(keys, sorted);
+        assert!(trie
+            .iter()
+            .rev()
+            .map(|(k, _)| k)
+            .eq(sorted.iter().rev().cloned()));
+        for k in numbers.iter() {
+            assert_eq!(trie.get(*k), Some(&(k / 2)));
+        }
+        assert!(!trie.contains_key(2));
+
+        let signed = [-300i16, 5, i16::MIN, -1, 0, i16::MAX, 256, -256]
+            .iter()
+            .map(|&k| (k, ()))
+            .collect::<IntKeyTrie<_, _>>();
+        let keys = signed.iter().map(|(k, _)| k).collect::<Vec<_>>();
+        assert_eq!(keys, vec![i16::MIN, -300, -256, -1, 0, 5, 256, i16::MAX]);
+    }
+}
@@ -0,0 +1,87 @@
+use super::LoudsTrie;
+
+/// Maps byte strings to dense `u32` IDs and back
+///
+/// The trie finds the ID of a string, while a vector indexed by ID holds
+/// the strings for the reverse direction. IDs are assigned in order of
+/// first insertion and never change.
+pub struct StringInterner {
+    ids: LoudsTrie<u32>,
+    strings: Vec<Box<[u8]>>,
+}
+
+impl StringInterner {
+    pub fn new() -> StringInterner {
+        StringInterner {
+            ids: LoudsTrie::new(),
+            strings: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Return the ID of `string`, assigning the next ID if it is new
+    pub fn intern<K: AsRef<[u8]>>(&mut self, string: K) -> u32 {
+        let string = string.as_ref();
+        let next = self.strings.len() as u32;
+        let id = *self.ids.insert_or_get(string, next);
+        if id == next {
+            self.strings.push(string.into());
+        }
+        id
+    }
+
+    /// Return the ID of `string`, if it has been interned
+    pub fn get<K: AsRef<[u8]>>(&self, string: K) -> Option<u32> {
+        self.ids.get(string).cloned()
+    }
+
+    /// Return the string with the given ID
+    pub fn resolve(&self, id: u32) -> Option<&[u8]> {
+        self.strings.get(id as usize).map(|s| &s[..])
+    }
+}
+
+impl Default for StringInterner {
+    fn default() -> StringInterner {
+        StringInterner::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interner_round_trip() {
+        let mut interner = StringInterner::new();
+        assert_eq!(interner.resolve(0), None);
+
+        let words = (0..2000u32)
+            .map(|i| format!("w{}", i * 7919 % 1000).into_bytes())
+            .collect::<Vec<_>>();
+        let ids = words.iter().map(|w| interner.intern(w)).collect::<Vec<_>>();
+        assert_eq!(interner.len(), 1000);
+
+        for (word, &id) in words.iter().zip(ids.iter()) {
+            assert!(id < 1000);
+            assert_eq!(interner.resolve(id), Some(&word[..]));
+            assert_eq!(interner.get(word), Some(id));
+            // Interning again keeps the same ID
+            assert_eq!(interner.intern(word), id);
+        }
+        assert_eq!(interner.len(), 1000);
+
+        // IDs are assigned in order of first insertion
+        assert_eq!(interner.intern(b""), 1000);
+        assert_eq!(interner.resolve(1000), Some(&b""[..]));
+        assert_eq!(interner.resolve(1001), None);
+        assert_eq!(interner.get(b"missing"), None);
+    }
+}
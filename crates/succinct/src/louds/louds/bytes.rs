@@ -1,27 +1,60 @@
+use crate::alloc::{self, Allocator, Global};
 use crate::tree::{Leaf, Tree};
 use crate::utils::binary_search_rank_equal;
 
-pub(crate) type ByteTree = Tree<ByteLeaf>;
+/// Stores the edge bytes of a `LoudsTrie` in LOUDS order
+///
+/// The children of a node are stored contiguously and sorted, so
+/// `child_number` only has to search `degree` bytes.
+pub trait BranchIndex<A: Allocator = Global> {
+    /// Create an empty index whose memory comes from `alloc`
+    fn new_in(alloc: A) -> Self;
 
-impl ByteTree {
-    pub(crate) fn child_number(
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The memory used by the index, in bytes
+    fn total_size(&self) -> usize;
+
+    fn get(&self, index: usize) -> u8;
+
+    fn insert(&mut self, index: usize, byte: u8);
+
+    /// Search the `degree` bytes starting at `index` for `needle`, returning
+    /// the number of them less than `needle` and whether it was found
+    fn child_number(
         &self,
         index: usize,
         degree: usize,
         needle: u8,
-    ) -> (usize, bool) {
-        let (leaf, index) = self.get_leaf(index);
-        leaf.child_number(index, degree, needle)
+    ) -> (usize, bool);
+}
+
+/// The default `BranchIndex`, which stores bytes in a B-tree of leaves
+pub struct ByteTree<A: Allocator = Global>(Tree<ByteLeaf, A>);
+
+impl ByteTree {
+    pub fn new() -> ByteTree {
+        ByteTree::new_in(Global)
     }
+}
 
-    #[cfg(test)]
-    pub(crate) fn get(&self, index: usize) -> u8 {
-        let (leaf, index) = self.get_leaf(index);
-        leaf.bytes[index]
+impl Default for ByteTree {
+    fn default() -> Self {
+        ByteTree::new()
     }
+}
 
+impl<A: Allocator> ByteTree<A> {
     pub(crate) fn iter(&self) -> impl Iterator<Item = u8> + '_ {
-        let mut leaf = Some(self.get_leaf(0).0);
+        let mut leaf = if self.0.len() == 0 {
+            None
+        } else {
+            Some(self.0.get_leaf(0).0)
+        };
         std::iter::from_fn(move || {
             leaf.and_then(|l| {
                 std::mem::replace(&mut leaf, unsafe { l.next.as_ref() })
@@ -32,6 +65,39 @@ impl ByteTree {
     }
 }
 
+impl<A: Allocator> BranchIndex<A> for ByteTree<A> {
+    fn new_in(alloc: A) -> ByteTree<A> {
+        ByteTree(Tree::new_in(alloc))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn total_size(&self) -> usize {
+        self.0.total_size()
+    }
+
+    fn get(&self, index: usize) -> u8 {
+        let (leaf, index) = self.0.get_leaf(index);
+        leaf.bytes[index]
+    }
+
+    fn insert(&mut self, index: usize, byte: u8) {
+        self.0.insert(index, byte);
+    }
+
+    fn child_number(
+        &self,
+        index: usize,
+        degree: usize,
+        needle: u8,
+    ) -> (usize, bool) {
+        let (leaf, index) = self.0.get_leaf(index);
+        leaf.child_number(index, degree, needle)
+    }
+}
+
 pub(crate) struct ByteLeaf {
     len: u16,
     bytes: [u8; 510],
@@ -40,6 +106,7 @@ pub(crate) struct ByteLeaf {
 
 impl Leaf for ByteLeaf {
     type Output = u8;
+    type Config = ();
     const CAPACITY: usize = 510;
 
     fn is_full(&self) -> bool {
@@ -54,7 +121,7 @@ impl Leaf for ByteLeaf {
         std::mem::size_of::<Self>()
     }
 
-    fn new(value: u8) -> Self {
+    fn new(_: (), value: u8) -> Self {
         let mut leaf = ByteLeaf {
             len: 1,
             bytes: [0; 510],
@@ -64,18 +131,18 @@ impl Leaf for ByteLeaf {
         leaf
     }
 
-    fn split(&mut self) -> Box<Self> {
+    fn split<A: Allocator>(&mut self, alloc: &A) -> *mut Self {
         debug_assert!(self.is_full());
 
         self.len = 255;
-        let mut leaf = Box::new(ByteLeaf {
+        let mut leaf = ByteLeaf {
             len: 255,
             bytes: [0; Self::CAPACITY],
             next: self.next,
-        });
+        };
         self.bytes[255..].swap_with_slice(&mut leaf.bytes[..255]);
-        self.next = Box::into_raw(leaf);
-        unsafe { Box::from_raw(self.next) }
+        self.next = alloc::into_raw_in(leaf, alloc);
+        self.next
     }
 
     fn insert(&mut self, index: usize, byte: u8) {
@@ -128,18 +195,18 @@ impl ByteLeaf {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::alloc::Global;
 
     #[test]
     fn test_byteleaf_split() {
-        let next = Box::into_raw(Box::new(ByteLeaf::new(0)));
+        let next = Box::into_raw(Box::new(ByteLeaf::new((), 0)));
         let mut leaf = ByteLeaf {
             len: 510,
             bytes: [0xAA; 510],
             next: next,
         };
-        let new = leaf.split();
-        let raw = Box::into_raw(new);
-        let new = unsafe { Box::from_raw(raw) };
+        let raw = leaf.split(&Global);
+        let new = unsafe { alloc::from_raw_in(raw, &Global) };
 
         assert_eq!(leaf.next, raw);
         assert_eq!(leaf.len, 255);
@@ -158,7 +225,7 @@ mod test {
 
     #[test]
     fn test_byteleaf_insert_begin() {
-        let mut leaf = ByteLeaf::new(255);
+        let mut leaf = ByteLeaf::new((), 255);
         let mut expected = vec![255u8];
 
         for i in 0..509 {
@@ -173,7 +240,7 @@ mod test {
 
     #[test]
     fn test_byteleaf_insert_end() {
-        let mut leaf = ByteLeaf::new(255);
+        let mut leaf = ByteLeaf::new((), 255);
         let mut expected = vec![255u8];
 
         for i in 0..509 {
@@ -188,7 +255,7 @@ mod test {
 
     #[test]
     fn test_byteleaf_insert_middle() {
-        let mut leaf = ByteLeaf::new(255);
+        let mut leaf = ByteLeaf::new((), 255);
         let mut expected = vec![255u8];
 
         for i in 0..509 {
@@ -1,14 +1,112 @@
+use crate::alloc::{Allocator, Global};
 use crate::select_rank::{BitVec, SelectRank};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
 use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ops::Range;
 
-mod bytes;
-mod values;
+pub(super) mod bytes;
+pub(super) mod values;
 
-pub struct LoudsTrie<T> {
-    pub(super) trie: BitVec,
-    pub(super) has_value: BitVec,
-    pub(super) bytes: bytes::ByteTree,
-    pub(super) values: values::ValueTree<T>,
+pub use bytes::{BranchIndex, ByteTree};
+pub use values::{PackedValue, PackedValues, TrieValues, ValueStore};
+
+use values::sealed::Store;
+
+/// A dynamic LOUDS trie, storing its edge bytes in the `BranchIndex` `B`
+/// and its values in the `ValueStore` `V`
+///
+/// The LOUDS bits are allocated in `A`. Tries created by `new_in` also
+/// keep their edge bytes and values there.
+pub struct LoudsTrie<T, B = ByteTree, V = TrieValues<T>, A: Allocator = Global>
+{
+    pub(super) trie: BitVec<A>,
+    pub(super) has_value: BitVec<A>,
+    pub(super) bytes: B,
+    pub(super) values: V,
+    marker: PhantomData<T>,
+}
+
+/// The error returned by `try_insert` when the key already exists
+#[derive(Debug, Eq, PartialEq)]
+pub struct OccupiedError<T> {
+    /// The value that was not inserted
+    pub value: T,
+}
+
+impl<T> fmt::Display for OccupiedError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "key already exists")
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for OccupiedError<T> {}
+
+/// A key's place in a trie, returned by `reserve_key` to be filled later
+///
+/// Creating nodes moves the nodes after them, so a slot is only valid
+/// until a key that adds nodes is inserted or reserved.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ValueSlot {
+    node_pos: usize,
+    // The number of nodes when the slot was made, to catch stale slots
+    num_nodes: usize,
+}
+
+/// A node found by `descend_prefix`, from which `insert_suffix` can insert
+/// keys without descending the prefix again
+///
+/// Keys inserted through the cursor only create nodes below it, which never
+/// move it, so `insert_suffix` keeps the cursor valid. Any other insertion
+/// that creates nodes makes it stale.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PublicCursor {
+    cursor: Cursor,
+    // The number of nodes the cursor was last valid for, as in `ValueSlot`
+    num_nodes: usize,
+}
+
+/// The memory used by each part of a trie, in bytes
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SizeBreakdown {
+    /// The LOUDS bits encoding the shape of the trie
+    pub trie: usize,
+    /// The bits marking which nodes hold a value
+    pub has_value: usize,
+    /// The edge bytes
+    pub bytes: usize,
+    /// The stored values
+    pub values: usize,
+    /// The struct itself and any auxiliary indexes
+    pub overhead: usize,
+}
+
+impl SizeBreakdown {
+    /// The total size, as returned by `total_size`
+    pub fn total(&self) -> usize {
+        self.trie + self.has_value + self.bytes + self.values + self.overhead
+    }
+}
+
+/// Shape statistics for a `LoudsTrie`, as returned by `stats`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrieStats {
+    /// The number of nodes, including the root
+    pub num_nodes: usize,
+    /// The number of edges (one byte is stored per edge)
+    pub num_edges: usize,
+    /// The number of nodes holding a value
+    pub num_values: usize,
+    /// The number of children of the root
+    pub root_degree: usize,
+    /// The total length of all keys
+    pub key_bytes: usize,
+    /// The average number of children of the non-leaf nodes
+    pub avg_branching: f64,
+    /// The memory used by the trie, as in `total_size`
+    pub total_size: usize,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -18,7 +116,7 @@ struct Cursor {
 }
 
 impl Cursor {
-    fn from_bit_pos(trie: &BitVec, pos: usize) -> Cursor {
+    fn from_bit_pos<A: Allocator>(trie: &BitVec<A>, pos: usize) -> Cursor {
         Cursor {
             bit_pos: pos,
             node_pos: trie.rank0(pos),
@@ -28,36 +126,375 @@ impl Cursor {
 
 impl<T> LoudsTrie<T> {
     pub fn new() -> LoudsTrie<T> {
+        LoudsTrie::with_branch_index()
+    }
+
+    /// Create an empty trie that keeps its values in one contiguous arena
+    ///
+    /// Inserting a key shifts the values of every later key, but dropping
+    /// the trie frees the values at once and `get` reads straight from the
+    /// arena, which suits large values such as `String`s.
+    pub fn new_arena_values() -> LoudsTrie<T> {
+        LoudsTrie::with_values(TrieValues::arena(), Global)
+    }
+
+    /// Like `from_iter`, but also return the number of overwritten keys
+    pub fn from_iter_reporting<K, I>(input: I) -> (LoudsTrie<T>, usize)
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, T)>,
+    {
+        let mut trie = LoudsTrie::new();
+        let mut overwritten = 0;
+        for (key, value) in input.into_iter() {
+            if trie.insert(key.as_ref(), value).is_some() {
+                overwritten += 1;
+            }
+        }
+        (trie, overwritten)
+    }
+}
+
+impl<T, A: Allocator + Clone> LoudsTrie<T, ByteTree<A>, TrieValues<T, A>, A> {
+    /// Create an empty trie whose nodes, edge bytes and values are all
+    /// allocated in `alloc`
+    pub fn new_in(alloc: A) -> Self {
+        LoudsTrie::with_values(TrieValues::tree_in(alloc.clone()), alloc)
+    }
+}
+
+impl<T, B: BranchIndex> LoudsTrie<T, B> {
+    /// Create an empty trie that stores its edge bytes in a `B`
+    pub fn with_branch_index() -> LoudsTrie<T, B> {
+        LoudsTrie::with_values(TrieValues::tree_in(Global), Global)
+    }
+}
+
+impl<T: PackedValue> LoudsTrie<T, ByteTree, PackedValues<T>> {
+    /// Create an empty trie that packs each value into `bits` bits
+    ///
+    /// This shrinks large dictionaries of small codes. The values are
+    /// unpacked when read, so `get` returns them by value, and there are no
+    /// methods that borrow them. Inserting a value that does not fit in
+    /// `bits` bits panics.
+    ///
+    /// Panics unless `bits` is between 1 and 64.
+    pub fn with_value_bits(bits: u32) -> Self {
+        LoudsTrie::with_values(PackedValues::new(bits), Global)
+    }
+}
+
+impl<T, B, V, A> LoudsTrie<T, B, V, A>
+where
+    B: BranchIndex<A>,
+    V: ValueStore<T>,
+    A: Allocator,
+{
+    fn with_values(values: V, alloc: A) -> LoudsTrie<T, B, V, A>
+    where
+        A: Clone,
+    {
         let mut louds = LoudsTrie {
-            trie: BitVec::new(),
-            has_value: BitVec::new(),
-            bytes: bytes::ByteTree::new(),
-            values: values::ValueTree::new(),
+            trie: BitVec::new_in(alloc.clone()),
+            has_value: BitVec::new_in(alloc.clone()),
+            bytes: B::new_in(alloc),
+            values,
+            marker: PhantomData,
         };
         louds.trie.insert(0, false);
         louds.has_value.insert(0, false);
         louds
     }
 
+    pub fn allocator(&self) -> &A {
+        self.trie.allocator()
+    }
+
     pub fn total_size(&self) -> usize {
-        std::mem::size_of::<Self>()
-            + self.bytes.total_size()
-            + self.trie.total_size()
-            + self.has_value.total_size()
-            + self.values.total_size()
+        self.size_breakdown().total()
+    }
+
+    /// Split `total_size` into the memory used by each part of the trie
+    pub fn size_breakdown(&self) -> SizeBreakdown {
+        SizeBreakdown {
+            trie: self.trie.total_size(),
+            has_value: self.has_value.total_size(),
+            bytes: self.bytes.total_size(),
+            values: self.values.total_size(),
+            overhead: std::mem::size_of::<Self>(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     pub fn len(&self) -> usize {
         self.values.len()
     }
 
+    /// Compute shape statistics for the trie in a single traversal
+    pub fn stats(&self) -> TrieStats {
+        let mut num_nodes = 0;
+        let mut num_edges = 0;
+        let mut num_inner = 0;
+        let mut key_bytes = 0;
+
+        // (bit position, depth) of the nodes left to visit
+        let mut stack = vec![(0, 0)];
+        while let Some((bit_pos, depth)) = stack.pop() {
+            let cursor = Cursor::from_bit_pos(&self.trie, bit_pos);
+            num_nodes += 1;
+            if self.has_value.get_bit(cursor.node_pos) {
+                key_bytes += depth;
+            }
+
+            let degree = self.degree(bit_pos);
+            if degree > 0 {
+                num_inner += 1;
+                num_edges += degree;
+                for i in 0..degree {
+                    stack.push((self.child(bit_pos, i).bit_pos, depth + 1));
+                }
+            }
+        }
+
+        TrieStats {
+            num_nodes,
+            num_edges,
+            num_values: self.has_value.num_ones() as usize,
+            root_degree: self.degree(0),
+            key_bytes,
+            avg_branching: if num_inner == 0 {
+                0.0
+            } else {
+                num_edges as f64 / num_inner as f64
+            },
+            total_size: self.total_size(),
+        }
+    }
+
     pub fn insert<K: AsRef<[u8]>>(&mut self, key: K, value: T) -> Option<T> {
-        let mut cursor = Cursor {
+        self.insert_full(key, value).1
+    }
+
+    /// Insert a key into the trie, returning whether the key's node was
+    /// newly created, along with the value it replaced (if any)
+    ///
+    /// A key can be new to the trie without creating a node, if it is a
+    /// prefix of an existing key.
+    pub fn insert_full<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: T,
+    ) -> (bool, Option<T>) {
+        let (created, cursor) = self.find_or_create(key.as_ref());
+        (created, self.set_value(cursor.node_pos, value))
+    }
+
+    /// Return a cursor at the node for `prefix`, if there is one
+    ///
+    /// The prefix does not need a value, but its node must exist (for
+    /// instance from inserting or reserving a key that starts with it).
+    pub fn descend_prefix<K: AsRef<[u8]>>(
+        &self,
+        prefix: K,
+    ) -> Option<PublicCursor> {
+        let cursor = self.find(prefix.as_ref().iter().cloned())?;
+        Some(PublicCursor {
+            cursor,
+            num_nodes: self.has_value.len(),
+        })
+    }
+
+    /// Insert the key made of `base`'s prefix followed by `suffix`, only
+    /// descending through `suffix`, and return the value it replaced
+    ///
+    /// `base` stays valid for further calls. Panics if nodes have been
+    /// created other than through `base` since it was made.
+    pub fn insert_suffix(
+        &mut self,
+        base: &mut PublicCursor,
+        suffix: &[u8],
+        value: T,
+    ) -> Option<T> {
+        assert_eq!(
+            base.num_nodes,
+            self.has_value.len(),
+            "cursor is stale: the trie has gained nodes since it was made"
+        );
+        let (_, cursor) = self.find_or_create_from(base.cursor.clone(), suffix);
+        base.num_nodes = self.has_value.len();
+        self.set_value(cursor.node_pos, value)
+    }
+
+    /// Create the nodes for `key` without giving it a value
+    ///
+    /// The key stays absent (to `get`, `len` and so on) until the returned
+    /// slot is passed to `fill`.
+    pub fn reserve_key<K: AsRef<[u8]>>(&mut self, key: K) -> ValueSlot {
+        let (_, cursor) = self.find_or_create(key.as_ref());
+        ValueSlot {
+            node_pos: cursor.node_pos,
+            num_nodes: self.has_value.len(),
+        }
+    }
+
+    /// Set the value of a key reserved by `reserve_key`, without searching
+    /// for the key again, and return the value it replaced
+    ///
+    /// Panics if nodes have been created since the slot was reserved.
+    pub fn fill(&mut self, slot: ValueSlot, value: T) -> Option<T> {
+        assert_eq!(
+            slot.num_nodes,
+            self.has_value.len(),
+            "slot is stale: the trie has gained nodes since it was reserved"
+        );
+        self.set_value(slot.node_pos, value)
+    }
+
+    /// Insert a key into the trie, unless it is already present
+    pub fn try_insert<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: T,
+    ) -> Result<(), OccupiedError<T>> {
+        if self.contains_key(key.as_ref()) {
+            Err(OccupiedError { value })
+        } else {
+            self.insert(key, value);
+            Ok(())
+        }
+    }
+
+    /// Return whether `key` is stored, without reading its value
+    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> bool {
+        match self.find(key.as_ref().iter().cloned()) {
+            Some(cursor) => self.has_value.get_bit(cursor.node_pos),
+            None => false,
+        }
+    }
+
+    /// Replace the value for `key`, returning the old value
+    ///
+    /// Unlike `insert`, this does nothing (and returns `None`) if `key` is
+    /// not already in the trie.
+    pub fn replace_if_present<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: T,
+    ) -> Option<T> {
+        let cursor = self.find(key.as_ref().iter().cloned())?;
+        if self.has_value.get_bit(cursor.node_pos) {
+            let value_pos = self.has_value.rank1(cursor.node_pos);
+            Some(self.values.set(value_pos, value))
+        } else {
+            None
+        }
+    }
+
+    /// Remove the value for `key`, returning it
+    ///
+    /// The key's node is kept (without a value), so re-inserting `key`
+    /// later does not change the shape of the trie.
+    pub fn take<K: AsRef<[u8]>>(&mut self, key: K) -> Option<T> {
+        let cursor = self.find(key.as_ref().iter().cloned())?;
+        if self.has_value.get_bit(cursor.node_pos) {
+            let value_pos = self.has_value.rank1(cursor.node_pos);
+            self.has_value.set_bit(cursor.node_pos, false);
+            Some(self.values.remove(value_pos))
+        } else {
+            None
+        }
+    }
+
+    /// Front-code the keys in sorted order
+    ///
+    /// Each key is stored as the length of the prefix it shares with the
+    /// previous key, along with the range of the returned bytes holding the
+    /// rest of the key.
+    pub fn to_front_coded(&self) -> (Vec<u8>, Vec<(usize, Range<usize>)>) {
+        let root = Cursor {
             bit_pos: 0,
             node_pos: 0,
         };
+        let mut suffixes = Vec::new();
+        let mut entries = Vec::new();
+        if self.has_value.get_bit(root.node_pos) {
+            entries.push((0, 0..0));
+        }
 
-        for byte in key.as_ref().iter().cloned() {
+        // The previous key is always a prefix of the current path, so the
+        // shared prefix is the shortest the path has been since that key
+        let mut key = Vec::new();
+        let mut shared = 0;
+        let mut stack = Vec::new();
+        self.push_children(&root, 0, &mut stack);
+        while let Some((cursor, byte, depth)) = stack.pop() {
+            key.truncate(depth);
+            key.push(byte);
+            shared = shared.min(depth);
+            if self.has_value.get_bit(cursor.node_pos) {
+                let start = suffixes.len();
+                suffixes.extend_from_slice(&key[shared..]);
+                entries.push((shared, start..suffixes.len()));
+                shared = key.len();
+            }
+            self.push_children(&cursor, key.len(), &mut stack);
+        }
+        (suffixes, entries)
+    }
+
+    /// Push the children of `cursor` onto `stack`, largest byte first
+    fn push_children(
+        &self,
+        cursor: &Cursor,
+        depth: usize,
+        stack: &mut Vec<(Cursor, u8, usize)>,
+    ) {
+        let degree = self.degree(cursor.bit_pos);
+        if degree == 0 {
+            return;
+        }
+        let byte_begin = self.child(cursor.bit_pos, 0).node_pos - 1;
+        for i in (0..degree).rev() {
+            let byte = self.bytes.get(byte_begin + i);
+            stack.push((self.child(cursor.bit_pos, i), byte, depth));
+        }
+    }
+
+    /// Give the node at `node_pos` a value, returning the value it replaced
+    fn set_value(&mut self, node_pos: usize, value: T) -> Option<T> {
+        let value_index = self.has_value.rank1(node_pos);
+        if self.has_value.get_bit(node_pos) {
+            Some(self.values.set(value_index, value))
+        } else {
+            self.has_value.set_bit(node_pos, true);
+            self.values.insert(value_index, value);
+            None
+        }
+    }
+
+    /// Find the node for `key`, creating it (and its ancestors) if needed,
+    /// and return whether any node was created
+    fn find_or_create(&mut self, key: &[u8]) -> (bool, Cursor) {
+        let root = Cursor {
+            bit_pos: 0,
+            node_pos: 0,
+        };
+        self.find_or_create_from(root, key)
+    }
+
+    /// Like `find_or_create`, but for `key` below the node at `cursor`
+    ///
+    /// New nodes come after `cursor` in level order, so `cursor` stays valid.
+    fn find_or_create_from(
+        &mut self,
+        mut cursor: Cursor,
+        key: &[u8],
+    ) -> (bool, Cursor) {
+        let mut created = false;
+        for byte in key.iter().cloned() {
             if self.is_leaf(cursor.bit_pos) {
                 let child = self.trie.select0(self.trie.rank1(cursor.bit_pos));
                 self.trie.insert(child, false);
@@ -67,6 +504,7 @@ impl<T> LoudsTrie<T> {
 
                 cursor = self.child(cursor.bit_pos, 0);
                 self.has_value.insert(cursor.node_pos, false);
+                created = true;
             } else {
                 let byte_begin = self.child(cursor.bit_pos, 0).node_pos - 1;
                 let degree = self.degree(cursor.bit_pos);
@@ -84,29 +522,23 @@ impl<T> LoudsTrie<T> {
 
                     cursor = self.child(cursor.bit_pos, child_number);
                     self.has_value.insert(cursor.node_pos, false);
+                    created = true;
                 } else {
                     cursor = self.child(cursor.bit_pos, child_number);
                 }
             };
         }
-
-        let value_index = self.has_value.rank1(cursor.node_pos);
-        if self.has_value.get_bit(cursor.node_pos) {
-            Some(self.values.set(value_index, value))
-        } else {
-            self.has_value.set_bit(cursor.node_pos, true);
-            self.values.insert(value_index, value);
-            None
-        }
+        (created, cursor)
     }
 
-    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<&T> {
+    /// Find the node for `key`, if there is one
+    fn find<I: IntoIterator<Item = u8>>(&self, key: I) -> Option<Cursor> {
         let mut cursor = Cursor {
             bit_pos: 0,
             node_pos: 0,
         };
 
-        for byte in key.as_ref().iter() {
+        for byte in key {
             if self.is_leaf(cursor.bit_pos) {
                 return None;
             }
@@ -115,19 +547,33 @@ impl<T> LoudsTrie<T> {
             let degree = self.degree(cursor.bit_pos);
 
             let (child_number, found) =
-                self.bytes.child_number(byte_begin, degree, *byte);
+                self.bytes.child_number(byte_begin, degree, byte);
             if !found {
                 return None;
             }
             cursor = self.child(cursor.bit_pos, child_number);
         }
+        Some(cursor)
+    }
 
-        if self.has_value.get_bit(cursor.node_pos) {
-            let value_pos = self.has_value.rank1(cursor.node_pos);
-            Some(self.values.get(value_pos))
+    /// Return the (key, child) pairs of the node at `cursor`
+    fn children<'a>(
+        &'a self,
+        cursor: &Cursor,
+        key: &'a [u8],
+    ) -> impl DoubleEndedIterator<Item = (Cursor, Vec<u8>)> + 'a {
+        let bit_pos = cursor.bit_pos;
+        let degree = self.degree(bit_pos);
+        let byte_begin = if degree > 0 {
+            self.child(bit_pos, 0).node_pos - 1
         } else {
-            None
-        }
+            0
+        };
+        (0..degree).map(move |i| {
+            let mut child_key = key.to_vec();
+            child_key.push(self.bytes.get(byte_begin + i));
+            (self.child(bit_pos, i), child_key)
+        })
     }
 
     /// Get the bit-index of `cursor`'s `i`th child
@@ -152,6 +598,264 @@ impl<T> LoudsTrie<T> {
     }
 }
 
+impl<T, B, A> LoudsTrie<T, B, TrieValues<T, A>, A>
+where
+    B: BranchIndex<A>,
+    A: Allocator,
+{
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<&T> {
+        self.get_from_bytes(key.as_ref().iter().cloned())
+    }
+
+    /// Like `get`, but reads the key lazily from an iterator of bytes
+    pub fn get_from_bytes<I: IntoIterator<Item = u8>>(
+        &self,
+        bytes: I,
+    ) -> Option<&T> {
+        self.value(&self.find(bytes)?)
+    }
+
+    /// Return a mutable reference to the value for `key`
+    pub fn get_mut<K: AsRef<[u8]>>(&mut self, key: K) -> Option<&mut T> {
+        let cursor = self.find(key.as_ref().iter().cloned())?;
+        if self.has_value.get_bit(cursor.node_pos) {
+            let value_pos = self.has_value.rank1(cursor.node_pos);
+            Some(self.values.get_mut(value_pos))
+        } else {
+            None
+        }
+    }
+
+    /// Insert `value` if `key` is absent, returning a mutable reference to
+    /// the key's value either way
+    pub fn insert_or_get<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: T,
+    ) -> &mut T {
+        let (_, cursor) = self.find_or_create(key.as_ref());
+
+        let value_index = self.has_value.rank1(cursor.node_pos);
+        if !self.has_value.get_bit(cursor.node_pos) {
+            self.has_value.set_bit(cursor.node_pos, true);
+            self.values.insert(value_index, value);
+        }
+        self.values.get_mut(value_index)
+    }
+
+    /// Return a mutable reference to the value for `key`, inserting
+    /// `T::default()` first if the key is absent
+    pub fn get_or_default_mut<K: AsRef<[u8]>>(&mut self, key: K) -> &mut T
+    where
+        T: Default,
+    {
+        self.insert_or_get(key, T::default())
+    }
+
+    /// Iterate over the (key, value) pairs in sorted key order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (Vec<u8>, &T)> + '_ {
+        self.iter_prefix(b"")
+    }
+
+    /// Iterate over the (key, value) pairs whose key starts with `prefix`,
+    /// in sorted key order
+    pub fn iter_prefix<K: AsRef<[u8]>>(
+        &self,
+        prefix: K,
+    ) -> impl DoubleEndedIterator<Item = (Vec<u8>, &T)> + '_ {
+        let prefix = prefix.as_ref();
+        let (front, back) = match self.find(prefix.iter().cloned()) {
+            Some(cursor) => (
+                vec![(cursor.clone(), prefix.to_vec())],
+                vec![(cursor, prefix.to_vec(), false)],
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        Iter {
+            trie: self,
+            front,
+            back,
+            front_last: None,
+            back_last: None,
+        }
+    }
+
+    /// Call `f` on each (key, value) pair in sorted key order
+    ///
+    /// Unlike `iter`, the keys are borrowed from a single buffer holding the
+    /// current path, so no allocation is made per key.
+    pub fn iter_into<F: FnMut(&[u8], &T)>(&self, mut f: F) {
+        let root = Cursor {
+            bit_pos: 0,
+            node_pos: 0,
+        };
+        if let Some(value) = self.value(&root) {
+            f(b"", value);
+        }
+
+        let mut key = Vec::new();
+        // (node, the node's byte, the depth of its parent)
+        let mut stack = Vec::new();
+        self.push_children(&root, 0, &mut stack);
+        while let Some((cursor, byte, depth)) = stack.pop() {
+            key.truncate(depth);
+            key.push(byte);
+            if let Some(value) = self.value(&cursor) {
+                f(&key, value);
+            }
+            self.push_children(&cursor, key.len(), &mut stack);
+        }
+    }
+
+    /// Return the `k` highest-valued (key, value) pairs whose key starts
+    /// with `prefix`, from highest to lowest value
+    ///
+    /// Ties are broken in favor of the smaller key. Only `k` matches are
+    /// held at a time.
+    pub fn top_k_prefix<K: AsRef<[u8]>>(
+        &self,
+        prefix: K,
+        k: usize,
+    ) -> Vec<(Vec<u8>, &T)>
+    where
+        T: Ord,
+    {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        // A min-heap of the best matches so far, so the worst is on top
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        for (key, value) in self.iter_prefix(prefix) {
+            if heap.len() == k {
+                let Reverse((min, _)) = heap.peek().unwrap();
+                if value <= *min {
+                    continue;
+                }
+                heap.pop();
+            }
+            heap.push(Reverse((value, Reverse(key))));
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((value, Reverse(key)))| (key, value))
+            .collect()
+    }
+
+    fn value(&self, cursor: &Cursor) -> Option<&T> {
+        if self.has_value.get_bit(cursor.node_pos) {
+            let value_pos = self.has_value.rank1(cursor.node_pos);
+            Some(self.values.get(value_pos))
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: PackedValue, B: BranchIndex> LoudsTrie<T, B, PackedValues<T>> {
+    /// Return a copy of the value for `key`, unpacked from its bits
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<T> {
+        let cursor = self.find(key.as_ref().iter().cloned())?;
+        if self.has_value.get_bit(cursor.node_pos) {
+            let value_pos = self.has_value.rank1(cursor.node_pos);
+            Some(self.values.get(value_pos))
+        } else {
+            None
+        }
+    }
+
+    /// The number of bits each value is packed into
+    pub fn value_bits(&self) -> u32 {
+        self.values.bits()
+    }
+}
+
+/// A depth-first iterator that can be driven from either end
+///
+/// `front` is a pre-order stack, while `back` visits children (largest
+/// first) before their parent. The two ends have met once one is about to
+/// yield the node that the other yielded last.
+struct Iter<'a, T, B, A: Allocator> {
+    trie: &'a LoudsTrie<T, B, TrieValues<T, A>, A>,
+    front: Vec<(Cursor, Vec<u8>)>,
+    /// Whether each node's children have already been pushed
+    back: Vec<(Cursor, Vec<u8>, bool)>,
+    front_last: Option<usize>,
+    back_last: Option<usize>,
+}
+
+impl<'a, T, B: BranchIndex<A>, A: Allocator> Iter<'a, T, B, A> {
+    fn yield_node(
+        &mut self,
+        cursor: Cursor,
+        key: Vec<u8>,
+        from_front: bool,
+    ) -> Option<(Vec<u8>, &'a T)> {
+        let other = if from_front {
+            self.back_last
+        } else {
+            self.front_last
+        };
+        if other == Some(cursor.node_pos) {
+            self.front.clear();
+            self.back.clear();
+            return None;
+        }
+
+        if from_front {
+            self.front_last = Some(cursor.node_pos);
+        } else {
+            self.back_last = Some(cursor.node_pos);
+        }
+        let trie = self.trie;
+        trie.value(&cursor).map(|value| (key, value))
+    }
+}
+
+impl<'a, T, B, A> Iterator for Iter<'a, T, B, A>
+where
+    B: BranchIndex<A>,
+    A: Allocator,
+{
+    type Item = (Vec<u8>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((cursor, key)) = self.front.pop() {
+            self.front.extend(self.trie.children(&cursor, &key).rev());
+            if self.trie.value(&cursor).is_some() {
+                return self.yield_node(cursor, key, true);
+            }
+        }
+        None
+    }
+}
+
+impl<'a, T, B, A> DoubleEndedIterator for Iter<'a, T, B, A>
+where
+    B: BranchIndex<A>,
+    A: Allocator,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while let Some((cursor, key, expanded)) = self.back.pop() {
+            if expanded {
+                if self.trie.value(&cursor).is_some() {
+                    return self.yield_node(cursor, key, false);
+                }
+            } else {
+                let children = self
+                    .trie
+                    .children(&cursor, &key)
+                    .map(|(cursor, key)| (cursor, key, false))
+                    .collect::<Vec<_>>();
+                self.back.push((cursor, key, true));
+                self.back.extend(children);
+            }
+        }
+        None
+    }
+}
+
 impl<T, K> FromIterator<(K, T)> for LoudsTrie<T>
 where
     K: AsRef<[u8]>,
@@ -171,6 +875,7 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
 
     #[test]
     fn test_louds_insertion() {
@@ -320,6 +1025,108 @@ mod test {
         assert_eq!(louds.degree(q.bit_pos), 0);
     }
 
+    #[test]
+    fn test_louds_stats() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let louds = LoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+
+        let stats = louds.stats();
+        assert_eq!(stats.num_nodes, 17);
+        assert_eq!(stats.num_edges, 16);
+        assert_eq!(stats.num_values, 11);
+        assert_eq!(stats.root_degree, 3);
+        assert_eq!(stats.key_bytes, 27);
+        // The root, b, c, d, e, h and j have children
+        assert_eq!(stats.avg_branching, 16.0 / 7.0);
+        assert_eq!(stats.total_size, louds.total_size());
+
+        let stats = LoudsTrie::<()>::new().stats();
+        assert_eq!((stats.num_nodes, stats.num_edges), (1, 0));
+        assert_eq!(stats.avg_branching, 0.0);
+    }
+
+    #[test]
+    fn test_louds_custom_branch_index() {
+        struct VecIndex(Vec<u8>);
+
+        impl BranchIndex for VecIndex {
+            fn new_in(_: Global) -> VecIndex {
+                VecIndex(Vec::new())
+            }
+
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+
+            fn total_size(&self) -> usize {
+                self.0.capacity()
+            }
+
+            fn get(&self, index: usize) -> u8 {
+                self.0[index]
+            }
+
+            fn insert(&mut self, index: usize, byte: u8) {
+                self.0.insert(index, byte);
+            }
+
+            fn child_number(
+                &self,
+                index: usize,
+                degree: usize,
+                needle: u8,
+            ) -> (usize, bool) {
+                match self.0[index..index + degree].binary_search(&needle) {
+                    Ok(i) => (i, true),
+                    Err(i) => (i, false),
+                }
+            }
+        }
+
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let keys = (0..2000)
+            .map(|_| (0..8).map(|_| b"ACGT"[rng.gen_range(0, 4)]).collect())
+            .collect::<Vec<Vec<u8>>>();
+
+        let mut dna = LoudsTrie::<usize, VecIndex>::with_branch_index();
+        let mut louds = LoudsTrie::new();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(dna.insert(key, i), louds.insert(key, i));
+        }
+        assert_eq!(dna.len(), louds.len());
+        assert_eq!(dna.bytes.0, louds.bytes.iter().collect::<Vec<_>>());
+        assert!(dna.iter().eq(louds.iter()));
+        for key in keys.iter() {
+            assert_eq!(dna.get(key), louds.get(key));
+        }
+        assert_eq!(dna.get(b"ACGU"), None);
+    }
+
+    #[test]
+    fn test_louds_size_breakdown() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let mut louds = LoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+
+        let sizes = louds.size_breakdown();
+        assert_eq!(sizes.total(), louds.total_size());
+        assert_eq!(
+            sizes.trie + sizes.has_value + sizes.bytes + sizes.values,
+            louds.total_size() - std::mem::size_of::<LoudsTrie<u8>>()
+        );
+
+        // The overhead does not depend on the contents
+        louds.insert(b"e", b'e');
+        let grown = louds.size_breakdown();
+        assert_eq!(grown.overhead, sizes.overhead);
+        assert_eq!(grown.total(), louds.total_size());
+    }
+
     #[test]
     fn test_louds_get() {
         let keys: [&[u8]; 11] = [
@@ -335,6 +1142,513 @@ mod test {
         assert_eq!(louds.get(b"belarus"), None);
         assert_eq!(louds.get(b"dh"), None);
         assert_eq!(louds.get(b"dj"), None);
+
+        let set = LoudsTrie::from_iter(keys.iter().map(|k| (k, ())));
+        assert!(keys.iter().all(|k| set.contains_key(k)));
+        assert!(!set.contains_key(b"belarus"));
+        assert!(!set.contains_key(b"dh"));
+        assert!(!set.contains_key(b""));
+
+        for key in keys.iter().chain(&[&b"dh"[..], b"belarus", b"e"]) {
+            for split in 0..=key.len() {
+                let (a, b) = key.split_at(split);
+                assert_eq!(
+                    louds.get_from_bytes(
+                        a.iter().cloned().chain(b.iter().cloned())
+                    ),
+                    louds.get(key)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_louds_insert_full() {
+        let mut louds = LoudsTrie::new();
+        assert_eq!(louds.insert_full(b"bel", 0), (true, None));
+        assert_eq!(louds.insert_full(b"bel", 1), (false, Some(0)));
+        // "be" is an interior node of "bel"
+        assert_eq!(louds.insert_full(b"be", 2), (false, None));
+        assert_eq!(louds.insert_full(b"beg", 3), (true, None));
+        assert_eq!(louds.insert_full(b"", 4), (false, None));
+        assert_eq!(louds.insert_full(b"be", 5), (false, Some(2)));
+
+        assert_eq!(louds.len(), 4);
+        assert_eq!(louds.get(b"bel"), Some(&1));
+        assert_eq!(louds.get(b"be"), Some(&5));
+    }
+
+    #[test]
+    fn test_louds_insert_or_get() {
+        let text = "the cat and the dog and the bird ate the cat";
+        let mut louds = LoudsTrie::new();
+        for word in text.split(' ') {
+            *louds.insert_or_get(word, 0) += 1;
+        }
+
+        assert_eq!(louds.len(), 6);
+        assert_eq!(louds.get("the"), Some(&4));
+        assert_eq!(louds.get("cat"), Some(&2));
+        assert_eq!(louds.get("and"), Some(&2));
+        assert_eq!(louds.get("dog"), Some(&1));
+        assert_eq!(louds.get("ate"), Some(&1));
+        assert_eq!(louds.get("th"), None);
+
+        // An existing value is left alone
+        assert_eq!(*louds.insert_or_get("bird", 10), 1);
+    }
+
+    #[test]
+    fn test_louds_replace_if_present() {
+        let mut louds = LoudsTrie::new();
+        louds.insert(b"bel", 1);
+        louds.insert(b"ben", 2);
+
+        assert_eq!(louds.replace_if_present(b"bel", 10), Some(1));
+        assert_eq!(louds.get(b"bel"), Some(&10));
+        *louds.get_mut(b"ben").unwrap() += 5;
+        assert_eq!(louds.get(b"ben"), Some(&7));
+
+        // Missing keys (including prefixes of existing keys) are not created
+        let size = louds.total_size();
+        assert_eq!(louds.replace_if_present(b"be", 3), None);
+        assert_eq!(louds.replace_if_present(b"bex", 3), None);
+        assert_eq!(louds.get_mut(b"b"), None);
+        assert_eq!(louds.get(b"be"), None);
+        assert_eq!(louds.len(), 2);
+        assert_eq!(louds.total_size(), size);
+
+        assert_eq!(louds.insert(b"be", 3), None);
+        assert_eq!(louds.len(), 3);
+    }
+
+    #[test]
+    fn test_louds_arena_values() {
+        let mut tree = LoudsTrie::new();
+        let mut arena = LoudsTrie::new_arena_values();
+        let mut rng = SmallRng::from_seed([5; 16]);
+        for i in 0..3000u32 {
+            let key = rng.gen_range(0, 1u32 << 12).to_be_bytes();
+            let value = format!("value {}", i);
+            assert_eq!(
+                tree.insert(key, value.clone()),
+                arena.insert(key, value)
+            );
+            if i % 3 == 0 {
+                let key = rng.gen_range(0, 1u32 << 12).to_be_bytes();
+                assert_eq!(tree.take(key), arena.take(key));
+            }
+        }
+        assert_eq!(tree.len(), arena.len());
+        assert!(tree.iter().eq(arena.iter()));
+
+        let key = tree.iter().nth(100).unwrap().0;
+        arena.get_mut(&key).unwrap().push('!');
+        assert!(arena.get(&key).unwrap().ends_with('!'));
+        let slouds = crate::louds::SLoudsTrie::from(&arena);
+        assert_eq!(slouds.get(&key), arena.get(&key));
+        assert!(arena.size_breakdown().values > 0);
+    }
+
+    #[test]
+    fn test_louds_new_in() {
+        let mut louds = LoudsTrie::new_in(Global);
+        let mut expected = LoudsTrie::new();
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let mut keys = Vec::new();
+        for i in 0..20_000u32 {
+            let key = rng.gen::<u32>().to_be_bytes();
+            keys.push(key);
+            assert_eq!(louds.insert(key, i), expected.insert(key, i));
+            if i % 3 == 0 {
+                let key = keys[i as usize / 2];
+                assert_eq!(louds.take(key), expected.take(key));
+            }
+        }
+        assert!(louds.iter().eq(expected.iter()));
+        assert_eq!(louds.total_size(), expected.total_size());
+    }
+
+    #[cfg(feature = "allocator_api")]
+    #[test]
+    fn test_louds_new_in_frees_everything() {
+        use std::alloc::{AllocError, Layout};
+        use std::cell::Cell;
+        use std::ptr::NonNull;
+
+        // Counts the live allocations made through it
+        #[derive(Clone, Copy)]
+        struct Counting<'a>(&'a Cell<usize>);
+
+        unsafe impl Allocator for Counting<'_> {
+            fn allocate(
+                &self,
+                layout: Layout,
+            ) -> Result<NonNull<[u8]>, AllocError> {
+                self.0.set(self.0.get() + 1);
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.0.set(self.0.get() - 1);
+                Global.deallocate(ptr, layout)
+            }
+        }
+
+        let live = Cell::new(0);
+        let mut louds = LoudsTrie::new_in(Counting(&live));
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let keys = (0..20_000)
+            .map(|_| rng.gen::<u32>().to_be_bytes())
+            .collect::<Vec<_>>();
+        for (i, &key) in keys.iter().enumerate() {
+            louds.insert(key, i);
+        }
+        for &key in keys[..10_000].iter() {
+            louds.take(key);
+        }
+        // The LOUDS bits, edge bytes and values all have nodes below the root
+        assert!(live.get() >= 3);
+        drop(louds);
+        assert_eq!(live.get(), 0);
+    }
+
+    #[test]
+    fn test_louds_packed_values() {
+        let mut tree = LoudsTrie::new();
+        let mut packed = LoudsTrie::with_value_bits(12);
+        assert_eq!(packed.value_bits(), 12);
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let mut keys = Vec::new();
+        for i in 0..20_000u64 {
+            let key = rng.gen::<u64>().to_be_bytes();
+            keys.push(key);
+            let value = (i * 7) & 0xFFF;
+            assert_eq!(packed.insert(key, value), tree.insert(key, value));
+            if i % 5 == 0 {
+                let key = keys[i as usize / 2];
+                assert_eq!(packed.take(key), tree.take(key));
+            }
+        }
+        assert_eq!(packed.len(), tree.len());
+        for (key, &value) in tree.iter() {
+            assert_eq!(packed.get(&key), Some(value));
+        }
+        assert_eq!(packed.get(b"missing"), None);
+        assert_eq!(packed.replace_if_present(b"missing", 1), None);
+
+        let key = tree.iter().nth(100).unwrap().0;
+        let old = tree.insert(&key, 4095);
+        assert_eq!(packed.insert(&key, 4095), old);
+        assert_eq!(packed.get(&key), Some(4095));
+        let slouds = crate::louds::SLoudsTrie::from(&packed);
+        assert_eq!(slouds.get(&key), Some(&4095));
+        assert_eq!(slouds.len(), packed.len());
+
+        // The values take 12 bits each (plus the tree's slack) instead of 64
+        let packed_values = packed.size_breakdown().values;
+        assert!(packed_values * 4 < tree.size_breakdown().values);
+        assert!(packed.total_size() < tree.total_size());
+    }
+
+    #[test]
+    #[should_panic(expected = "4096 does not fit in 12 bits")]
+    fn test_louds_packed_value_too_wide() {
+        let mut packed = LoudsTrie::<u16, _, _>::with_value_bits(12);
+        packed.insert(b"a", 4096);
+    }
+
+    #[test]
+    fn test_louds_take() {
+        let keys: [&[u8]; 6] = [b"bel", b"bem", b"ben", b"be", b"cg", b"a"];
+        let mut louds =
+            LoudsTrie::from_iter(keys.iter().enumerate().map(|(i, k)| (k, i)));
+        let size = louds.total_size();
+
+        assert_eq!(louds.take(b"bem"), Some(1));
+        assert_eq!(louds.take(b"bem"), None);
+        assert_eq!(louds.take(b"b"), None);
+        assert_eq!(louds.take(b"bex"), None);
+        assert_eq!(louds.get(b"bem"), None);
+        assert!(!louds.contains_key(b"bem"));
+        assert_eq!(louds.len(), 5);
+
+        // Taking "be" keeps the keys under it
+        assert_eq!(louds.take(b"be"), Some(3));
+        let under = louds.iter_prefix(b"be").map(|(k, &v)| (k, v));
+        assert_eq!(
+            under.collect::<Vec<_>>(),
+            vec![(b"bel".to_vec(), 0), (b"ben".to_vec(), 2)]
+        );
+
+        // Re-inserting reuses the existing nodes
+        assert_eq!(louds.insert_full(b"bem", 10), (false, None));
+        assert_eq!(louds.insert_full(b"be", 11), (false, None));
+        assert_eq!(louds.get(b"bem"), Some(&10));
+        assert_eq!(louds.len(), 6);
+        assert_eq!(louds.total_size(), size);
+
+        for key in keys.iter() {
+            assert!(louds.take(key).is_some());
+        }
+        assert!(louds.is_empty());
+        assert_eq!(louds.iter().count(), 0);
+        louds.insert(b"cg", 5);
+        assert_eq!(louds.get(b"cg"), Some(&5));
+    }
+
+    #[test]
+    fn test_louds_reserve_key() {
+        let mut louds = LoudsTrie::new();
+        louds.insert(b"ben", 0);
+        let keys: [&[u8]; 5] = [b"bel", b"ben", b"be", b"cg", b""];
+        for key in keys.iter() {
+            louds.reserve_key(key);
+        }
+        // "ben" has 4 nodes, "bel" and "cg" add 3 more
+        assert_eq!(louds.stats().num_nodes, 7);
+
+        // Once every key has its nodes, reserving again keeps slots valid
+        let slots = keys
+            .iter()
+            .map(|key| louds.reserve_key(key))
+            .collect::<Vec<_>>();
+        assert_eq!(louds.len(), 1);
+        assert_eq!(louds.get(b"bel"), None);
+        assert_eq!(louds.get(b"be"), None);
+        assert!(!louds.contains_key(b"cg"));
+        assert_eq!(louds.iter().count(), 1);
+
+        for (i, slot) in slots.iter().enumerate().rev() {
+            let replaced = louds.fill(*slot, i + 10);
+            assert_eq!(replaced, if i == 1 { Some(0) } else { None });
+        }
+        assert_eq!(louds.len(), keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(louds.get(key), Some(&(i + 10)));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_louds_fill_stale_slot() {
+        let mut louds = LoudsTrie::new();
+        let slot = louds.reserve_key(b"bel");
+        louds.insert(b"ax", 1);
+        louds.fill(slot, 0);
+    }
+
+    #[test]
+    fn test_louds_insert_suffix() {
+        let bases: [&[u8]; 3] = [b"/usr/lib/", b"/usr/", b"/var/log/"];
+        let suffixes: [&[u8]; 5] = [b"", b"a", b"ab", b"lib/x", b"\xff"];
+
+        let mut full = LoudsTrie::new();
+        let mut louds = LoudsTrie::new();
+        for (i, base) in bases.iter().enumerate() {
+            louds.reserve_key(base);
+            let mut cursor = louds.descend_prefix(base).unwrap();
+            for (j, suffix) in suffixes.iter().enumerate() {
+                let key = [*base, suffix].concat();
+                let value = i * 10 + j;
+                assert_eq!(
+                    louds.insert_suffix(&mut cursor, suffix, value),
+                    full.insert(&key, value)
+                );
+            }
+            let old = louds.insert_suffix(&mut cursor, b"a", 99);
+            assert_eq!(old, Some(i * 10 + 1));
+            full.insert([*base, b"a"].concat(), 99);
+        }
+        assert_eq!(louds.len(), full.len());
+        assert!(louds.iter().eq(full.iter()));
+        assert_eq!(
+            crate::louds::SLoudsTrie::from(&louds),
+            crate::louds::SLoudsTrie::from(&full)
+        );
+        assert!(louds.descend_prefix(b"/usr/bin").is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_louds_insert_suffix_stale_cursor() {
+        let mut louds = LoudsTrie::new();
+        louds.insert(b"be", 0);
+        let mut cursor = louds.descend_prefix(b"be").unwrap();
+        louds.insert(b"ax", 1);
+        louds.insert_suffix(&mut cursor, b"l", 2);
+    }
+
+    #[test]
+    fn test_louds_get_or_default_mut() {
+        let edges: [(&[u8], u32); 6] = [
+            (b"a", 1),
+            (b"b", 2),
+            (b"a", 3),
+            (b"ab", 4),
+            (b"a", 5),
+            (b"b", 6),
+        ];
+        let mut louds: LoudsTrie<Vec<u32>> = LoudsTrie::new();
+        for (src, dest) in edges.iter().cloned() {
+            louds.get_or_default_mut(src).push(dest);
+        }
+
+        assert_eq!(louds.len(), 3);
+        assert_eq!(louds.get(b"a"), Some(&vec![1, 3, 5]));
+        assert_eq!(louds.get(b"ab"), Some(&vec![4]));
+        assert_eq!(louds.get(b"b"), Some(&vec![2, 6]));
+
+        assert!(louds.get_or_default_mut(b"c").is_empty());
+        assert_eq!(louds.get(b"c"), Some(&vec![]));
+    }
+
+    #[test]
+    fn test_louds_duplicates() {
+        let keys: [&[u8]; 6] = [b"b", b"bel", b"b", b"", b"bel", b"b"];
+        let (louds, overwritten) = LoudsTrie::from_iter_reporting(
+            keys.iter().enumerate().map(|(i, k)| (k, i)),
+        );
+        assert_eq!(overwritten, 3);
+        assert_eq!(louds.len(), 3);
+        assert_eq!(louds.get(b"b"), Some(&5));
+        assert_eq!(louds.get(b"bel"), Some(&4));
+        assert_eq!(louds.get(b""), Some(&3));
+
+        let mut louds = LoudsTrie::new();
+        assert_eq!(louds.try_insert(b"be", 0), Ok(()));
+        assert_eq!(louds.try_insert(b"b", 1), Ok(()));
+        assert_eq!(louds.try_insert(b"be", 2), Err(OccupiedError { value: 2 }));
+        assert_eq!(louds.get(b"be"), Some(&0));
+        assert_eq!(louds.len(), 2);
+    }
+
+    #[test]
+    fn test_louds_to_front_coded() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let louds = LoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+
+        let (suffixes, entries) = louds.to_front_coded();
+        assert_eq!(suffixes, b"belmnfcgdhoijpqk".to_vec());
+        assert_eq!(
+            entries
+                .iter()
+                .map(|(shared, _)| *shared)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2, 2, 1, 0, 0, 1, 1, 2, 1]
+        );
+
+        let mut decoded = Vec::new();
+        let mut key = Vec::new();
+        for (shared, range) in entries {
+            key.truncate(shared);
+            key.extend_from_slice(&suffixes[range]);
+            decoded.push(key.clone());
+        }
+        assert_eq!(
+            decoded,
+            louds.iter().map(|(key, _)| key).collect::<Vec<_>>()
+        );
+
+        let mut louds = LoudsTrie::new();
+        assert_eq!(louds.to_front_coded(), (vec![], vec![]));
+        louds.insert(b"", ());
+        louds.insert(b"ab", ());
+        assert_eq!(
+            louds.to_front_coded(),
+            (b"ab".to_vec(), vec![(0, 0..0), (0, 0..2)])
+        );
+    }
+
+    #[test]
+    fn test_louds_iter() {
+        let mut keys: Vec<&[u8]> =
+            vec![b"bel", b"", b"beg", b"a", b"b", b"bega", b"ca", b"c"];
+        let louds = LoudsTrie::from_iter(keys.iter().map(|k| (k, k.len())));
+        keys.sort();
+
+        let expected = keys
+            .iter()
+            .map(|k| (k.to_vec(), k.len()))
+            .collect::<Vec<_>>();
+        let forward = louds.iter().map(|(k, v)| (k, *v)).collect::<Vec<_>>();
+        let mut backward =
+            louds.iter().rev().map(|(k, v)| (k, *v)).collect::<Vec<_>>();
+        assert_eq!(forward, expected);
+        backward.reverse();
+        assert_eq!(backward, expected);
+
+        assert_eq!(
+            louds
+                .iter_prefix(b"be")
+                .rev()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>(),
+            vec![b"bel".to_vec(), b"bega".to_vec(), b"beg".to_vec()]
+        );
+        assert_eq!(louds.iter_prefix(b"bx").next(), None);
+
+        let mut visited = Vec::new();
+        louds.iter_into(|k, v| visited.push((k.to_vec(), *v)));
+        assert_eq!(visited, expected);
+        LoudsTrie::<()>::new().iter_into(|_, _| panic!());
+        assert_eq!(LoudsTrie::<()>::new().iter().next_back(), None);
+
+        // Alternate ends without yielding anything twice
+        for split in 0..=keys.len() {
+            let mut iter = louds.iter();
+            let mut front = (0..split)
+                .filter_map(|_| iter.next())
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>();
+            let mut back = Vec::new();
+            while let Some((k, _)) = iter.next_back() {
+                back.push(k);
+            }
+            assert_eq!(iter.next(), None);
+            back.reverse();
+            front.extend(back);
+            assert_eq!(
+                front,
+                keys.iter().map(|k| k.to_vec()).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_louds_top_k_prefix() {
+        let louds = LoudsTrie::from_iter(vec![
+            (&b"car"[..], 5),
+            (b"card", 9),
+            (b"care", 2),
+            (b"cart", 9),
+            (b"cat", 7),
+            (b"dog", 10),
+        ]);
+
+        assert_eq!(
+            louds.top_k_prefix(b"ca", 3),
+            vec![
+                (b"card".to_vec(), &9),
+                (b"cart".to_vec(), &9),
+                (b"cat".to_vec(), &7)
+            ]
+        );
+        assert_eq!(
+            louds.top_k_prefix(b"car", 10),
+            vec![
+                (b"card".to_vec(), &9),
+                (b"cart".to_vec(), &9),
+                (b"car".to_vec(), &5),
+                (b"care".to_vec(), &2)
+            ]
+        );
+        assert_eq!(louds.top_k_prefix(b"", 1), vec![(b"dog".to_vec(), &10)]);
+        assert_eq!(louds.top_k_prefix(b"ca", 0), vec![]);
+        assert_eq!(louds.top_k_prefix(b"x", 2), vec![]);
     }
 
     #[test]
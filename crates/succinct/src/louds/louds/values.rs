@@ -1,25 +1,255 @@
-use crate::tree::{Leaf, Tree};
+use std::marker::PhantomData;
 
-pub(crate) type ValueTree<T> = Tree<Vec<T>>;
+use crate::alloc::{self, Allocator, Global};
+use crate::tree::{Leaf, RemoveLeaf, Tree};
 
-impl<T> ValueTree<T> {
+pub(crate) type ValueTree<T, A = Global> = Tree<Vec<T>, A>;
+
+impl<T, A: Allocator> ValueTree<T, A> {
     pub(crate) fn get(&self, index: usize) -> &T {
         let (leaf, index) = self.get_leaf(index);
         &leaf[index]
     }
 
+    pub(crate) fn get_mut(&mut self, index: usize) -> &mut T {
+        let (leaf, index) = self.get_leaf_mut(index);
+        &mut leaf[index]
+    }
+
     pub(crate) fn set(&mut self, index: usize, value: T) -> T {
         let (leaf, index) = self.get_leaf_mut(index);
         std::mem::replace(&mut leaf[index], value)
     }
 
+    #[cfg(test)]
     pub(crate) fn iter(&self) -> impl Iterator<Item = &T> + '_ {
         self.iter_leaf().flat_map(|leaf| leaf.iter())
     }
 }
 
+/// An unsigned integer that `LoudsTrie::with_value_bits` can pack into a
+/// fixed number of bits
+pub trait PackedValue: Copy {
+    fn pack(self) -> u64;
+
+    /// The inverse of `pack`, for bits that came from it
+    fn unpack(bits: u64) -> Self;
+}
+
+macro_rules! impl_packed_value {
+    ($($int:ty),*) => {
+        $(
+            impl PackedValue for $int {
+                fn pack(self) -> u64 {
+                    self as u64
+                }
+
+                fn unpack(bits: u64) -> $int {
+                    bits as $int
+                }
+            }
+        )*
+    };
+}
+
+impl_packed_value!(u8, u16, u32, u64, usize);
+
+pub(crate) mod sealed {
+    /// The operations a `LoudsTrie` needs from its values, which are kept
+    /// out of the public API
+    pub trait Store<T> {
+        fn len(&self) -> usize;
+
+        fn total_size(&self) -> usize;
+
+        fn insert(&mut self, index: usize, value: T);
+
+        fn set(&mut self, index: usize, value: T) -> T;
+
+        fn remove(&mut self, index: usize) -> T;
+
+        fn get_cloned(&self, index: usize) -> T
+        where
+            T: Clone;
+    }
+}
+
+/// How a `LoudsTrie` stores its values, in key order
+///
+/// This is implemented by `TrieValues`, which keeps whole values that can
+/// be borrowed, and `PackedValues`, which packs each value into a fixed
+/// number of bits.
+pub trait ValueStore<T>: sealed::Store<T> {}
+
+/// The default `ValueStore`, either in a `ValueTree` allocated in `A` or in
+/// one contiguous arena
+///
+/// Inserting into the arena shifts every later value, but dropping it frees
+/// a single allocation and reading a value is a slice index.
+pub struct TrieValues<T, A: Allocator = Global>(Storage<T, A>);
+
+enum Storage<T, A: Allocator> {
+    Tree(ValueTree<T, A>),
+    Arena(Vec<T>),
+}
+
+impl<T> TrieValues<T> {
+    pub(crate) fn arena() -> TrieValues<T> {
+        TrieValues(Storage::Arena(Vec::new()))
+    }
+}
+
+impl<T, A: Allocator> TrieValues<T, A> {
+    pub(crate) fn tree_in(alloc: A) -> TrieValues<T, A> {
+        TrieValues(Storage::Tree(ValueTree::new_in(alloc)))
+    }
+
+    pub(crate) fn get(&self, index: usize) -> &T {
+        match &self.0 {
+            Storage::Tree(tree) => tree.get(index),
+            Storage::Arena(arena) => &arena[index],
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self, index: usize) -> &mut T {
+        match &mut self.0 {
+            Storage::Tree(tree) => tree.get_mut(index),
+            Storage::Arena(arena) => &mut arena[index],
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        let (tree, arena) = match &self.0 {
+            Storage::Tree(tree) => (Some(tree.iter()), None),
+            Storage::Arena(arena) => (None, Some(arena.iter())),
+        };
+        tree.into_iter()
+            .flatten()
+            .chain(arena.into_iter().flatten())
+    }
+}
+
+impl<T, A: Allocator> sealed::Store<T> for TrieValues<T, A> {
+    fn len(&self) -> usize {
+        match &self.0 {
+            Storage::Tree(tree) => tree.len(),
+            Storage::Arena(arena) => arena.len(),
+        }
+    }
+
+    fn total_size(&self) -> usize {
+        match &self.0 {
+            Storage::Tree(tree) => tree.total_size(),
+            Storage::Arena(arena) => {
+                arena.capacity() * std::mem::size_of::<T>()
+            }
+        }
+    }
+
+    fn insert(&mut self, index: usize, value: T) {
+        match &mut self.0 {
+            Storage::Tree(tree) => tree.insert(index, value),
+            Storage::Arena(arena) => arena.insert(index, value),
+        }
+    }
+
+    fn set(&mut self, index: usize, value: T) -> T {
+        std::mem::replace(self.get_mut(index), value)
+    }
+
+    fn remove(&mut self, index: usize) -> T {
+        match &mut self.0 {
+            Storage::Tree(tree) => tree.remove(index),
+            Storage::Arena(arena) => arena.remove(index),
+        }
+    }
+
+    fn get_cloned(&self, index: usize) -> T
+    where
+        T: Clone,
+    {
+        self.get(index).clone()
+    }
+}
+
+impl<T, A: Allocator> ValueStore<T> for TrieValues<T, A> {}
+
+/// A `ValueStore` that packs each value into the same number of bits, for
+/// a trie created by `LoudsTrie::with_value_bits`
+///
+/// Packed values are unpacked on every read, so they can only be read by
+/// value.
+pub struct PackedValues<T> {
+    tree: PackedTree,
+    marker: PhantomData<T>,
+}
+
+impl<T: PackedValue> PackedValues<T> {
+    /// Panics unless `bits` is between 1 and 64
+    pub(crate) fn new(bits: u32) -> PackedValues<T> {
+        assert!(bits > 0 && bits <= 64);
+        PackedValues {
+            tree: PackedTree::with_config(bits as u8),
+            marker: PhantomData,
+        }
+    }
+
+    /// The number of bits each value is packed into
+    pub(crate) fn bits(&self) -> u32 {
+        u32::from(self.tree.config())
+    }
+
+    pub(crate) fn get(&self, index: usize) -> T {
+        T::unpack(self.tree.get(index))
+    }
+
+    /// Pack `value`, checking that it fits
+    fn pack(&self, value: T) -> u64 {
+        let (value, bits) = (value.pack(), self.bits());
+        assert!(
+            bits == 64 || value >> bits == 0,
+            "{} does not fit in {} bits",
+            value,
+            bits
+        );
+        value
+    }
+}
+
+impl<T: PackedValue> sealed::Store<T> for PackedValues<T> {
+    fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    fn total_size(&self) -> usize {
+        self.tree.total_size()
+    }
+
+    fn insert(&mut self, index: usize, value: T) {
+        let value = self.pack(value);
+        self.tree.insert(index, value);
+    }
+
+    fn set(&mut self, index: usize, value: T) -> T {
+        let value = self.pack(value);
+        T::unpack(self.tree.set(index, value))
+    }
+
+    fn remove(&mut self, index: usize) -> T {
+        T::unpack(self.tree.remove(index))
+    }
+
+    fn get_cloned(&self, index: usize) -> T {
+        self.get(index)
+    }
+}
+
+impl<T: PackedValue> ValueStore<T> for PackedValues<T> {}
+
 impl<T> Leaf for Vec<T> {
     type Output = T;
+    type Config = ();
     const CAPACITY: usize = 64;
 
     fn total_size(&self) -> usize {
@@ -30,14 +260,14 @@ impl<T> Leaf for Vec<T> {
         self.len()
     }
 
-    fn new(value: T) -> Self {
+    fn new(_: (), value: T) -> Self {
         let mut vec = Vec::with_capacity(Self::CAPACITY);
         vec.push(value);
         vec
     }
 
-    fn split(&mut self) -> Box<Self> {
-        Box::new(self.split_off(Self::CAPACITY / 2))
+    fn split<A: Allocator>(&mut self, alloc: &A) -> *mut Self {
+        alloc::into_raw_in(self.split_off(Self::CAPACITY / 2), alloc)
     }
 
     fn insert(&mut self, index: usize, value: T) {
@@ -45,9 +275,137 @@ impl<T> Leaf for Vec<T> {
     }
 }
 
+impl<T> RemoveLeaf for Vec<T> {
+    fn remove(&mut self, index: usize) -> T {
+        self.remove(index)
+    }
+}
+
+/// A tree of unsigned integers, each packed into the same number of bits
+pub(crate) type PackedTree = Tree<PackedLeaf>;
+
+impl PackedTree {
+    pub(crate) fn get(&self, index: usize) -> u64 {
+        let (leaf, index) = self.get_leaf(index);
+        leaf.get(index)
+    }
+
+    pub(crate) fn set(&mut self, index: usize, value: u64) -> u64 {
+        let (leaf, index) = self.get_leaf_mut(index);
+        leaf.set(index, value)
+    }
+}
+
+/// Up to `CAPACITY` integers of the same width, packed into
+/// `width * CAPACITY / 64` words
+///
+/// The width is the tree's `Config`, and is recovered from the number of
+/// words rather than stored again in each leaf.
+pub(crate) struct PackedLeaf {
+    len: u16,
+    words: Box<[u64]>,
+}
+
+impl PackedLeaf {
+    fn width(&self) -> usize {
+        self.words.len() * 64 / Self::CAPACITY
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.width())
+    }
+
+    fn get(&self, index: usize) -> u64 {
+        let width = self.width();
+        let (word, offset) = (index * width / 64, index * width % 64);
+        let mut value = self.words[word] >> offset;
+        if offset + width > 64 {
+            value |= self.words[word + 1] << (64 - offset);
+        }
+        value & self.mask()
+    }
+
+    fn set(&mut self, index: usize, value: u64) -> u64 {
+        debug_assert_eq!(value & !self.mask(), 0);
+        let old = self.get(index);
+        let width = self.width();
+        let mask = self.mask();
+        let (word, offset) = (index * width / 64, index * width % 64);
+        self.words[word] &= !(mask << offset);
+        self.words[word] |= value << offset;
+        if offset + width > 64 {
+            let shift = 64 - offset;
+            self.words[word + 1] &= !(mask >> shift);
+            self.words[word + 1] |= value >> shift;
+        }
+        old
+    }
+}
+
+impl Leaf for PackedLeaf {
+    type Output = u64;
+    /// The number of bits in each value
+    type Config = u8;
+    const CAPACITY: usize = 256;
+
+    fn total_size(&self) -> usize {
+        std::mem::size_of::<u64>() * self.words.len()
+            + std::mem::size_of::<Self>()
+    }
+
+    fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    fn new(width: u8, value: u64) -> Self {
+        debug_assert!(width > 0 && width <= 64);
+        let words = width as usize * Self::CAPACITY / 64;
+        let mut leaf = PackedLeaf {
+            len: 1,
+            words: vec![0; words].into_boxed_slice(),
+        };
+        leaf.set(0, value);
+        leaf
+    }
+
+    fn split<A: Allocator>(&mut self, alloc: &A) -> *mut Self {
+        let half = Self::CAPACITY / 2;
+        let width = self.width() as u8;
+        let mut new = PackedLeaf::new(width, self.get(half));
+        for i in half + 1..self.len() {
+            new.set(i - half, self.get(i));
+        }
+        new.len = self.len - half as u16;
+        self.len = half as u16;
+        alloc::into_raw_in(new, alloc)
+    }
+
+    fn insert(&mut self, index: usize, value: u64) {
+        for i in (index..self.len()).rev() {
+            let moved = self.get(i);
+            self.set(i + 1, moved);
+        }
+        self.set(index, value);
+        self.len += 1;
+    }
+}
+
+impl RemoveLeaf for PackedLeaf {
+    fn remove(&mut self, index: usize) -> u64 {
+        let value = self.get(index);
+        for i in index + 1..self.len() {
+            let moved = self.get(i);
+            self.set(i - 1, moved);
+        }
+        self.len -= 1;
+        value
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
 
     #[test]
     fn test_insert_begin() {
@@ -78,4 +436,60 @@ mod test {
             assert_eq!(*values.get(i), reference[i]);
         }
     }
+
+    #[test]
+    fn test_remove() {
+        let mut reference = (0..5000usize).collect::<Vec<_>>();
+        let mut values = ValueTree::new();
+        for (i, &v) in reference.iter().enumerate() {
+            values.insert(i, v);
+        }
+
+        let mut packed = PackedTree::with_config(13);
+        for (i, &v) in reference.iter().enumerate() {
+            packed.insert(i, v as u64);
+        }
+
+        let mut i = 0;
+        while !reference.is_empty() {
+            i = (i + 7919) % reference.len();
+            let expected = reference.remove(i);
+            assert_eq!(values.remove(i), expected);
+            assert_eq!(packed.remove(i), expected as u64);
+            assert_eq!(values.len(), reference.len());
+            if reference.len() % 500 == 0 {
+                assert!(values.iter().eq(reference.iter()));
+            }
+        }
+
+        // The emptied tree can be reused
+        values.insert(0, 1);
+        values.insert(1, 2);
+        assert!(values.iter().eq([1, 2].iter()));
+    }
+
+    #[test]
+    fn test_packed_insert_middle() {
+        for &width in &[1u8, 7, 12, 33, 64] {
+            let mask = u64::MAX >> (64 - width);
+            let mut reference = Vec::new();
+            let mut values = PackedTree::with_config(width);
+
+            let mut rng = SmallRng::from_seed([5; 16]);
+            for _ in 0..3000 {
+                let value = rng.gen::<u64>() & mask;
+                let index = reference.len() / 3;
+                reference.insert(index, value);
+                values.insert(index, value);
+                assert_eq!(values.len(), reference.len());
+            }
+
+            for (i, &value) in reference.iter().enumerate() {
+                assert_eq!(values.get(i), value);
+            }
+            assert_eq!(values.set(5, 1), reference[5]);
+            assert_eq!(values.get(5), 1);
+            assert_eq!(values.get(6), reference[6]);
+        }
+    }
 }
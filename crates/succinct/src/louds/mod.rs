@@ -1,5 +1,26 @@
+mod archive;
+mod dawg;
+mod int;
+mod interner;
 mod louds;
+mod multiset;
+mod radix;
 mod slouds;
+mod text;
+mod typed;
+mod wavelet;
 
-pub use louds::LoudsTrie;
-pub use slouds::SLoudsTrie;
+pub use archive::{SLoudsArchive, SLoudsArchiveReader};
+pub use dawg::SDawg;
+pub use int::{IntKey, IntKeyTrie};
+pub use interner::StringInterner;
+pub use louds::{
+    BranchIndex, ByteTree, LoudsTrie, OccupiedError, PackedValue, PackedValues,
+    PublicCursor, SizeBreakdown, TrieStats, TrieValues, ValueSlot, ValueStore,
+};
+pub use multiset::LoudsMultiset;
+pub use radix::RadixLoudsTrie;
+pub use slouds::{LoudsBuilder, SLoudsTrie};
+pub use text::StrLoudsTrie;
+pub use typed::{TrieKey, TypedLoudsTrie};
+pub use wavelet::WaveletTrie;
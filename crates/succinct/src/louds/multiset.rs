@@ -0,0 +1,89 @@
+use super::LoudsTrie;
+
+/// A multiset of byte strings, storing a count for each distinct key
+pub struct LoudsMultiset {
+    counts: LoudsTrie<u64>,
+    total: u64,
+}
+
+impl LoudsMultiset {
+    pub fn new() -> LoudsMultiset {
+        LoudsMultiset {
+            counts: LoudsTrie::new(),
+            total: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// The number of distinct keys
+    pub fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// The number of keys, counting repeats
+    pub fn total_count(&self) -> u64 {
+        self.total
+    }
+
+    pub fn total_size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.counts.total_size()
+    }
+
+    /// Add one copy of `key`, returning its new count
+    pub fn insert<K: AsRef<[u8]>>(&mut self, key: K) -> u64 {
+        let count = self.counts.insert_or_get(key, 0);
+        *count += 1;
+        self.total += 1;
+        *count
+    }
+
+    /// The number of copies of `key`
+    pub fn count<K: AsRef<[u8]>>(&self, key: K) -> u64 {
+        self.counts.get(key).cloned().unwrap_or(0)
+    }
+
+    /// Iterate over the distinct keys and their counts, in sorted key order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (Vec<u8>, u64)> + '_ {
+        self.counts.iter().map(|(key, &count)| (key, count))
+    }
+}
+
+impl Default for LoudsMultiset {
+    fn default() -> LoudsMultiset {
+        LoudsMultiset::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_multiset_counts() {
+        let mut multiset = LoudsMultiset::new();
+        assert!(multiset.is_empty());
+        assert_eq!(multiset.count(b"the"), 0);
+
+        for i in 1..=100 {
+            assert_eq!(multiset.insert(b"the"), i);
+        }
+        let text = "the cat and the dog and the bird";
+        for word in text.split(' ') {
+            multiset.insert(word);
+        }
+
+        assert_eq!(multiset.count(b"the"), 103);
+        assert_eq!(multiset.count(b"and"), 2);
+        assert_eq!(multiset.count(b"cat"), 1);
+        assert_eq!(multiset.count(b"th"), 0);
+        assert_eq!(multiset.distinct(), 5);
+        assert_eq!(multiset.total_count(), 108);
+        assert_eq!(
+            multiset.iter().map(|(_, count)| count).sum::<u64>(),
+            multiset.total_count()
+        );
+    }
+}
@@ -0,0 +1,566 @@
+use std::collections::VecDeque;
+use std::iter::FromIterator;
+
+use super::louds::values::ValueTree;
+use crate::select_rank::{BitVec, SelectRank};
+
+/// A path-compressed LOUDS trie
+///
+/// Chains of single-child nodes are collapsed into a single edge. The first
+/// byte of each edge label lives in `bytes` (so branching is a binary search
+/// over the siblings, like in `LoudsTrie`), while the rest of the label is
+/// stored in `suffixes`.
+pub struct RadixLoudsTrie<T> {
+    trie: BitVec,
+    has_value: BitVec,
+    bytes: ValueTree<u8>,
+    /// The `[start, end)` range in `suffixes` of each node's edge label
+    labels: ValueTree<(u32, u32)>,
+    suffixes: Vec<u8>,
+    values: ValueTree<T>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Cursor {
+    bit_pos: usize,
+    node_pos: usize,
+}
+
+impl Cursor {
+    fn from_bit_pos(trie: &BitVec, pos: usize) -> Cursor {
+        Cursor {
+            bit_pos: pos,
+            node_pos: trie.rank0(pos),
+        }
+    }
+}
+
+/// A node detached from the trie by `remove_node`
+struct Node<T> {
+    degree: usize,
+    byte: u8,
+    label: (u32, u32),
+    value: Option<T>,
+}
+
+impl<T> Default for RadixLoudsTrie<T> {
+    fn default() -> Self {
+        RadixLoudsTrie::new()
+    }
+}
+
+impl<T> RadixLoudsTrie<T> {
+    pub fn new() -> RadixLoudsTrie<T> {
+        let mut louds = RadixLoudsTrie {
+            trie: BitVec::new(),
+            has_value: BitVec::new(),
+            bytes: ValueTree::new(),
+            labels: ValueTree::new(),
+            suffixes: Vec::new(),
+            values: ValueTree::new(),
+        };
+        louds.trie.insert(0, false);
+        louds.has_value.insert(0, false);
+        louds
+    }
+
+    pub fn total_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.bytes.total_size()
+            + self.trie.total_size()
+            + self.has_value.total_size()
+            + self.labels.total_size()
+            + self.suffixes.capacity() * std::mem::size_of::<u8>()
+            + self.values.total_size()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Insert a key into the trie, returning the old value (if any)
+    ///
+    /// If the key diverges from the trie partway through a compressed edge,
+    /// that edge is split, which moves the edge's subtree one level down.
+    pub fn insert<K: AsRef<[u8]>>(&mut self, key: K, value: T) -> Option<T> {
+        let mut cursor = Cursor {
+            bit_pos: 0,
+            node_pos: 0,
+        };
+
+        let mut remaining = key.as_ref();
+        while let Some((&byte, rest)) = remaining.split_first() {
+            let (child_number, found) = if self.is_leaf(cursor.bit_pos) {
+                (0, false)
+            } else {
+                let byte_begin = self.child(cursor.bit_pos, 0).node_pos - 1;
+                let degree = self.degree(cursor.bit_pos);
+                self.child_number(byte_begin, degree, byte)
+            };
+
+            if !found {
+                cursor = self.insert_child(&cursor, child_number, byte, rest);
+                break;
+            }
+
+            let child = self.child(cursor.bit_pos, child_number);
+            let suffix = self.suffix(child.node_pos - 1);
+            let len = suffix.len();
+            let common =
+                suffix.iter().zip(rest).take_while(|(a, b)| a == b).count();
+            if common < len {
+                self.split_edge(child.node_pos, common);
+            }
+            remaining = &rest[common..];
+            cursor = self.child(cursor.bit_pos, child_number);
+        }
+
+        let value_index = self.has_value.rank1(cursor.node_pos);
+        if self.has_value.get_bit(cursor.node_pos) {
+            Some(self.values.set(value_index, value))
+        } else {
+            self.has_value.set_bit(cursor.node_pos, true);
+            self.values.insert(value_index, value);
+            None
+        }
+    }
+
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<&T> {
+        let mut cursor = Cursor {
+            bit_pos: 0,
+            node_pos: 0,
+        };
+
+        let mut remaining = key.as_ref();
+        while let Some((&byte, rest)) = remaining.split_first() {
+            if self.is_leaf(cursor.bit_pos) {
+                return None;
+            }
+
+            let byte_begin = self.child(cursor.bit_pos, 0).node_pos - 1;
+            let degree = self.degree(cursor.bit_pos);
+
+            let (child_number, found) =
+                self.child_number(byte_begin, degree, byte);
+            if !found {
+                return None;
+            }
+
+            let suffix = self.suffix(byte_begin + child_number);
+            if !rest.starts_with(suffix) {
+                return None;
+            }
+            remaining = &rest[suffix.len()..];
+            cursor = self.child(cursor.bit_pos, child_number);
+        }
+
+        if self.has_value.get_bit(cursor.node_pos) {
+            let value_pos = self.has_value.rank1(cursor.node_pos);
+            Some(self.values.get(value_pos))
+        } else {
+            None
+        }
+    }
+
+    /// Insert a new (valueless) leaf as `cursor`'s `i`th child
+    fn insert_child(
+        &mut self,
+        cursor: &Cursor,
+        i: usize,
+        byte: u8,
+        suffix: &[u8],
+    ) -> Cursor {
+        let child = self.trie.select0(self.trie.rank1(cursor.bit_pos + i));
+        self.trie.insert(child, false);
+        self.trie.insert(cursor.bit_pos, true);
+
+        let child = self.child(cursor.bit_pos, i);
+        let start = self.suffixes.len() as u32;
+        self.suffixes.extend_from_slice(suffix);
+        let end = self.suffixes.len() as u32;
+
+        self.bytes.insert(child.node_pos - 1, byte);
+        self.labels.insert(child.node_pos - 1, (start, end));
+        self.has_value.insert(child.node_pos, false);
+        child
+    }
+
+    /// Get the edge label (minus its first byte) of the `index`th non-root node
+    fn suffix(&self, index: usize) -> &[u8] {
+        let (start, end) = *self.labels.get(index);
+        &self.suffixes[start as usize..end as usize]
+    }
+
+    /// Find `byte` among the `degree` edge bytes starting at `begin`,
+    /// returning its rank and whether it was found
+    fn child_number(
+        &self,
+        begin: usize,
+        degree: usize,
+        byte: u8,
+    ) -> (usize, bool) {
+        let (leaf, index) = self.bytes.get_leaf(begin);
+        if let Some(siblings) = leaf.get(index..index + degree) {
+            return match siblings.binary_search(&byte) {
+                Ok(i) => (i, true),
+                Err(i) => (i, false),
+            };
+        }
+
+        // The siblings span several leaves
+        let (mut lo, mut hi) = (0, degree);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if *self.bytes.get(begin + mid) < byte {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        (lo, lo < degree && *self.bytes.get(begin + lo) == byte)
+    }
+
+    /// Split the edge into the `node`th node after `at` bytes of its suffix
+    ///
+    /// A new node takes `node`'s place, and `node`'s subtree is moved one
+    /// level down to hang off of it.
+    fn split_edge(&mut self, node: usize, at: usize) {
+        // In level order, the subtree's nodes on each level are contiguous,
+        // and each level's run ends up where the next level's run starts
+        // (the last, empty, run is where the deepest level goes).
+        let mut levels = Vec::new();
+        let mut level = node..node + 1;
+        while !level.is_empty() {
+            let next =
+                self.first_child(level.start)..self.first_child(level.end);
+            levels.push(level);
+            level = next;
+        }
+        levels.push(level);
+
+        let (start, end) = *self.labels.get(node - 1);
+        let byte = self.suffixes[start as usize + at];
+        let first = self.bytes.set(node - 1, byte);
+        self.labels.set(node - 1, (start + at as u32 + 1, end));
+
+        // Move the deepest level first, so the runs above it stay put
+        for depth in (0..levels.len() - 1).rev() {
+            let dest = levels[depth + 1].start;
+            for _ in levels[depth].clone() {
+                let moved = self.remove_node(levels[depth].start);
+                self.insert_node(dest - 1, moved);
+            }
+        }
+
+        self.insert_node(
+            node,
+            Node {
+                degree: 1,
+                byte: first,
+                label: (start, start + at as u32),
+                value: None,
+            },
+        );
+    }
+
+    /// Get the bit-index of the `node`th node
+    fn node_start(&self, node: usize) -> usize {
+        if node == 0 {
+            0
+        } else {
+            self.trie.select0(node - 1) + 1
+        }
+    }
+
+    /// Get the index of the first child of the `node`th node, or where it
+    /// would go
+    fn first_child(&self, node: usize) -> usize {
+        // Every node before `node` has one 0 bit, and one 1 bit per child
+        1 + self.node_start(node) - node
+    }
+
+    fn remove_node(&mut self, node: usize) -> Node<T> {
+        let bit_pos = self.node_start(node);
+        let mut degree = 0;
+        while self.trie.remove(bit_pos) {
+            degree += 1;
+        }
+
+        let value_index = self.has_value.rank1(node);
+        let value = if self.has_value.remove(node) {
+            Some(self.values.remove(value_index))
+        } else {
+            None
+        };
+        Node {
+            degree,
+            byte: self.bytes.remove(node - 1),
+            label: self.labels.remove(node - 1),
+            value,
+        }
+    }
+
+    fn insert_node(&mut self, node: usize, inserted: Node<T>) {
+        let bit_pos = self.node_start(node);
+        self.trie.insert(bit_pos, false);
+        for _ in 0..inserted.degree {
+            self.trie.insert(bit_pos, true);
+        }
+
+        self.has_value.insert(node, inserted.value.is_some());
+        if let Some(value) = inserted.value {
+            self.values.insert(self.has_value.rank1(node), value);
+        }
+        self.bytes.insert(node - 1, inserted.byte);
+        self.labels.insert(node - 1, inserted.label);
+    }
+
+    /// Build a trie from (key, value) pairs sorted by unique keys
+    fn from_sorted(entries: Vec<(Vec<u8>, T)>) -> RadixLoudsTrie<T> {
+        let mut louds = RadixLoudsTrie {
+            trie: BitVec::new(),
+            has_value: BitVec::new(),
+            bytes: ValueTree::new(),
+            labels: ValueTree::new(),
+            suffixes: Vec::new(),
+            values: ValueTree::new(),
+        };
+
+        let (keys, values): (Vec<_>, Vec<_>) = entries.into_iter().unzip();
+        let mut values = values.into_iter().map(Some).collect::<Vec<_>>();
+        let mut trie = Vec::new();
+        let mut has_value = Vec::new();
+
+        // Each node owns the range of keys with the same prefix, which is
+        // `depth` bytes long.
+        let mut queue = VecDeque::new();
+        queue.push_back((0, keys.len(), 0));
+        while let Some((mut begin, end, depth)) = queue.pop_front() {
+            if begin < end && keys[begin].len() == depth {
+                has_value.push(true);
+                let value = values[begin].take().unwrap();
+                louds.values.insert(louds.values.len(), value);
+                begin += 1;
+            } else {
+                has_value.push(false);
+            }
+
+            while begin < end {
+                let byte = keys[begin][depth];
+                let child_end = begin
+                    + keys[begin..end]
+                        .iter()
+                        .take_while(|key| key[depth] == byte)
+                        .count();
+
+                // Keys are sorted, so the first and last key share the
+                // shortest common prefix in this range.
+                let (first, last) = (&keys[begin], &keys[child_end - 1]);
+                let prefix = first[depth..]
+                    .iter()
+                    .zip(last[depth..].iter())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+
+                let start = louds.suffixes.len() as u32;
+                louds
+                    .suffixes
+                    .extend_from_slice(&first[depth + 1..depth + prefix]);
+                let index = louds.bytes.len();
+                louds.bytes.insert(index, byte);
+                louds
+                    .labels
+                    .insert(index, (start, louds.suffixes.len() as u32));
+
+                trie.push(true);
+                queue.push_back((begin, child_end, depth + prefix));
+                begin = child_end;
+            }
+            trie.push(false);
+        }
+
+        louds.trie = BitVec::from_iter(trie);
+        louds.has_value = BitVec::from_iter(has_value);
+        louds
+    }
+
+    /// Get the bit-index of `cursor`'s `i`th child
+    fn child(&self, cursor: usize, i: usize) -> Cursor {
+        Cursor::from_bit_pos(
+            &self.trie,
+            self.trie.select0(self.trie.rank1(cursor + i)) + 1,
+        )
+    }
+
+    fn is_leaf(&self, cursor: usize) -> bool {
+        !self.trie.get_bit(cursor)
+    }
+
+    fn degree(&self, cursor: usize) -> usize {
+        if self.is_leaf(cursor) {
+            0
+        } else {
+            let next = self.trie.select0(self.trie.rank0(cursor));
+            next - cursor
+        }
+    }
+}
+
+impl<T, K> FromIterator<(K, T)> for RadixLoudsTrie<T>
+where
+    K: AsRef<[u8]>,
+{
+    fn from_iter<I>(input: I) -> Self
+    where
+        I: IntoIterator<Item = (K, T)>,
+    {
+        let mut input = input
+            .into_iter()
+            .map(|(key, value)| (key.as_ref().to_vec(), value))
+            .collect::<Vec<_>>();
+        // Stable sort, so later duplicates overwrite earlier ones
+        input.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut entries: Vec<(Vec<u8>, T)> = Vec::with_capacity(input.len());
+        for (key, value) in input {
+            match entries.last_mut() {
+                Some(last) if last.0 == key => last.1 = value,
+                _ => entries.push((key, value)),
+            }
+        }
+        RadixLoudsTrie::from_sorted(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::louds::LoudsTrie;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_radix_louds_compression() {
+        // We are encoding the following tree:
+        //          *
+        //          | abc
+        //          *
+        //     def /  \ xyz
+        //        *    *
+        let louds =
+            RadixLoudsTrie::from_iter(vec![(b"abcdef", 0), (b"abcxyz", 1)]);
+
+        assert_eq!(
+            louds.trie.iter().collect::<Vec<_>>(),
+            vec![true, false, true, true, false, false, false]
+        );
+        assert_eq!(
+            louds.has_value.iter().collect::<Vec<_>>(),
+            vec![false, false, true, true]
+        );
+        assert_eq!(louds.bytes.iter().cloned().collect::<Vec<_>>(), b"adx");
+        assert_eq!(&louds.suffixes, b"bcefyz");
+
+        assert_eq!(louds.get(b"abcdef"), Some(&0));
+        assert_eq!(louds.get(b"abcxyz"), Some(&1));
+        assert_eq!(louds.get(b"abc"), None);
+        assert_eq!(louds.get(b"ab"), None);
+        assert_eq!(louds.get(b"abcdefg"), None);
+    }
+
+    #[test]
+    fn test_radix_louds_get() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let louds = RadixLoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+        assert_eq!(louds.len(), keys.len());
+
+        for key in keys.iter() {
+            assert_eq!(louds.get(key), Some(&key[0]));
+        }
+
+        assert_eq!(louds.get(b""), None);
+        assert_eq!(louds.get(b"belarus"), None);
+        assert_eq!(louds.get(b"c"), None);
+        assert_eq!(louds.get(b"dh"), None);
+        assert_eq!(louds.get(b"dj"), None);
+    }
+
+    #[test]
+    fn test_radix_louds_insert_split() {
+        let keys: [&[u8]; 10] = [
+            b"abcdef", b"abcxyz", b"ab", b"abcdefg", b"", b"b", b"abcd",
+            b"abcdef", b"xyz", b"abcdeg",
+        ];
+
+        let mut louds = RadixLoudsTrie::new();
+        let mut reference = BTreeMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(louds.insert(key, i), reference.insert(key.to_vec(), i));
+            assert_eq!(louds.len(), reference.len());
+
+            for (k, v) in reference.iter() {
+                assert_eq!(louds.get(k), Some(v));
+            }
+        }
+
+        assert_eq!(louds.get(b"a"), None);
+        assert_eq!(louds.get(b"abc"), None);
+        assert_eq!(louds.get(b"abcde"), None);
+        assert_eq!(louds.get(b"xy"), None);
+    }
+
+    #[test]
+    fn test_radix_louds_insert_diverging() {
+        // Long keys over a small alphabet, so most inserts split an edge
+        // partway through
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let mut louds = RadixLoudsTrie::new();
+        let mut reference = BTreeMap::new();
+        for i in 0..3000 {
+            let len = rng.gen_range(0, 12);
+            let key = (0..len)
+                .map(|_| rng.gen_range(b'a', b'e'))
+                .collect::<Vec<u8>>();
+            assert_eq!(louds.insert(&key, i), reference.insert(key.clone(), i));
+            assert_eq!(louds.get(&key), Some(&i));
+            assert_eq!(louds.len(), reference.len());
+        }
+
+        for (k, v) in reference.iter() {
+            assert_eq!(louds.get(k), Some(v));
+        }
+        // Splitting edges one at a time gives the same shape as building
+        // the trie in one go
+        let rebuilt = RadixLoudsTrie::from_iter(reference.iter());
+        assert!(louds.trie.iter().eq(rebuilt.trie.iter()));
+        assert!(louds.bytes.iter().eq(rebuilt.bytes.iter()));
+        assert_eq!(louds.get(b"eeee"), None);
+    }
+
+    #[test]
+    fn test_radix_louds_matches_louds() {
+        let numbers: [u16; 25] = [
+            9424, 12398, 54780, 51835, 63026, 8401, 63521, 49588, 14290, 60102,
+            12443, 35584, 11924, 55247, 770, 20443, 1862, 11155, 25753, 7685,
+            1900, 7743, 43659, 63103, 3614,
+        ];
+
+        let louds =
+            LoudsTrie::from_iter(numbers.iter().map(|k| (k.to_be_bytes(), k)));
+        let mut radix = RadixLoudsTrie::new();
+        for k in numbers.iter() {
+            radix.insert(k.to_be_bytes(), k);
+        }
+
+        for k in 0..=u16::MAX {
+            assert_eq!(radix.get(k.to_be_bytes()), louds.get(k.to_be_bytes()));
+        }
+    }
+}
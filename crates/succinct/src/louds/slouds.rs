@@ -1,7 +1,11 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, Read, Write};
 use std::iter::FromIterator;
 
-use super::louds::LoudsTrie;
+use bytemuck::Pod;
+
+use super::louds::{ByteTree, LoudsTrie, SizeBreakdown, ValueStore};
+use crate::alloc::Allocator;
 use crate::select_rank::{SBitVec, SelectRank};
 
 /// A Static LOUDS trie
@@ -28,33 +32,247 @@ impl Cursor {
     }
 }
 
+impl<T> Default for SLoudsTrie<T> {
+    fn default() -> Self {
+        SLoudsTrie::new()
+    }
+}
+
 impl<T> SLoudsTrie<T> {
+    /// Create an empty trie
+    pub fn new() -> SLoudsTrie<T> {
+        SLoudsTrie::from_iter(std::iter::empty::<(&[u8], T)>())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
     pub fn total_size(&self) -> usize {
-        std::mem::size_of::<Self>()
-            + self.trie.total_size()
-            + self.has_value.total_size()
-            + self.bytes.capacity() * std::mem::size_of::<u8>()
-            + self.values.capacity() * std::mem::size_of::<T>()
+        self.size_breakdown().total()
+    }
+
+    /// Split `total_size` into the memory used by each part of the trie
+    pub fn size_breakdown(&self) -> SizeBreakdown {
+        SizeBreakdown {
+            trie: self.trie.total_size(),
+            has_value: self.has_value.total_size(),
+            bytes: self.bytes.capacity() * std::mem::size_of::<u8>(),
+            values: self.values.capacity() * std::mem::size_of::<T>(),
+            overhead: std::mem::size_of::<Self>(),
+        }
+    }
+
+    /// Return whether `key` is stored, without reading its value
+    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> bool {
+        match self.find(key.as_ref()) {
+            Some(cursor) => self.has_value.get_bit(cursor.node_pos),
+            None => false,
+        }
     }
 
     pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<&T> {
+        let cursor = self.find(key.as_ref())?;
+        self.value_of(&cursor)
+    }
+
+    /// Look up many keys at once, returning their values in the order of
+    /// `keys`
+    ///
+    /// The keys are visited in sorted order, so each one only descends from
+    /// the deepest node it shares with the previous key.
+    pub fn get_batch(&self, keys: &[&[u8]]) -> Vec<Option<&T>> {
+        let mut order = (0..keys.len()).collect::<Vec<_>>();
+        order.sort_unstable_by_key(|&i| keys[i]);
+
+        let mut output = vec![None; keys.len()];
+        // path[d] is the node for the first d bytes of the previous key, up
+        // to the first byte that was missing
+        let mut path = vec![Cursor {
+            bit_pos: 0,
+            node_pos: 0,
+        }];
+        let mut prev: &[u8] = &[];
+        for i in order {
+            let key = keys[i];
+            let shared =
+                prev.iter().zip(key).take_while(|(a, b)| a == b).count();
+            prev = key;
+            if path.len() <= shared {
+                // The previous key already left the trie within the prefix
+                // this key shares with it
+                continue;
+            }
+
+            path.truncate(shared + 1);
+            for &byte in &key[shared..] {
+                match self.step(path.last().unwrap(), byte) {
+                    Some(cursor) => path.push(cursor),
+                    None => break,
+                }
+            }
+            if path.len() == key.len() + 1 {
+                output[i] = self.value_of(path.last().unwrap());
+            }
+        }
+        output
+    }
+
+    /// Return the bytes of the edges out of the node for `prefix`, and
+    /// whether each child holds a value, or `None` if there is no such node
+    pub fn children_of<K: AsRef<[u8]>>(
+        &self,
+        prefix: K,
+    ) -> Option<Vec<(u8, bool)>> {
+        let cursor = self.find(prefix.as_ref())?;
+        let degree = self.degree(cursor.bit_pos);
+        Some(
+            (0..degree)
+                .map(|i| {
+                    let child = self.child(cursor.bit_pos, i);
+                    (
+                        self.bytes[child.node_pos - 1],
+                        self.has_value.get_bit(child.node_pos),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Return the number of keys that are lexicographically less than `key`
+    pub fn rank_of_key<K: AsRef<[u8]>>(&self, key: K) -> usize {
         let mut cursor = Cursor {
             bit_pos: 0,
             node_pos: 0,
         };
 
+        let mut rank = 0;
         for byte in key.as_ref().iter() {
+            // The current node is a proper prefix of `key`, so sorts first
+            rank += self.has_value.get_bit(cursor.node_pos) as usize;
             if self.is_leaf(cursor.bit_pos) {
-                return None;
+                return rank;
             }
 
-            let byte_begin = self.child(cursor.bit_pos, 0).node_pos - 1;
+            let first = self.child(cursor.bit_pos, 0).node_pos;
             let degree = self.degree(cursor.bit_pos);
-            let slice = &self.bytes[byte_begin..byte_begin + degree];
-            let child = slice.binary_search(byte).ok()?;
+            let slice = &self.bytes[first - 1..first - 1 + degree];
+            let (child, found) = match slice.binary_search(byte) {
+                Ok(child) => (child, true),
+                Err(child) => (child, false),
+            };
+            rank += self.subtree_values(first, first + child);
+            if !found {
+                return rank;
+            }
+            cursor = self.child(cursor.bit_pos, child);
+        }
+        rank
+    }
+
+    /// Return the breadth-first index of the node for `prefix`, if there is
+    /// one, which is also its index in `LoudsBuilder::build_with_aggregates`
+    pub fn node_index<K: AsRef<[u8]>>(&self, prefix: K) -> Option<usize> {
+        self.find(prefix.as_ref()).map(|cursor| cursor.node_pos)
+    }
+
+    /// Return the number of stored keys starting with `prefix`
+    ///
+    /// This takes a few ranks for each level of the trie below `prefix`.
+    pub fn count_prefix<K: AsRef<[u8]>>(&self, prefix: K) -> usize {
+        match self.find(prefix.as_ref()) {
+            Some(cursor) => {
+                self.subtree_values(cursor.node_pos, cursor.node_pos + 1)
+            }
+            None => 0,
+        }
+    }
+
+    /// Map a stored key to its dense id in `0..len()`, or `None` if `key` is
+    /// not stored
+    ///
+    /// Ids follow sorted key order, so this is a monotone minimal perfect
+    /// hash of the key set, inverted by `select_key`.
+    pub fn key_to_id<K: AsRef<[u8]>>(&self, key: K) -> Option<usize> {
+        let key = key.as_ref();
+        let cursor = self.find(key)?;
+        if self.has_value.get_bit(cursor.node_pos) {
+            Some(self.rank_of_key(key))
+        } else {
+            None
+        }
+    }
+
+    /// Return the `rank`th smallest key, so that
+    /// `rank_of_key(select_key(rank)) == rank`
+    ///
+    /// Panics if `rank` is not less than the number of keys.
+    pub fn select_key(&self, mut rank: usize) -> Vec<u8> {
+        assert!(rank < self.values.len());
+        let mut cursor = Cursor {
+            bit_pos: 0,
+            node_pos: 0,
+        };
+
+        let mut key = Vec::new();
+        loop {
+            if self.has_value.get_bit(cursor.node_pos) {
+                if rank == 0 {
+                    return key;
+                }
+                rank -= 1;
+            }
+
+            // Find the child whose subtree holds the `rank`th value, by the
+            // number of values before each candidate
+            let first = self.child(cursor.bit_pos, 0).node_pos;
+            let (mut low, mut high) = (0, self.degree(cursor.bit_pos));
+            while high - low > 1 {
+                let mid = (low + high) / 2;
+                if self.subtree_values(first, first + mid) <= rank {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            let child = low;
+            rank -= self.subtree_values(first, first + child);
+            key.push(self.bytes[first - 1 + child]);
             cursor = self.child(cursor.bit_pos, child);
         }
+    }
 
+    /// Find the node for `key`, if there is one
+    fn find(&self, key: &[u8]) -> Option<Cursor> {
+        let mut cursor = Cursor {
+            bit_pos: 0,
+            node_pos: 0,
+        };
+
+        for &byte in key.iter() {
+            cursor = self.step(&cursor, byte)?;
+        }
+        Some(cursor)
+    }
+
+    /// Follow the edge labelled `byte` out of `cursor`
+    fn step(&self, cursor: &Cursor, byte: u8) -> Option<Cursor> {
+        if self.is_leaf(cursor.bit_pos) {
+            return None;
+        }
+
+        let byte_begin = self.child(cursor.bit_pos, 0).node_pos - 1;
+        let degree = self.degree(cursor.bit_pos);
+        let slice = &self.bytes[byte_begin..byte_begin + degree];
+        let child = slice.binary_search(&byte).ok()?;
+        Some(self.child(cursor.bit_pos, child))
+    }
+
+    fn value_of(&self, cursor: &Cursor) -> Option<&T> {
         if self.has_value.get_bit(cursor.node_pos) {
             let value_pos = self.has_value.rank1(cursor.node_pos);
             self.values.get(value_pos)
@@ -63,6 +281,189 @@ impl<T> SLoudsTrie<T> {
         }
     }
 
+    /// Return the smallest key in the trie
+    pub fn min_key(&self) -> Option<Vec<u8>> {
+        // Go by rank, since a trie built from a `LoudsTrie` can have nodes
+        // (even leaves) without values
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.select_key(0))
+        }
+    }
+
+    /// Return the largest key in the trie
+    pub fn max_key(&self) -> Option<Vec<u8>> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.select_key(self.len() - 1))
+        }
+    }
+
+    /// Iterate over the (key, value) pairs in sorted key order
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<u8>, &T)> + '_ {
+        let root = Cursor {
+            bit_pos: 0,
+            node_pos: 0,
+        };
+        self.iter_from(root, Vec::new())
+    }
+
+    /// Return how much of `query` matches a path in the trie, along with
+    /// every key under the deepest node it reaches, in sorted order
+    pub fn longest_common_prefix_keys<K: AsRef<[u8]>>(
+        &self,
+        query: K,
+    ) -> (usize, Vec<Vec<u8>>) {
+        let query = query.as_ref();
+        let mut cursor = Cursor {
+            bit_pos: 0,
+            node_pos: 0,
+        };
+        let mut depth = 0;
+        for &byte in query.iter() {
+            match self.step(&cursor, byte) {
+                Some(child) => cursor = child,
+                None => break,
+            }
+            depth += 1;
+        }
+
+        let keys = self.iter_from(cursor, query[..depth].to_vec());
+        (depth, keys.map(|(key, _)| key).collect())
+    }
+
+    /// Iterate over the keys under `cursor` (whose key is `key`)
+    fn iter_from(
+        &self,
+        cursor: Cursor,
+        key: Vec<u8>,
+    ) -> impl Iterator<Item = (Vec<u8>, &T)> + '_ {
+        let mut stack = vec![(cursor, key)];
+        std::iter::from_fn(move || {
+            while let Some((cursor, key)) = stack.pop() {
+                let degree = self.degree(cursor.bit_pos);
+                if degree > 0 {
+                    let byte_begin = self.child(cursor.bit_pos, 0).node_pos - 1;
+                    for i in (0..degree).rev() {
+                        let mut child_key = key.clone();
+                        child_key.push(self.bytes[byte_begin + i]);
+                        stack.push((self.child(cursor.bit_pos, i), child_key));
+                    }
+                }
+
+                if self.has_value.get_bit(cursor.node_pos) {
+                    let value_pos = self.has_value.rank1(cursor.node_pos);
+                    return Some((key, &self.values[value_pos]));
+                }
+            }
+            None
+        })
+    }
+
+    /// Return all keys within Levenshtein distance `max_dist` of `query`, in
+    /// key order
+    ///
+    /// Walks the trie keeping the edit-distance row of each prefix against
+    /// `query`, and prunes any subtree whose row is entirely over `max_dist`.
+    pub fn fuzzy<K: AsRef<[u8]>>(
+        &self,
+        query: K,
+        max_dist: u32,
+    ) -> Vec<(Vec<u8>, &T)> {
+        self.weighted_search(query.as_ref(), &|_, _| 1, max_dist)
+            .into_iter()
+            .map(|(key, _, value)| (key, value))
+            .collect()
+    }
+
+    /// Return all keys within `max_cost` edits of `query` along with their
+    /// costs, cheapest first
+    ///
+    /// This is `fuzzy`, except that replacing a byte `q` of `query` with a
+    /// different byte `b` costs `cost(q, b)` rather than 1. Insertions and
+    /// deletions still cost 1.
+    pub fn suggest<K: AsRef<[u8]>>(
+        &self,
+        query: K,
+        cost: &dyn Fn(u8, u8) -> u32,
+        max_cost: u32,
+    ) -> Vec<(Vec<u8>, u32)> {
+        let mut output = self
+            .weighted_search(query.as_ref(), cost, max_cost)
+            .into_iter()
+            .map(|(key, cost, _)| (key, cost))
+            .collect::<Vec<_>>();
+        // The search yields keys in order, so a stable sort breaks ties by key
+        output.sort_by_key(|&(_, cost)| cost);
+        output
+    }
+
+    /// Find the keys within `max_cost` of `query` in key order, where
+    /// substituting `b` for `q` costs `cost(q, b)`
+    fn weighted_search(
+        &self,
+        query: &[u8],
+        cost: &dyn Fn(u8, u8) -> u32,
+        max_cost: u32,
+    ) -> Vec<(Vec<u8>, u32, &T)> {
+        let root = Cursor {
+            bit_pos: 0,
+            node_pos: 0,
+        };
+        let row = (0..=query.len() as u32).collect::<Vec<_>>();
+
+        let mut output = Vec::new();
+        let mut stack = vec![(root, Vec::new(), row)];
+        while let Some((cursor, key, row)) = stack.pop() {
+            if row[query.len()] <= max_cost
+                && self.has_value.get_bit(cursor.node_pos)
+            {
+                let value_pos = self.has_value.rank1(cursor.node_pos);
+                output.push((
+                    key.clone(),
+                    row[query.len()],
+                    &self.values[value_pos],
+                ));
+            }
+
+            let degree = self.degree(cursor.bit_pos);
+            if degree == 0 {
+                continue;
+            }
+            let byte_begin = self.child(cursor.bit_pos, 0).node_pos - 1;
+            for i in (0..degree).rev() {
+                let byte = self.bytes[byte_begin + i];
+                let mut child_row = Vec::with_capacity(row.len());
+                child_row.push(row[0] + 1);
+                for (j, &q) in query.iter().enumerate() {
+                    let substitute = if q == byte {
+                        row[j]
+                    } else {
+                        row[j].saturating_add(cost(q, byte))
+                    };
+                    let delete = row[j + 1] + 1;
+                    let insert = child_row[j] + 1;
+                    child_row.push(substitute.min(delete).min(insert));
+                }
+
+                // Costs never decrease further down, so the smallest entry
+                // bounds every key below this child
+                if child_row.iter().min() <= Some(&max_cost) {
+                    let mut child_key = key.clone();
+                    child_key.push(byte);
+                    stack.push((
+                        self.child(cursor.bit_pos, i),
+                        child_key,
+                        child_row,
+                    ));
+                }
+            }
+        }
+        output
+    }
+
     /// Get the bit-index of `cursor`'s `i`th child
     fn child(&self, cursor: usize, i: usize) -> Cursor {
         Cursor::from_bit_pos(
@@ -71,6 +472,41 @@ impl<T> SLoudsTrie<T> {
         )
     }
 
+    /// The index of the first child of `node`, or where its children would
+    /// go if it is a leaf, so that the children of the nodes `start..end`
+    /// are `first_child(start)..first_child(end)`
+    fn first_child(&self, node: usize) -> usize {
+        let bit_pos = match node {
+            0 => 0,
+            _ => self.trie.select0(node - 1) + 1,
+        };
+        if bit_pos == self.trie.len() {
+            // Past the last node, whose children (if it had any) are last
+            node
+        } else {
+            self.trie.rank1(bit_pos) + 1
+        }
+    }
+
+    /// The number of values in the subtrees of the nodes `start..end`
+    ///
+    /// The descendants of consecutive nodes are consecutive on every level
+    /// below them, so this takes two ranks of `has_value` per level rather
+    /// than storing a count for every node.
+    fn subtree_values(&self, mut start: usize, mut end: usize) -> usize {
+        let mut count = 0;
+        while start < end {
+            let end_rank = match end {
+                end if end == self.has_value.len() => self.values.len(),
+                end => self.has_value.rank1(end),
+            };
+            count += end_rank - self.has_value.rank1(start);
+            start = self.first_child(start);
+            end = self.first_child(end);
+        }
+        count
+    }
+
     fn is_leaf(&self, cursor: usize) -> bool {
         !self.trie.get_bit(cursor)
     }
@@ -85,87 +521,263 @@ impl<T> SLoudsTrie<T> {
     }
 }
 
-/// A really bad trie implementation to construct the SLoudsTrie trie
-struct BadTrie<T> {
-    children: Vec<(u8, BadTrie<T>)>,
-    value: Option<T>,
-}
+impl<T: Pod> SLoudsTrie<T> {
+    /// Write the number of keys (as a little-endian `u64`), followed by
+    /// each key's length (as a little-endian `u64`), bytes, and raw value
+    /// bytes in sorted key order.
+    ///
+    /// The values are written in native byte order, so a trie whose values
+    /// span more than one byte can only be read back on a machine of the
+    /// same endianness.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&(self.len() as u64).to_le_bytes())?;
+        for (key, value) in self.iter() {
+            w.write_all(&(key.len() as u64).to_le_bytes())?;
+            w.write_all(&key)?;
+            w.write_all(bytemuck::bytes_of(value))?;
+        }
+        Ok(())
+    }
 
-impl<T> BadTrie<T> {
-    fn get_or_insert_child(&mut self, k: u8) -> &mut BadTrie<T> {
-        let mut max = self.children.len();
-        for i in 0..self.children.len() {
-            if self.children[i].0 < k {
-                continue;
-            } else if self.children[i].0 == k {
-                return &mut self.children[i].1;
-            } else {
-                max = i;
-                break;
+    /// Read a trie written by `write_to` on a machine of the same endianness
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<SLoudsTrie<T>> {
+        let mut len = [0; 8];
+        r.read_exact(&mut len)?;
+
+        let mut entries: Vec<(Vec<u8>, T)> = Vec::new();
+        for _ in 0..u64::from_le_bytes(len) {
+            let mut key_len = [0; 8];
+            r.read_exact(&mut key_len)?;
+            let key_len = u64::from_le_bytes(key_len);
+            let mut key = Vec::new();
+            r.by_ref().take(key_len).read_to_end(&mut key)?;
+            if key.len() as u64 != key_len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let mut value = T::zeroed();
+            r.read_exact(bytemuck::bytes_of_mut(&mut value))?;
+
+            if matches!(entries.last(), Some((prev, _)) if *prev >= key) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "keys are not sorted",
+                ));
             }
+            entries.push((key, value));
         }
-        self.children.insert(
-            max,
-            (
-                k,
-                BadTrie {
-                    children: Vec::new(),
-                    value: None,
-                },
-            ),
-        );
-        return &mut self.children[max].1;
+        Ok(SLoudsTrie::from_sorted_unique(entries))
     }
+}
+
+impl<T> SLoudsTrie<T> {
+    /// Build a trie from keys that are already sorted and unique, without
+    /// materializing an intermediate tree
+    ///
+    /// The nodes at each depth are the distinct key prefixes of that length,
+    /// and in sorted order they are also in LOUDS order, so we can build the
+    /// trie level by level from the runs of keys sharing a prefix.
+    pub fn from_sorted_unique<K, I>(input: I) -> SLoudsTrie<T>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, T)>,
+    {
+        let (keys, mut input): (Vec<K>, Vec<Option<T>>) = input
+            .into_iter()
+            .map(|(key, value)| (key, Some(value)))
+            .unzip();
+        debug_assert!(keys.windows(2).all(|w| w[0].as_ref() < w[1].as_ref()));
+
+        let mut louds = Vec::new();
+        let mut bytes = Vec::new();
+        let mut values = Vec::with_capacity(input.len());
+        let mut has_value = Vec::new();
+
+        // Each run of keys shares a prefix of length `depth`
+        let mut runs = vec![(0, keys.len())];
+        let mut depth = 0;
+        while !runs.is_empty() {
+            let mut next_runs = Vec::new();
+            for (mut start, run_end) in runs {
+                if start < run_end && keys[start].as_ref().len() == depth {
+                    values.push(input[start].take().unwrap());
+                    has_value.push(true);
+                    start += 1;
+                } else {
+                    has_value.push(false);
+                }
 
-    fn insert(&mut self, key: &[u8], value: T) {
-        if key == b"" {
-            self.value = Some(value);
-            return;
+                while start < run_end {
+                    let byte = keys[start].as_ref()[depth];
+                    let mut end = start + 1;
+                    while end < run_end && keys[end].as_ref()[depth] == byte {
+                        end += 1;
+                    }
+                    louds.push(true);
+                    bytes.push(byte);
+                    next_runs.push((start, end));
+                    start = end;
+                }
+                louds.push(false);
+            }
+            runs = next_runs;
+            depth += 1;
         }
 
-        let mut node = self;
-        for k in key.iter().cloned() {
-            node = node.get_or_insert_child(k);
+        SLoudsTrie {
+            trie: SBitVec::from_iter(louds),
+            has_value: SBitVec::from_iter(has_value),
+            bytes,
+            values,
         }
-        node.value = Some(value);
     }
 }
 
-impl<T, K> FromIterator<(K, T)> for SLoudsTrie<T>
-where
-    K: AsRef<[u8]>,
-{
-    fn from_iter<I>(input: I) -> Self
+impl<T: Send> SLoudsTrie<T> {
+    /// Build a trie on `num_shards` threads
+    ///
+    /// Keys are split into shards by ranges of their first byte, so each
+    /// shard's subtries are disjoint and can be built independently before
+    /// being joined under a common root. Like `from_iter`, later duplicate
+    /// keys overwrite earlier ones.
+    pub fn build_sharded<K, I>(input: I, num_shards: usize) -> SLoudsTrie<T>
     where
+        K: AsRef<[u8]> + Send,
         I: IntoIterator<Item = (K, T)>,
     {
-        let mut trie = BadTrie {
+        assert!(num_shards > 0);
+        let mut shards =
+            (0..num_shards).map(|_| Vec::new()).collect::<Vec<_>>();
+        for (key, value) in input.into_iter() {
+            let shard = key
+                .as_ref()
+                .first()
+                .map_or(0, |&b| b as usize * num_shards / 256);
+            shards[shard].push((key, value));
+        }
+
+        let tries = std::thread::scope(|scope| {
+            let handles = shards
+                .into_iter()
+                .map(|shard| {
+                    scope.spawn(move || {
+                        let mut trie = LoudsBuilder::<T>::new();
+                        for (key, value) in shard {
+                            trie.insert(key, value);
+                        }
+                        trie
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        // Shards cover increasing byte ranges, so appending their children
+        // keeps the root's children sorted
+        let mut root = LoudsBuilder::new();
+        for mut trie in tries {
+            root.children.append(&mut trie.children);
+            if trie.value.is_some() {
+                root.value = trie.value;
+            }
+        }
+        root.build()
+    }
+}
+
+impl<T: Clone> SLoudsTrie<T> {
+    /// Return a copy of this trie without `key`
+    pub fn without<K: AsRef<[u8]>>(&self, key: K) -> SLoudsTrie<T> {
+        self.without_all(std::iter::once(key))
+    }
+
+    /// Return a copy of this trie without any of `keys`
+    ///
+    /// This rebuilds the trie once, so prefer it over repeated `without`s.
+    pub fn without_all<K, I>(&self, keys: I) -> SLoudsTrie<T>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = K>,
+    {
+        let removed = keys
+            .into_iter()
+            .map(|key| key.as_ref().to_vec())
+            .collect::<HashSet<_>>();
+        self.iter()
+            .filter(|(key, _)| !removed.contains(key))
+            .map(|(key, value)| (key, value.clone()))
+            .collect()
+    }
+}
+
+/// Builds an `SLoudsTrie` from keys in any order
+///
+/// Keys go into a pointer-based tree, which is flattened into LOUDS order
+/// by `build`. Each node of the tree can also hold an aggregate (such as a
+/// count or maximum over its subtree), which `build_with_aggregates` returns
+/// in the same order as the nodes of the trie (see `SLoudsTrie::node_index`).
+pub struct LoudsBuilder<T, A = ()> {
+    children: Vec<(u8, LoudsBuilder<T, A>)>,
+    value: Option<T>,
+    aggregate: Option<A>,
+}
+
+impl<T, A> Default for LoudsBuilder<T, A> {
+    fn default() -> Self {
+        LoudsBuilder::new()
+    }
+}
+
+impl<T, A> LoudsBuilder<T, A> {
+    pub fn new() -> LoudsBuilder<T, A> {
+        LoudsBuilder {
             children: Vec::new(),
             value: None,
-        };
-        for (key, value) in input.into_iter() {
-            trie.insert(key.as_ref(), value);
+            aggregate: None,
         }
+    }
 
+    /// Insert a key, returning the value it replaced
+    pub fn insert<K: AsRef<[u8]>>(&mut self, key: K, value: T) -> Option<T> {
+        self.node_mut(key.as_ref()).value.replace(value)
+    }
+
+    /// Attach `aggregate` to the node for `prefix` (creating it if needed),
+    /// returning the aggregate it replaced
+    pub fn set_node_aggregate<K: AsRef<[u8]>>(
+        &mut self,
+        prefix: K,
+        aggregate: A,
+    ) -> Option<A> {
+        self.node_mut(prefix.as_ref()).aggregate.replace(aggregate)
+    }
+
+    pub fn build(self) -> SLoudsTrie<T> {
+        self.build_with_aggregates().0
+    }
+
+    /// Build the trie, along with each node's aggregate indexed by the
+    /// node's `SLoudsTrie::node_index`
+    pub fn build_with_aggregates(self) -> (SLoudsTrie<T>, Vec<Option<A>>) {
         let mut louds = Vec::new();
         let mut bytes: Vec<u8> = Vec::new();
         let mut values = Vec::new();
         let mut has_value = Vec::new();
+        let mut aggregates = Vec::new();
 
         let mut queue = VecDeque::new();
-        queue.push_back(trie);
-        while let Some(mut current) = queue.pop_front() {
-            louds.append(&mut vec![true; current.children.len()]);
+        queue.push_back(self);
+        while let Some(current) = queue.pop_front() {
+            louds.extend(std::iter::repeat_n(true, current.children.len()));
             louds.push(false);
 
-            if let Some(value) = current.value.take() {
-                values.push(value);
-                has_value.push(true);
-            } else {
-                has_value.push(false);
-            }
+            has_value.push(current.value.is_some());
+            values.extend(current.value);
+            aggregates.push(current.aggregate);
 
-            for (k, v) in current.children.drain(..) {
+            for (k, v) in current.children {
                 bytes.push(k);
                 queue.push_back(v);
             }
@@ -173,33 +785,80 @@ where
 
         bytes.shrink_to_fit();
         values.shrink_to_fit();
-        SLoudsTrie {
+        let trie = SLoudsTrie {
             trie: SBitVec::from_iter(louds),
             has_value: SBitVec::from_iter(has_value),
             bytes,
             values,
+        };
+        (trie, aggregates)
+    }
+
+    /// Find the node for `key`, creating it (and its ancestors) if needed
+    fn node_mut(&mut self, key: &[u8]) -> &mut LoudsBuilder<T, A> {
+        let mut node = self;
+        for &byte in key.iter() {
+            let i = match node.children.binary_search_by_key(&byte, |c| c.0) {
+                Ok(i) => i,
+                Err(i) => {
+                    node.children.insert(i, (byte, LoudsBuilder::new()));
+                    i
+                }
+            };
+            node = &mut node.children[i].1;
         }
+        node
     }
 }
 
-impl<T: Clone> From<LoudsTrie<T>> for SLoudsTrie<T> {
-    fn from(louds: LoudsTrie<T>) -> SLoudsTrie<T> {
+impl<T, K> FromIterator<(K, T)> for SLoudsTrie<T>
+where
+    K: AsRef<[u8]>,
+{
+    fn from_iter<I>(input: I) -> Self
+    where
+        I: IntoIterator<Item = (K, T)>,
+    {
+        let mut builder = LoudsBuilder::<T>::new();
+        for (key, value) in input.into_iter() {
+            builder.insert(key, value);
+        }
+        builder.build()
+    }
+}
+
+impl<T, V, A> From<LoudsTrie<T, ByteTree<A>, V, A>> for SLoudsTrie<T>
+where
+    T: Clone,
+    V: ValueStore<T>,
+    A: Allocator,
+{
+    fn from(louds: LoudsTrie<T, ByteTree<A>, V, A>) -> SLoudsTrie<T> {
         SLoudsTrie {
             trie: SBitVec::from_iter(louds.trie.iter()),
             has_value: SBitVec::from_iter(louds.has_value.iter()),
             bytes: Vec::from_iter(louds.bytes.iter()),
-            values: Vec::from_iter(louds.values.iter().cloned()),
+            values: (0..louds.len())
+                .map(|i| louds.values.get_cloned(i))
+                .collect(),
         }
     }
 }
 
-impl<T: Clone> From<&LoudsTrie<T>> for SLoudsTrie<T> {
-    fn from(louds: &LoudsTrie<T>) -> SLoudsTrie<T> {
+impl<T, V, A> From<&LoudsTrie<T, ByteTree<A>, V, A>> for SLoudsTrie<T>
+where
+    T: Clone,
+    V: ValueStore<T>,
+    A: Allocator,
+{
+    fn from(louds: &LoudsTrie<T, ByteTree<A>, V, A>) -> SLoudsTrie<T> {
         SLoudsTrie {
             trie: SBitVec::from_iter(louds.trie.iter()),
             has_value: SBitVec::from_iter(louds.has_value.iter()),
             bytes: Vec::from_iter(louds.bytes.iter()),
-            values: Vec::from_iter(louds.values.iter().cloned()),
+            values: (0..louds.len())
+                .map(|i| louds.values.get_cloned(i))
+                .collect(),
         }
     }
 }
@@ -207,6 +866,7 @@ impl<T: Clone> From<&LoudsTrie<T>> for SLoudsTrie<T> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
 
     #[test]
     fn test_slouds_from_iter() {
@@ -370,6 +1030,53 @@ mod test {
         assert_eq!(slouds.get(b"belarus"), None);
         assert_eq!(slouds.get(b"dh"), None);
         assert_eq!(slouds.get(b"dj"), None);
+
+        let set = SLoudsTrie::from_iter(keys.iter().map(|k| (k, ())));
+        assert!(keys.iter().all(|k| set.contains_key(k)));
+        assert!(!set.contains_key(b"belarus"));
+        assert!(!set.contains_key(b"dh"));
+        assert!(!set.contains_key(b""));
+    }
+
+    #[test]
+    fn test_slouds_size_breakdown() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let slouds = SLoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+
+        let sizes = slouds.size_breakdown();
+        assert_eq!(sizes.total(), slouds.total_size());
+        assert_eq!(sizes.bytes, 16);
+        assert_eq!(sizes.values, 11);
+        assert_eq!(sizes.overhead, std::mem::size_of::<SLoudsTrie<u8>>());
+    }
+
+    #[test]
+    fn test_slouds_children_of() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let slouds = SLoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+
+        assert_eq!(
+            slouds.children_of(b"d"),
+            Some(vec![
+                (b'h', false),
+                (b'i', true),
+                (b'j', false),
+                (b'k', true)
+            ])
+        );
+        assert_eq!(
+            slouds.children_of(b""),
+            Some(vec![(b'b', true), (b'c', false), (b'd', false)])
+        );
+        assert_eq!(slouds.children_of(b"bel"), Some(vec![]));
+        assert_eq!(slouds.children_of(b"bx"), None);
+        assert_eq!(slouds.children_of(b"belt"), None);
     }
 
     #[test]
@@ -388,6 +1095,381 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_slouds_iter() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let slouds = SLoudsTrie::from_iter(keys.iter().map(|k| (k, k.len())));
+
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        assert_eq!(
+            slouds.iter().map(|(k, v)| (k, *v)).collect::<Vec<_>>(),
+            sorted
+                .iter()
+                .map(|k| (k.to_vec(), k.len()))
+                .collect::<Vec<_>>()
+        );
+
+        let slouds = SLoudsTrie::from_iter(vec![(&b""[..], 0), (&b"a"[..], 1)]);
+        assert_eq!(
+            slouds.iter().collect::<Vec<_>>(),
+            vec![(b"".to_vec(), &0), (b"a".to_vec(), &1)]
+        );
+    }
+
+    #[test]
+    fn test_slouds_suggest() {
+        let words: [&[u8]; 5] = [b"bell", b"belt", b"bolt", b"melt", b"x"];
+        let slouds = SLoudsTrie::from_iter(words.iter().map(|w| (w, ())));
+
+        // Mixing up `a` and `e` is cheap, any other substitution is not
+        let cost = |a: u8, b: u8| match (a, b) {
+            (b'a', b'e') | (b'e', b'a') => 1,
+            _ => 3,
+        };
+        assert_eq!(
+            slouds.suggest(b"balt", &cost, 3),
+            vec![
+                (b"belt".to_vec(), 1),
+                (b"bolt".to_vec(), 2),
+                (b"bell".to_vec(), 3),
+                (b"melt".to_vec(), 3),
+            ]
+        );
+        assert_eq!(
+            slouds.suggest(b"belt", &cost, 0),
+            vec![(b"belt".to_vec(), 0)]
+        );
+
+        // With unit costs, this finds the same keys as `fuzzy`
+        let mut unit = slouds.suggest(b"balt", &|_, _| 1, 1);
+        unit.sort();
+        let fuzzy = slouds.fuzzy(b"balt", 1);
+        assert!(unit.iter().map(|(k, _)| k).eq(fuzzy.iter().map(|(k, _)| k)));
+    }
+
+    #[test]
+    fn test_slouds_fuzzy() {
+        let words: [&[u8]; 9] = [
+            b"cat", b"cart", b"cast", b"cot", b"dog", b"at", b"scat",
+            b"cattle", b"c",
+        ];
+        let slouds = SLoudsTrie::from_iter(
+            words.iter().enumerate().map(|(i, w)| (w, i)),
+        );
+
+        let keys = |dist| {
+            slouds
+                .fuzzy(b"cat", dist)
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(0), vec![b"cat".to_vec()]);
+        assert_eq!(
+            keys(1),
+            vec![
+                b"at".to_vec(),
+                b"cart".to_vec(),
+                b"cast".to_vec(),
+                b"cat".to_vec(),
+                b"cot".to_vec(),
+                b"scat".to_vec(),
+            ]
+        );
+        assert!(keys(2).contains(&b"c".to_vec()));
+        assert!(!keys(2).contains(&b"dog".to_vec()));
+        assert!(!keys(2).contains(&b"cattle".to_vec()));
+        assert!(keys(3).contains(&b"cattle".to_vec()));
+
+        assert_eq!(slouds.fuzzy(b"cot", 0), vec![(b"cot".to_vec(), &3)]);
+        assert_eq!(slouds.fuzzy(b"xyzzy", 1), vec![]);
+    }
+
+    #[test]
+    fn test_slouds_longest_common_prefix_keys() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let slouds = SLoudsTrie::from_iter(keys.iter().map(|k| (k, ())));
+        let lcp = |query: &[u8]| {
+            let (depth, keys) = slouds.longest_common_prefix_keys(query);
+            let keys = keys.into_iter().map(|k| String::from_utf8(k).unwrap());
+            (depth, keys.collect::<Vec<_>>())
+        };
+
+        assert_eq!(lcp(b"dj"), (2, vec!["djp".into(), "djq".into()]));
+        assert_eq!(lcp(b"djx"), (2, vec!["djp".into(), "djq".into()]));
+        assert_eq!(lcp(b"djq"), (3, vec!["djq".into()]));
+        assert_eq!(lcp(b"djqq"), (3, vec!["djq".into()]));
+        assert_eq!(
+            lcp(b"bex"),
+            (2, vec!["bel".into(), "bem".into(), "ben".into()])
+        );
+        assert_eq!(lcp(b"z").0, 0);
+        assert_eq!(lcp(b"z").1.len(), keys.len());
+        assert_eq!(lcp(b"").1.len(), keys.len());
+    }
+
+    #[test]
+    fn test_slouds_min_max_key() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let slouds = SLoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+        assert_eq!(slouds.min_key(), Some(b"b".to_vec()));
+        assert_eq!(slouds.max_key(), Some(b"dk".to_vec()));
+
+        let slouds = slouds.without(b"b");
+        assert_eq!(slouds.min_key(), Some(b"bel".to_vec()));
+
+        let slouds = SLoudsTrie::from_iter(vec![(&b""[..], 0), (&b"a"[..], 1)]);
+        assert_eq!(slouds.min_key(), Some(b"".to_vec()));
+        assert_eq!(slouds.max_key(), Some(b"a".to_vec()));
+
+        let slouds = SLoudsTrie::from_iter(vec![(&b""[..], 0)]);
+        assert_eq!(slouds.min_key(), Some(b"".to_vec()));
+        assert_eq!(slouds.max_key(), Some(b"".to_vec()));
+
+        let slouds = SLoudsTrie::<u8>::from_iter(Vec::<(&[u8], u8)>::new());
+        assert_eq!(slouds.min_key(), None);
+        assert_eq!(slouds.max_key(), None);
+
+        // Taken keys leave valueless leaves behind
+        let mut louds = LoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+        louds.take(b"b");
+        louds.take(b"bel");
+        louds.take(b"dk");
+        let slouds = SLoudsTrie::from(&louds);
+        assert_eq!(slouds.min_key(), Some(b"bem".to_vec()));
+        assert_eq!(slouds.max_key(), Some(b"djq".to_vec()));
+    }
+
+    #[test]
+    fn test_slouds_without() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let slouds = SLoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+
+        let without = slouds.without(b"b");
+        assert_eq!(without.get(b"b"), None);
+        for key in keys.iter().filter(|&&k| k != &b"b"[..]) {
+            assert_eq!(without.get(key), Some(&key[0]));
+        }
+
+        // Removing keys that aren't present is a no-op
+        assert_eq!(slouds.without(b"be"), slouds);
+        assert_eq!(slouds.without(b"zzz"), slouds);
+
+        let removed: [&[u8]; 4] = [b"bel", b"cg", b"dk", b"djq"];
+        let without = slouds.without_all(removed.iter());
+        for key in keys.iter() {
+            if removed.contains(key) {
+                assert_eq!(without.get(key), None);
+            } else {
+                assert_eq!(without.get(key), Some(&key[0]));
+            }
+        }
+        assert_eq!(without.bytes.len(), slouds.bytes.len() - 5);
+    }
+
+    #[test]
+    fn test_slouds_rank_select_key() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let slouds = SLoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+
+        for (rank, key) in sorted.iter().enumerate() {
+            assert_eq!(slouds.rank_of_key(key), rank);
+            assert_eq!(slouds.select_key(rank), key.to_vec());
+        }
+
+        assert_eq!(slouds.rank_of_key(b""), 0);
+        assert_eq!(slouds.rank_of_key(b"a"), 0);
+        assert_eq!(slouds.rank_of_key(b"be"), 1);
+        assert_eq!(slouds.rank_of_key(b"bela"), 2);
+        assert_eq!(slouds.rank_of_key(b"c"), 5);
+        assert_eq!(slouds.rank_of_key(b"dj"), 8);
+        assert_eq!(slouds.rank_of_key(b"dja"), 8);
+        assert_eq!(slouds.rank_of_key(b"djz"), 10);
+        assert_eq!(slouds.rank_of_key(b"e"), 11);
+
+        let louds = LoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])));
+        let slouds = SLoudsTrie::from(&louds);
+        for (rank, key) in sorted.iter().enumerate() {
+            assert_eq!(slouds.rank_of_key(key), rank);
+            assert_eq!(slouds.select_key(rank), key.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_slouds_count_prefix() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let slouds = SLoudsTrie::from_iter(keys.iter().map(|k| (k, ())));
+        let prefixes: [&[u8]; 7] =
+            [b"", b"b", b"be", b"bel", b"d", b"dj", b"x"];
+        for prefix in prefixes.iter() {
+            let expected = keys.iter().filter(|k| k.starts_with(prefix));
+            assert_eq!(slouds.count_prefix(prefix), expected.count());
+        }
+        assert_eq!(slouds.count_prefix(b"d"), 5);
+        assert_eq!(slouds.count_prefix(b"belarus"), 0);
+    }
+
+    #[test]
+    fn test_slouds_rank_many_keys() {
+        let mut rng = SmallRng::from_seed([5; 16]);
+        let mut keys = (0..3000)
+            .map(|_| {
+                let len = rng.gen_range(1, 9);
+                (0..len).map(|_| rng.gen_range(0, 7)).collect()
+            })
+            .collect::<Vec<Vec<u8>>>();
+        keys.sort();
+        keys.dedup();
+        let slouds = SLoudsTrie::from_iter(keys.iter().map(|k| (k, ())));
+
+        for (rank, key) in keys.iter().enumerate() {
+            assert_eq!(slouds.rank_of_key(key), rank);
+            assert_eq!(slouds.select_key(rank), *key);
+
+            let prefix = &key[..key.len() / 2];
+            let expected = keys.iter().filter(|k| k.starts_with(prefix));
+            assert_eq!(slouds.count_prefix(prefix), expected.count());
+        }
+        assert_eq!(slouds.min_key().as_ref(), keys.first());
+        assert_eq!(slouds.max_key().as_ref(), keys.last());
+    }
+
+    #[test]
+    fn test_slouds_builder() {
+        let keys: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let mut builder = LoudsBuilder::new();
+        for key in keys.iter() {
+            assert_eq!(builder.insert(key, key[0]), None);
+        }
+        assert_eq!(builder.insert(b"bf", b'b'), Some(b'b'));
+
+        // Count the keys under each first byte
+        for key in keys.iter() {
+            let count = builder.set_node_aggregate(&key[..1], 0).unwrap_or(0);
+            builder.set_node_aggregate(&key[..1], count + 1);
+        }
+        let (slouds, aggregates) = builder.build_with_aggregates();
+        assert_eq!(
+            slouds,
+            SLoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])))
+        );
+
+        assert_eq!(aggregates.len(), slouds.has_value.len());
+        for prefix in [&b"b"[..], b"c", b"d"].iter() {
+            let index = slouds.node_index(prefix).unwrap();
+            assert_eq!(aggregates[index], Some(slouds.count_prefix(prefix)));
+        }
+        assert_eq!(aggregates[slouds.node_index(b"").unwrap()], None);
+        assert_eq!(aggregates[slouds.node_index(b"dj").unwrap()], None);
+        assert_eq!(slouds.node_index(b"bex"), None);
+    }
+
+    #[test]
+    fn test_slouds_from_sorted_unique() {
+        let mut keys: Vec<&[u8]> = vec![
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        keys.sort();
+        assert_eq!(
+            SLoudsTrie::from_sorted_unique(keys.iter().map(|k| (k, k[0]))),
+            SLoudsTrie::from_iter(keys.iter().map(|k| (k, k[0])))
+        );
+
+        let mut keys = (0..5000u32)
+            .map(|i| (i * 7919 % 100000).to_string().into_bytes())
+            .collect::<Vec<_>>();
+        keys.push(b"".to_vec());
+        keys.sort();
+        keys.dedup();
+        assert_eq!(
+            SLoudsTrie::from_sorted_unique(
+                keys.iter().enumerate().map(|(i, k)| (k, i))
+            ),
+            SLoudsTrie::from_iter(keys.iter().enumerate().map(|(i, k)| (k, i)))
+        );
+
+        assert_eq!(
+            SLoudsTrie::<u8>::from_sorted_unique(Vec::<(&[u8], u8)>::new()),
+            SLoudsTrie::from_iter(Vec::<(&[u8], u8)>::new())
+        );
+    }
+
+    #[test]
+    fn test_slouds_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SLoudsTrie<u32>>();
+
+        let keys = (0..10000u32)
+            .map(|i| (i * 7919).to_string().into_bytes())
+            .collect::<Vec<_>>();
+        let slouds = std::sync::Arc::new(SLoudsTrie::from_iter(
+            keys.iter().enumerate().map(|(i, k)| (k, i)),
+        ));
+        let keys = std::sync::Arc::new(keys);
+
+        let handles = (0..4)
+            .map(|t| {
+                let slouds = slouds.clone();
+                let keys = keys.clone();
+                std::thread::spawn(move || {
+                    for i in (t..keys.len()).step_by(4) {
+                        assert_eq!(slouds.get(&keys[i]), Some(&i));
+                    }
+                    assert_eq!(slouds.get(b"x"), None);
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_slouds_build_sharded() {
+        let mut keys = (0..5000u32)
+            .map(|i| (i * 7919 % 100000).to_string().into_bytes())
+            .collect::<Vec<_>>();
+        keys.push(b"".to_vec());
+        keys.push(vec![0]);
+        keys.push(vec![255, 1]);
+        keys.push(b"3".to_vec());
+
+        let expected =
+            SLoudsTrie::from_iter(keys.iter().enumerate().map(|(i, k)| (k, i)));
+        for &num_shards in &[1, 3, 16, 256, 300] {
+            let sharded = SLoudsTrie::build_sharded(
+                keys.iter().enumerate().map(|(i, k)| (k, i)),
+                num_shards,
+            );
+            assert_eq!(sharded, expected);
+        }
+    }
+
     #[test]
     fn test_slouds_empty() {
         let slouds = SLoudsTrie::from_iter([(b"", 0)].iter().cloned());
@@ -399,4 +1481,55 @@ mod test {
 
         assert_eq!(slouds.get(b""), Some(&0));
     }
+
+    #[test]
+    fn test_slouds_get_batch() {
+        let slouds = SLoudsTrie::from_iter(
+            [
+                b"",
+                &b"bel"[..],
+                b"bem",
+                b"ben",
+                b"bf",
+                b"cg",
+                b"dho",
+                b"djp",
+            ]
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, i)),
+        );
+
+        let mut queries: Vec<&[u8]> = vec![
+            b"ben", b"b", b"", b"bem", b"zz", b"djp", b"bel", b"dhoo", b"be",
+            b"cg", b"ben", b"dj", b"djq", b"a", b"bfx", b"bf", b"dho",
+        ];
+        for _ in 0..3 {
+            let expected =
+                queries.iter().map(|k| slouds.get(k)).collect::<Vec<_>>();
+            assert_eq!(slouds.get_batch(&queries), expected);
+            queries.reverse();
+            queries.rotate_left(5);
+        }
+        assert_eq!(slouds.get_batch(&[]), vec![]);
+    }
+
+    #[test]
+    fn test_slouds_key_to_id() {
+        let words = ["dog", "", "do", "cat", "cats", "zebra", "dot", "a"];
+        let slouds = words
+            .iter()
+            .map(|w| (w.as_bytes(), ()))
+            .collect::<SLoudsTrie<_>>();
+
+        let mut sorted = words.to_vec();
+        sorted.sort();
+        for (id, word) in sorted.iter().enumerate() {
+            assert_eq!(slouds.key_to_id(word), Some(id));
+            assert_eq!(slouds.select_key(id), word.as_bytes());
+        }
+        for absent in ["d", "ca", "catss", "b", "zebras"].iter() {
+            assert_eq!(slouds.key_to_id(absent), None);
+        }
+    }
 }
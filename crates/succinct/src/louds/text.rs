@@ -0,0 +1,126 @@
+use std::iter::FromIterator;
+
+use super::LoudsTrie;
+
+/// A `LoudsTrie` keyed by UTF-8 strings
+///
+/// Keys are stored as raw bytes, but since only `&str`s are ever inserted,
+/// every key read back out is valid UTF-8 and is returned as a `String`.
+pub struct StrLoudsTrie<T> {
+    trie: LoudsTrie<T>,
+}
+
+impl<T> StrLoudsTrie<T> {
+    pub fn new() -> StrLoudsTrie<T> {
+        StrLoudsTrie {
+            trie: LoudsTrie::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.trie.total_size()
+    }
+
+    /// Insert a key into the trie, returning the value it replaced
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+        self.trie.insert(key, value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.trie.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+        self.trie.get_mut(key)
+    }
+
+    /// Iterate over the (key, value) pairs in sorted key order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (String, &T)> + '_ {
+        self.iter_prefix("")
+    }
+
+    /// Iterate over the (key, value) pairs whose key starts with `prefix`,
+    /// in sorted key order
+    pub fn iter_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl DoubleEndedIterator<Item = (String, &'a T)> + 'a {
+        self.trie.iter_prefix(prefix).map(|(key, value)| {
+            let key = String::from_utf8(key).expect("keys are only str");
+            (key, value)
+        })
+    }
+
+    /// The underlying byte-keyed trie
+    pub fn as_louds(&self) -> &LoudsTrie<T> {
+        &self.trie
+    }
+}
+
+impl<T> Default for StrLoudsTrie<T> {
+    fn default() -> StrLoudsTrie<T> {
+        StrLoudsTrie::new()
+    }
+}
+
+impl<T, K> FromIterator<(K, T)> for StrLoudsTrie<T>
+where
+    K: AsRef<str>,
+{
+    fn from_iter<I>(input: I) -> Self
+    where
+        I: IntoIterator<Item = (K, T)>,
+    {
+        let mut trie = StrLoudsTrie::new();
+        for (key, value) in input.into_iter() {
+            trie.insert(key.as_ref(), value);
+        }
+        trie
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_str_louds_round_trip() {
+        let words = ["naïve", "naive", "東京", "日本語", "日本", "", "🦀rust"];
+        let trie = words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (w, i))
+            .collect::<StrLoudsTrie<_>>();
+        assert_eq!(trie.len(), words.len());
+
+        for (i, word) in words.iter().enumerate() {
+            assert_eq!(trie.get(word), Some(&i));
+        }
+        assert_eq!(trie.get("日"), None);
+
+        let mut expected = words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (w.to_string(), i))
+            .collect::<Vec<_>>();
+        expected.sort();
+        let actual = trie.iter().map(|(k, &v)| (k, v)).collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+        assert!(trie
+            .iter()
+            .rev()
+            .map(|(k, &v)| (k, v))
+            .eq(expected.iter().rev().cloned()));
+
+        let prefixed = trie.iter_prefix("日本").map(|(k, _)| k);
+        assert_eq!(prefixed.collect::<Vec<_>>(), vec!["日本", "日本語"]);
+    }
+}
@@ -0,0 +1,249 @@
+use std::iter::FromIterator;
+
+use super::{IntKey, LoudsTrie};
+
+/// A value that can key a `TypedLoudsTrie`
+///
+/// Encodings are compared byte-wise, so `encode` must preserve order, and
+/// no encoding may be a proper prefix of another so that fields can be
+/// concatenated. Integers are fixed-width and big-endian (see `IntKey`),
+/// while byte strings escape each `0` as `0, 0xff` and end with `0, 0`.
+pub trait TrieKey {
+    /// The type `decode` returns, which owns any borrowed data
+    type Owned;
+
+    /// Append the encoding of `self` to `out`
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decode a key written by `encode` from the front of `bytes`, moving
+    /// `bytes` past it
+    fn decode(bytes: &mut &[u8]) -> Self::Owned;
+}
+
+macro_rules! impl_int_trie_key {
+    ($($t:ty),*) => {$(
+        impl TrieKey for $t {
+            type Owned = $t;
+
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(self.to_bytes().as_ref());
+            }
+
+            fn decode(bytes: &mut &[u8]) -> $t {
+                let (head, rest) = bytes.split_at(std::mem::size_of::<$t>());
+                *bytes = rest;
+                <$t as IntKey>::from_bytes(head)
+            }
+        }
+    )*};
+}
+
+impl_int_trie_key!(u8, u16, u32, u64, u128, usize);
+impl_int_trie_key!(i8, i16, i32, i64, i128, isize);
+
+impl TrieKey for [u8] {
+    type Owned = Vec<u8>;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        for &byte in self.iter() {
+            out.push(byte);
+            if byte == 0 {
+                out.push(0xff);
+            }
+        }
+        out.extend_from_slice(&[0, 0]);
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Vec<u8> {
+        let mut decoded = Vec::new();
+        let mut i = 0;
+        loop {
+            let byte = bytes[i];
+            if byte == 0 {
+                i += 1;
+                if bytes[i] == 0 {
+                    break;
+                }
+            }
+            decoded.push(byte);
+            i += 1;
+        }
+        *bytes = &bytes[i + 1..];
+        decoded
+    }
+}
+
+impl TrieKey for Vec<u8> {
+    type Owned = Vec<u8>;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        self[..].encode(out)
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Vec<u8> {
+        <[u8]>::decode(bytes)
+    }
+}
+
+impl<K: TrieKey + ?Sized> TrieKey for &K {
+    type Owned = K::Owned;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        (**self).encode(out)
+    }
+
+    fn decode(bytes: &mut &[u8]) -> K::Owned {
+        K::decode(bytes)
+    }
+}
+
+// Each field's encoding is prefix-free, so concatenating them compares
+// tuples field by field
+macro_rules! impl_tuple_trie_key {
+    ($(($($name:ident $index:tt),*)),*) => {$(
+        impl<$($name: TrieKey),*> TrieKey for ($($name,)*) {
+            type Owned = ($($name::Owned,)*);
+
+            fn encode(&self, out: &mut Vec<u8>) {
+                $(self.$index.encode(out);)*
+            }
+
+            fn decode(bytes: &mut &[u8]) -> Self::Owned {
+                ($($name::decode(bytes),)*)
+            }
+        }
+    )*};
+}
+
+impl_tuple_trie_key!(
+    (A 0, B 1),
+    (A 0, B 1, C 2),
+    (A 0, B 1, C 2, D 3)
+);
+
+/// A `LoudsTrie` keyed by any `TrieKey`, such as a tuple of integers
+pub struct TypedLoudsTrie<K, T> {
+    trie: LoudsTrie<T>,
+    _key: std::marker::PhantomData<K>,
+}
+
+fn encode<K: TrieKey>(key: &K) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    key.encode(&mut bytes);
+    bytes
+}
+
+impl<K: TrieKey, T> TypedLoudsTrie<K, T> {
+    pub fn new() -> TypedLoudsTrie<K, T> {
+        TypedLoudsTrie {
+            trie: LoudsTrie::new(),
+            _key: std::marker::PhantomData,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.trie.total_size()
+    }
+
+    /// Insert a key into the trie, returning the value it replaced
+    pub fn insert(&mut self, key: K, value: T) -> Option<T> {
+        self.trie.insert(encode(&key), value)
+    }
+
+    pub fn contains_key(&self, key: K) -> bool {
+        self.trie.contains_key(encode(&key))
+    }
+
+    pub fn get(&self, key: K) -> Option<&T> {
+        self.trie.get(encode(&key))
+    }
+
+    pub fn get_mut(&mut self, key: K) -> Option<&mut T> {
+        self.trie.get_mut(encode(&key))
+    }
+
+    /// Iterate over the (key, value) pairs in increasing key order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (K::Owned, &T)> + '_ {
+        self.trie
+            .iter()
+            .map(|(key, value)| (K::decode(&mut &key[..]), value))
+    }
+
+    /// The underlying byte-keyed trie
+    pub fn as_louds(&self) -> &LoudsTrie<T> {
+        &self.trie
+    }
+}
+
+impl<K: TrieKey, T> Default for TypedLoudsTrie<K, T> {
+    fn default() -> TypedLoudsTrie<K, T> {
+        TypedLoudsTrie::new()
+    }
+}
+
+impl<K: TrieKey, T> FromIterator<(K, T)> for TypedLoudsTrie<K, T> {
+    fn from_iter<It>(input: It) -> Self
+    where
+        It: IntoIterator<Item = (K, T)>,
+    {
+        let mut trie = TypedLoudsTrie::new();
+        for (key, value) in input.into_iter() {
+            trie.insert(key, value);
+        }
+        trie
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_typed_trie_tuples() {
+        let trie = vec![((2u32, 0u16), 'c'), ((1, 3), 'b'), ((1, 2), 'a')]
+            .into_iter()
+            .collect::<TypedLoudsTrie<_, _>>();
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            vec![((1, 2), &'a'), ((1, 3), &'b'), ((2, 0), &'c')]
+        );
+        assert_eq!(trie.get((1, 3)), Some(&'b'));
+        assert!(!trie.contains_key((3, 1)));
+
+        let signed = [(-1i8, 5u64, -7i64), (-1, 5, 3), (-2, 9, 0), (0, 0, 0)]
+            .iter()
+            .map(|&k| (k, ()))
+            .collect::<TypedLoudsTrie<_, _>>();
+        let keys = signed.iter().map(|(k, _)| k).collect::<Vec<_>>();
+        assert_eq!(keys, vec![(-2, 9, 0), (-1, 5, -7), (-1, 5, 3), (0, 0, 0)]);
+    }
+
+    #[test]
+    fn test_typed_trie_byte_strings() {
+        let words: [&[u8]; 6] = [b"ab\0", b"abc", b"a", b"", b"ab", b"\0"];
+        let trie = words
+            .iter()
+            .enumerate()
+            .map(|(i, &w)| ((w, i as u8), i))
+            .collect::<TypedLoudsTrie<_, _>>();
+
+        let mut expected = words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| ((w.to_vec(), i as u8), i))
+            .collect::<Vec<_>>();
+        expected.sort();
+        let actual = trie.iter().map(|(k, &v)| (k, v)).collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+        assert_eq!(trie.get((&b"ab"[..], 4)), Some(&4));
+        assert_eq!(trie.get((&b"ab"[..], 0)), None);
+    }
+}
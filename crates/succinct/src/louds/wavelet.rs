@@ -0,0 +1,192 @@
+use std::iter::FromIterator;
+
+use super::SLoudsTrie;
+use crate::select_rank::{SBitVec, SelectRank};
+
+/// The number of 1s before `index`, where `index` may be `bits.len()`
+fn rank1(bits: &SBitVec, index: usize) -> usize {
+    if index == bits.len() {
+        bits.num_ones()
+    } else {
+        bits.rank1(index)
+    }
+}
+
+/// A wavelet matrix over a sequence of bytes, answering how often a byte
+/// occurs in a prefix of the sequence with one rank per bit
+#[derive(Debug)]
+struct WaveletMatrix {
+    // The bits of each byte (most significant first), with the sequence
+    // stably sorted by the bits above at each layer, along with the number
+    // of 0s in the layer
+    layers: Vec<(SBitVec, usize)>,
+}
+
+impl WaveletMatrix {
+    fn new(mut bytes: Vec<u8>) -> WaveletMatrix {
+        let mut layers = Vec::with_capacity(8);
+        for shift in (0..8).rev() {
+            let bits =
+                SBitVec::from_iter(bytes.iter().map(|b| b >> shift & 1 == 1));
+            let zeros = bits.len() - bits.num_ones();
+            let (mut low, high): (Vec<u8>, Vec<u8>) =
+                bytes.iter().partition(|&b| b >> shift & 1 == 0);
+            low.extend(high);
+            bytes = low;
+            layers.push((bits, zeros));
+        }
+        WaveletMatrix { layers }
+    }
+
+    fn total_size(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|(bits, _)| bits.total_size())
+            .sum::<usize>()
+            + self.layers.capacity() * std::mem::size_of::<(SBitVec, usize)>()
+    }
+
+    /// The number of times `byte` occurs before `index`
+    fn rank(&self, byte: u8, index: usize) -> usize {
+        // Follow where position 0 and `index` move to under `byte`'s bits;
+        // the copies of `byte` before `index` end up between them
+        let (mut start, mut end) = (0, index);
+        for (layer, (bits, zeros)) in self.layers.iter().enumerate() {
+            if byte >> (7 - layer) & 1 == 0 {
+                start -= rank1(bits, start);
+                end -= rank1(bits, end);
+            } else {
+                start = zeros + rank1(bits, start);
+                end = zeros + rank1(bits, end);
+            }
+        }
+        end - start
+    }
+}
+
+/// The bytes at one depth of the keys
+#[derive(Debug)]
+struct Level {
+    // Whether each key (in sorted order) is long enough to have a byte here
+    longer: SBitVec,
+    bytes: WaveletMatrix,
+}
+
+/// A static trie that can count the keys passing through each edge
+///
+/// For each depth, the bytes of the keys at that depth (in sorted key
+/// order) are kept in a wavelet matrix. The keys under a prefix are a
+/// contiguous run in sorted order, so counting the keys under a prefix that
+/// continue with some byte is a rank query over that run.
+#[derive(Debug)]
+pub struct WaveletTrie {
+    trie: SLoudsTrie<()>,
+    levels: Vec<Level>,
+}
+
+impl WaveletTrie {
+    /// Build the trie from sorted, unique keys
+    pub fn from_sorted_unique<K, I>(keys: I) -> WaveletTrie
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = K>,
+    {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        let trie = SLoudsTrie::from_sorted_unique(keys.iter().map(|k| (k, ())));
+
+        let depth = keys.iter().map(|k| k.as_ref().len()).max().unwrap_or(0);
+        let levels = (0..depth)
+            .map(|d| {
+                let longer = keys.iter().map(|k| k.as_ref().len() > d);
+                let bytes = keys.iter().filter_map(|k| k.as_ref().get(d));
+                Level {
+                    longer: SBitVec::from_iter(longer),
+                    bytes: WaveletMatrix::new(bytes.cloned().collect()),
+                }
+            })
+            .collect();
+        WaveletTrie { trie, levels }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    pub fn total_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.trie.total_size()
+            + self
+                .levels
+                .iter()
+                .map(|level| {
+                    level.longer.total_size() + level.bytes.total_size()
+                })
+                .sum::<usize>()
+    }
+
+    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> bool {
+        self.trie.contains_key(key)
+    }
+
+    /// Return the number of keys starting with `prefix` whose next byte is
+    /// `byte`
+    pub fn rank<K: AsRef<[u8]>>(&self, prefix: K, byte: u8) -> usize {
+        let prefix = prefix.as_ref();
+        let level = match self.levels.get(prefix.len()) {
+            Some(level) => level,
+            None => return 0,
+        };
+
+        let start = self.trie.rank_of_key(prefix);
+        let end = start + self.trie.count_prefix(prefix);
+        let start = rank1(&level.longer, start);
+        let end = rank1(&level.longer, end);
+        level.bytes.rank(byte, end) - level.bytes.rank(byte, start)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wavelet_trie_rank() {
+        let fixture: [&[u8]; 11] = [
+            b"bel", b"bem", b"ben", b"bf", b"cg", b"dho", b"di", b"djp",
+            b"djq", b"dk", b"b",
+        ];
+        let mut keys = fixture.to_vec();
+        keys.extend(vec![&b""[..], b"\xff\x00", b"\xff"]);
+        keys.sort();
+        let trie = WaveletTrie::from_sorted_unique(keys.iter());
+        assert_eq!(trie.len(), keys.len());
+        assert!(trie.contains_key(b"djq"));
+        assert!(!trie.contains_key(b"dj"));
+
+        let mut prefixes = keys
+            .iter()
+            .flat_map(|k| (0..=k.len()).map(move |i| &k[..i]))
+            .collect::<Vec<_>>();
+        prefixes.extend(vec![&b"a"[..], b"bex", b"dz", b"benx"]);
+        for prefix in prefixes.iter() {
+            for byte in 0..=255 {
+                let expected = keys
+                    .iter()
+                    .filter(|k| k.starts_with(prefix))
+                    .filter(|k| k.get(prefix.len()) == Some(&byte))
+                    .count();
+                assert_eq!(trie.rank(prefix, byte), expected);
+            }
+        }
+        assert_eq!(trie.rank(b"", b'd'), 5);
+        assert_eq!(trie.rank(b"dj", b'q'), 1);
+
+        let empty = WaveletTrie::from_sorted_unique(Vec::<&[u8]>::new());
+        assert!(empty.is_empty());
+        assert_eq!(empty.rank(b"", 0), 0);
+    }
+}
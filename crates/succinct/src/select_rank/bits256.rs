@@ -6,11 +6,151 @@ use super::{
 const INCREMENT: [u32; 4] =
     [0x01_01_01_00, 0x01_01_00_00, 0x01_00_00_00, 0x00_00_00_00];
 
+/// The word shifts behind `Bits256::insert_bit` and `remove_bit`
+///
+/// Only one of the scalar and AVX2 versions is used in a given build, but
+/// the tests compare the two.
+#[cfg_attr(not(test), allow(dead_code))]
+pub(crate) mod shift {
+    /// For each word index, a mask selecting the words above it
+    #[cfg(target_arch = "x86_64")]
+    const ABOVE: [[u64; 4]; 4] =
+        [[0, !0, !0, !0], [0, 0, !0, !0], [0, 0, 0, !0], [0, 0, 0, 0]];
+
+    /// For each word index, a mask selecting the words from it up to (but
+    /// not including) the last word
+    #[cfg(target_arch = "x86_64")]
+    const BELOW_LAST: [[u64; 4]; 4] =
+        [[!0, !0, !0, 0], [0, !0, !0, 0], [0, 0, !0, 0], [0, 0, 0, 0]];
+
+    /// Shift the words above `upper` up by one bit, carrying in the top bit
+    /// of the word below
+    #[inline]
+    pub(crate) fn up(bits: &mut [u64; 4], upper: usize) {
+        #[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
+        unsafe {
+            up_avx2(bits, upper)
+        }
+        #[cfg(not(all(target_arch = "x86_64", target_feature = "avx2")))]
+        up_scalar(bits, upper)
+    }
+
+    /// Shift the words above `upper` down by one bit, carrying their bottom
+    /// bit into the (empty) top bit of the word below
+    #[inline]
+    pub(crate) fn down(bits: &mut [u64; 4], upper: usize) {
+        #[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
+        unsafe {
+            down_avx2(bits, upper)
+        }
+        #[cfg(not(all(target_arch = "x86_64", target_feature = "avx2")))]
+        down_scalar(bits, upper)
+    }
+
+    pub(crate) fn up_scalar(bits: &mut [u64; 4], upper: usize) {
+        for i in (upper + 1..4).rev() {
+            bits[i] = (bits[i] << 1) | (bits[i - 1] >> 63);
+        }
+    }
+
+    pub(crate) fn down_scalar(bits: &mut [u64; 4], upper: usize) {
+        for i in upper + 1..4 {
+            bits[i - 1] |= bits[i] << 63;
+            bits[i] >>= 1;
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The CPU must support AVX2.
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    pub(crate) unsafe fn up_avx2(bits: &mut [u64; 4], upper: usize) {
+        use std::arch::x86_64::*;
+
+        let ptr = bits.as_mut_ptr() as *mut __m256i;
+        let v = _mm256_loadu_si256(ptr);
+        let mask = _mm256_loadu_si256(ABOVE[upper].as_ptr() as *const _);
+
+        // [v3, v0, v1, v2]: word i now holds the word below it
+        let below = _mm256_permute4x64_epi64(v, 0b10_01_00_11);
+        let shifted = _mm256_or_si256(
+            _mm256_slli_epi64(v, 1),
+            _mm256_srli_epi64(below, 63),
+        );
+        _mm256_storeu_si256(ptr, _mm256_blendv_epi8(v, shifted, mask));
+    }
+
+    /// # Safety
+    ///
+    /// The CPU must support AVX2.
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    pub(crate) unsafe fn down_avx2(bits: &mut [u64; 4], upper: usize) {
+        use std::arch::x86_64::*;
+
+        let ptr = bits.as_mut_ptr() as *mut __m256i;
+        let v = _mm256_loadu_si256(ptr);
+        let above = _mm256_loadu_si256(ABOVE[upper].as_ptr() as *const _);
+        let carry_mask =
+            _mm256_loadu_si256(BELOW_LAST[upper].as_ptr() as *const _);
+
+        // [v1, v2, v3, v0]: word i now holds the word above it
+        let next = _mm256_permute4x64_epi64(v, 0b00_11_10_01);
+        let carry = _mm256_and_si256(_mm256_slli_epi64(next, 63), carry_mask);
+        let shifted = _mm256_blendv_epi8(v, _mm256_srli_epi64(v, 1), above);
+        _mm256_storeu_si256(ptr, _mm256_or_si256(shifted, carry));
+    }
+}
+
 /// A bitstring holding up to 256 bits
 ///
 /// We will assume that the bits are "packed" -- in other words, that if
 /// this bitstring contains n bits, then it will be the *first* n bits
 /// in the bitstring
+///
+/// Ranks are exclusive: `rank1(i)` counts the ones strictly before
+/// position `i`, so `rank1(0) == 0` and `rank1(len()) == num_ones()`.
+/// Selects are 0-indexed, so `select1(0)` is the position of the first
+/// one, and `select1(rank1(i)) == i` whenever bit `i` is set.
+///
+/// ```
+/// use succinct::select_rank::{Bits256, SelectRank};
+///
+/// let mut block = Bits256::new();
+/// for i in 0..200 {
+///     block.insert_bit(i, i % 4 == 1);
+/// }
+/// assert_eq!(block.num_ones(), 50);
+///
+/// assert_eq!(block.rank1(6), 2);
+/// assert_eq!(block.select1(2), 9);
+/// assert_eq!(block.select0(block.rank0(100)), 100);
+///
+/// assert!(!block.remove_bit(0));
+/// assert_eq!(block.select1(0), 0);
+/// ```
+///
+/// A full block must be split before inserting into it again:
+///
+/// ```
+/// use succinct::select_rank::{Bits256, SelectRank};
+///
+/// let mut block = Bits256::from(true);
+/// for i in 1..256 {
+///     block.insert_bit(i, false);
+/// }
+/// block.set_bit(200, true);
+/// assert!(block.is_full());
+/// assert_eq!(block.rank1(0), 0);
+/// assert_eq!(block.rank1(256), 2);
+///
+/// let upper = block.split();
+/// assert_eq!((block.len(), upper.len()), (128, 128));
+/// assert_eq!((block.num_ones(), upper.num_ones()), (1, 1));
+/// assert_eq!(upper.select1(0), 200 - 128);
+/// assert_eq!(upper.num_zeros(), 127);
+/// ```
 #[derive(Debug, Default, Eq, PartialEq)]
 pub struct Bits256 {
     pub(super) n_ones: [u8; 4],
@@ -52,75 +192,58 @@ impl Bits256 {
         self.len - self.num_ones()
     }
 
-    /// Insert a bit at our index
+    /// Insert a bit at `index`, shifting the later bits up
+    ///
+    /// The block must not be full.
     pub fn insert_bit(&mut self, index: usize, bit: bool) {
         debug_assert!(!self.is_full());
         debug_assert!(index <= self.len as usize);
 
         let index = index as u8;
-        let mut upper = (index >> 6) as usize;
+        let upper = (index >> 6) as usize;
         let lower = index & 0b0011_1111;
 
-        let mut last = self.bits[upper] >> 63;
-
-        // TODO(alan): See if you can SIMD accelerate this (shift right
-        // + bitwise |)
-        self.bits[upper] = pdep(self.bits[upper], !(1 << lower))
+        // The top bit of each word gets carried into the next word
+        let [b0, b1, b2, _] = self.bits;
+        let carries =
+            ((b0 >> 63) << 8 | (b1 >> 63) << 16 | (b2 >> 63) << 24) as u32;
+        let word = pdep(self.bits[upper], !(1 << lower))
             | ((bit as u64) * (1 << lower));
+        shift::up(&mut self.bits, upper);
+        self.bits[upper] = word;
 
         self.n_ones = (u32::from_le_bytes(self.n_ones)
             + (u32::from(bit)) * INCREMENT[upper]
-            - (last as u32) * INCREMENT[upper])
+            - (carries & INCREMENT[upper]))
             .to_le_bytes();
-
         self.len += 1;
-        upper += 1;
-        while upper * 64 < self.len as usize {
-            let old = last;
-            last = self.bits[upper] >> 63;
-            self.n_ones = (u32::from_le_bytes(self.n_ones)
-                + (old as u32) * INCREMENT[upper]
-                - (last as u32) * INCREMENT[upper])
-                .to_le_bytes();
-            self.bits[upper] = (self.bits[upper] << 1) | (old as u64);
-            upper += 1;
-        }
     }
 
-    /// Remove a bit at our index
+    /// Remove the bit at `index`, shifting the later bits down
     pub fn remove_bit(&mut self, index: usize) -> bool {
         debug_assert!(index < self.len as usize);
 
-        // TODO(alan): See if you can SIMD accelerate this shift left
-
         let index = index as u8;
-        let mut upper = (index >> 6) as usize;
+        let upper = (index >> 6) as usize;
         let lower = index & 0b0011_1111;
 
         let output = (self.bits[upper] & 1 << lower) != 0;
 
+        // The bottom bit of each word gets carried into the previous word
+        let [_, b1, b2, b3] = self.bits;
+        let carries = ((b1 & 1) << 8 | (b2 & 1) << 16 | (b3 & 1) << 24) as u32;
         self.bits[upper] = pext(self.bits[upper], !(1 << lower));
+        shift::down(&mut self.bits, upper);
+
         self.n_ones = (u32::from_le_bytes(self.n_ones)
+            + (carries & INCREMENT[upper])
             - (output as u32) * INCREMENT[upper])
             .to_le_bytes();
-
-        upper += 1;
-        while upper * 64 < self.len as usize {
-            let bit = self.bits[upper] & 0b1 != 0;
-            self.bits[upper] >>= 1;
-            self.bits[upper - 1] |= (bit as u64) << 63;
-
-            debug_assert!(upper < 4); // If upper == 4, upper * 64 > self.len
-                                      // Update *previous* version
-            self.n_ones[upper] += bit as u8;
-            upper += 1;
-        }
-
         self.len -= 1;
         output
     }
 
-    /// Set the bit at index to the given value
+    /// Set the bit at `index` to the given value
     pub fn set_bit(&mut self, index: usize, bit: bool) {
         debug_assert!(index < self.len as usize);
         let index = index as u8;
@@ -144,6 +267,7 @@ impl Bits256 {
         }
     }
 
+    /// Split a full block in half, returning the upper 128 bits
     pub fn split(&mut self) -> Bits256 {
         debug_assert!(self.len == 256);
         let new = Bits256 {
@@ -196,6 +320,9 @@ impl SelectRank for Bits256 {
 
     /// Return the number of 1s before the `i`th position
     fn rank1(&self, index: usize) -> usize {
+        if index == 256 {
+            return self.num_ones() as usize;
+        }
         let upper = usize::from((index as u8) >> 6);
         let lower = (index as u8) & 0b0011_1111;
 
@@ -462,6 +589,8 @@ mod test {
             assert_eq!(bits256.rank0(i), 0);
             assert_eq!(bits256.select1(i), i);
         }
+        assert_eq!(bits256.rank1(256), 256);
+        assert_eq!(bits256.rank0(256), 0);
     }
 
     #[test]
@@ -494,6 +623,36 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_bits256_shift_matches_scalar() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        let words = [
+            [0xdead_beef_dead_beef, 0x8000_0000_0000_0001, !0, 1 << 63],
+            [!0, !0, !0, 0],
+            [1, 0x8000_0000_0000_0000, 0x5555_5555_5555_5555, 3],
+        ];
+        for bits in words.iter() {
+            for upper in 0..4 {
+                let mut expected = *bits;
+                let mut actual = *bits;
+                shift::up_scalar(&mut expected, upper);
+                unsafe { shift::up_avx2(&mut actual, upper) };
+                assert_eq!(actual, expected);
+
+                let mut expected = *bits;
+                expected[upper] &= !(1 << 63);
+                let mut actual = expected;
+                shift::down_scalar(&mut expected, upper);
+                unsafe { shift::down_avx2(&mut actual, upper) };
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+
     proptest! {
         #[test]
         fn test_bits256_prop_insert(input
@@ -12,6 +12,29 @@ const INCREMENT: [u64; 8] = [
     0,
 ];
 
+/// A bitstring holding up to 512 bits, with a 9-bit running count of ones
+/// for each of its 64-bit words
+///
+/// This is a standalone rank/select block for building larger bit-vectors:
+///
+/// ```
+/// use succinct::select_rank::{Bits512, SelectRank};
+///
+/// let mut block = Bits512::new();
+/// for i in 0..300 {
+///     block.insert_bit(i, i % 3 == 0);
+/// }
+/// assert_eq!(block.len(), 300);
+/// assert_eq!(block.num_ones(), 100);
+///
+/// assert_eq!(block.rank1(10), 4);
+/// assert_eq!(block.select1(4), 12);
+/// assert_eq!(block.select1(block.rank1(150)), 150);
+/// assert_eq!(block.select0(block.rank0(151)), 151);
+///
+/// assert!(block.remove_bit(0));
+/// assert_eq!(block.rank1(10), 3);
+/// ```
 #[derive(Debug, Eq, PartialEq)]
 pub struct Bits512 {
     pub(super) n_ones: u9x7,
@@ -28,6 +51,31 @@ impl Bits512 {
         }
     }
 
+    /// Create a leaf from its raw words (LSB-first), ignoring any bits
+    /// past `len`
+    pub(super) fn from_words(mut bits: [u64; 8], len: usize) -> Bits512 {
+        debug_assert!(len <= 512);
+
+        let mut n_ones = [0; 7];
+        let mut count = 0;
+        for (i, word) in bits.iter_mut().enumerate() {
+            let valid = len.saturating_sub(64 * i).min(64);
+            if valid < 64 {
+                *word &= (1 << valid) - 1;
+            }
+            if i < 7 {
+                count += word.count_ones() as u16;
+                n_ones[i] = count;
+            }
+        }
+
+        Bits512 {
+            bits,
+            len,
+            n_ones: u9x7::new(n_ones),
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.len == 0
     }
@@ -49,8 +97,10 @@ impl Bits512 {
         self.len as u32 - self.num_ones()
     }
 
-    /// Insert a bit at our index
-    pub fn insert(&mut self, index: usize, bit: bool) {
+    /// Insert a bit at `index`, shifting the later bits up
+    ///
+    /// The block must not be full.
+    pub fn insert_bit(&mut self, index: usize, bit: bool) {
         debug_assert!(!self.is_full());
         debug_assert!(index <= self.len as usize);
 
@@ -78,6 +128,26 @@ impl Bits512 {
         self.len += 1;
     }
 
+    /// Remove the bit at `index`, shifting the later bits down
+    pub fn remove_bit(&mut self, index: usize) -> bool {
+        debug_assert!(index < self.len);
+        let upper = index / 64;
+        let lower = index % 64;
+
+        let bit = self.bits[upper] & (1 << lower) != 0;
+        let below = (1 << lower) - 1;
+        self.bits[upper] =
+            (self.bits[upper] & below) | ((self.bits[upper] >> 1) & !below);
+        for i in (upper + 1)..8 {
+            self.bits[i - 1] |= self.bits[i] << 63;
+            self.bits[i] >>= 1;
+        }
+
+        *self = Bits512::from_words(self.bits, self.len - 1);
+        bit
+    }
+
+    /// Set the bit at `index` to the given value
     pub fn set_bit(&mut self, index: usize, bit: bool) {
         debug_assert!(index < self.len as usize);
         let upper = index / 64;
@@ -95,6 +165,7 @@ impl Bits512 {
         }
     }
 
+    /// Split a full block in half, returning the upper 256 bits
     pub fn split(&mut self) -> Bits512 {
         debug_assert!(self.is_full());
 
@@ -236,11 +307,11 @@ mod test {
         let mut bits = Bits512::new();
         bits.len = 100;
 
-        bits.insert(0, true);
-        bits.insert(25, true);
-        bits.insert(25, false);
-        bits.insert(25, true);
-        bits.insert(64, true);
+        bits.insert_bit(0, true);
+        bits.insert_bit(25, true);
+        bits.insert_bit(25, false);
+        bits.insert_bit(25, true);
+        bits.insert_bit(64, true);
 
         assert_eq!(
             bits,
@@ -252,6 +323,25 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_bits512_remove_bit() {
+        let mut expected = (0..512).map(|i| i % 3 == 0).collect::<Vec<_>>();
+        let mut bits = Bits512::new();
+        for (i, &bit) in expected.iter().enumerate() {
+            bits.insert_bit(i, bit);
+        }
+
+        for i in 0..500 {
+            let index = (i * 7) % expected.len();
+            assert_eq!(bits.remove_bit(index), expected.remove(index));
+            assert_eq!(bits.iter().collect::<Vec<_>>(), expected);
+            assert_eq!(
+                bits.num_ones() as usize,
+                expected.iter().filter(|&&b| b).count()
+            );
+        }
+    }
+
     #[test]
     fn test_bits512_split() {
         let mut first = Bits512 {
@@ -325,7 +415,7 @@ mod test {
 
             for (order, bit) in input.iter().cloned() {
                 let order = order as usize % (bits.len() + 1);
-                bits512.insert(order, bit);
+                bits512.insert_bit(order, bit);
                 bits.insert(order, bit);
 
                 prop_assert_eq!(&bits512.iter().collect::<Vec<_>>(), &bits);
@@ -3,27 +3,95 @@ use std::iter::FromIterator;
 use bit_parallel::u32x16;
 
 use super::{Bits512, SelectRank};
+use crate::alloc::{self, Allocator, Global};
 use crate::tree::{PackedPtr, Ptr, PtrMut};
 
 const CAPACITY: usize = 16;
 
-pub struct BitVec {
-    root: Box<Node>,
+/// A dynamic bit-vector, stored as a B-tree of `Bits512` leaves
+///
+/// The root is stored inline, and every node below it is allocated in `A`.
+pub struct BitVec<A: Allocator = Global> {
+    root: Node,
+    alloc: A,
 }
 
 impl BitVec {
     pub fn new() -> BitVec {
+        BitVec::new_in(Global)
+    }
+
+    /// Create a bit-vector from a slice of bits
+    pub fn from_bool_slice(bits: &[bool]) -> BitVec {
+        BitVec::from_leaves(
+            bits.chunks(512).map(|chunk| {
+                let mut words = [0; 8];
+                for (i, &bit) in chunk.iter().enumerate() {
+                    words[i / 64] |= (bit as u64) << (i % 64);
+                }
+                Bits512::from_words(words, chunk.len())
+            }),
+            Global,
+        )
+    }
+
+    /// Create a bit-vector of `len` bits, where exactly the bits at `ones`
+    /// are set
+    pub fn from_positions(len: usize, ones: &[usize]) -> BitVec {
+        let mut words = vec![0u64; len.div_ceil(64)];
+        for &i in ones {
+            assert!(i < len);
+            words[i / 64] |= 1 << (i % 64);
+        }
+        BitVec::from_words(&words, len, Global)
+    }
+
+    /// Create a bit-vector from the first `len` bits of a bitmap, where
+    /// each byte is read LSB-first
+    pub fn from_byte_bitmap(bytes: &[u8], len: usize) -> BitVec {
+        assert!(len <= 8 * bytes.len());
+        BitVec::from_leaves(
+            bytes[..len.div_ceil(8)].chunks(64).enumerate().map(
+                |(i, chunk)| {
+                    let mut words = [0; 8];
+                    for (j, word) in chunk.chunks(8).enumerate() {
+                        let mut buffer = [0; 8];
+                        buffer[..word.len()].copy_from_slice(word);
+                        words[j] = u64::from_le_bytes(buffer);
+                    }
+                    Bits512::from_words(words, (len - 512 * i).min(512))
+                },
+            ),
+            Global,
+        )
+    }
+}
+
+impl<A: Allocator> BitVec<A> {
+    /// Create an empty bit-vector whose nodes are allocated in `alloc`
+    pub fn new_in(alloc: A) -> BitVec<A> {
         BitVec {
-            root: Box::new(Node {
+            root: Node {
                 lens: [0; CAPACITY],
                 n_ones: [0; CAPACITY],
                 ptrs: [PackedPtr::null(); CAPACITY],
-            }),
+            },
+            alloc,
         }
     }
 
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
     pub fn total_size(&self) -> usize {
-        std::mem::size_of::<Self>() + self.root.total_size()
+        // The root is counted by `size_of::<Self>()`
+        std::mem::size_of::<Self>() - std::mem::size_of::<Node>()
+            + self.root.total_size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.lens[CAPACITY - 1] == 0
     }
 
     pub fn len(&self) -> usize {
@@ -38,8 +106,84 @@ impl BitVec {
         self.len() as u32 - self.num_ones()
     }
 
-    fn split(&mut self, stack: Vec<(*mut Node, usize)>, new: Box<Bits512>) {
-        let mut ptr = PackedPtr::from_leaf(new);
+    /// Pack our bits into 64-bit words (LSB-first), since leaves may not
+    /// be full
+    fn words(&self) -> Vec<u64> {
+        let mut words: Vec<u64> = Vec::with_capacity(self.len().div_ceil(64));
+        let mut len = 0;
+        for leaf in self.iter_leaf() {
+            for (i, &word) in leaf.bits.iter().enumerate() {
+                let n = leaf.len().saturating_sub(64 * i).min(64);
+                if n == 0 {
+                    break;
+                }
+                let word = if n < 64 { word & ((1 << n) - 1) } else { word };
+
+                let offset = len % 64;
+                if offset == 0 {
+                    words.push(word);
+                } else {
+                    *words.last_mut().unwrap() |= word << offset;
+                    if offset + n > 64 {
+                        words.push(word >> (64 - offset));
+                    }
+                }
+                len += n;
+            }
+        }
+        words
+    }
+
+    /// Build the tree bottom-up from (full) leaves
+    fn from_leaves(
+        leaves: impl Iterator<Item = Bits512>,
+        alloc: A,
+    ) -> BitVec<A> {
+        let mut level = leaves
+            .map(|leaf| PackedPtr::leaf_in(leaf, &alloc))
+            .collect::<Vec<_>>();
+        if level.is_empty() {
+            return BitVec::new_in(alloc);
+        }
+
+        loop {
+            let mut nodes = level
+                .chunks(CAPACITY)
+                .map(|chunk| {
+                    let mut node = Node {
+                        lens: [0; CAPACITY],
+                        n_ones: [0; CAPACITY],
+                        ptrs: [PackedPtr::null(); CAPACITY],
+                    };
+                    let (mut len, mut n_ones) = (0, 0);
+                    for i in 0..CAPACITY {
+                        if let Some(&ptr) = chunk.get(i) {
+                            len += ptr.len() as u32;
+                            n_ones += ptr.num_ones();
+                            node.ptrs[i] = ptr;
+                        }
+                        node.lens[i] = len;
+                        node.n_ones[i] = n_ones;
+                    }
+                    node
+                })
+                .collect::<Vec<_>>();
+
+            if nodes.len() == 1 {
+                return BitVec {
+                    root: nodes.pop().unwrap(),
+                    alloc,
+                };
+            }
+            level = nodes
+                .into_iter()
+                .map(|node| PackedPtr::inner_in(node, &alloc))
+                .collect();
+        }
+    }
+
+    fn split(&mut self, stack: Vec<(*mut Node, usize)>, new: Bits512) {
+        let mut ptr = PackedPtr::leaf_in(new, &self.alloc);
         for (node, rank) in stack.iter().rev().cloned() {
             let node = unsafe { &mut *node };
             if !node.is_full() {
@@ -48,7 +192,7 @@ impl BitVec {
                 node.n_ones[rank] -= ptr.num_ones();
                 return;
             } else {
-                let mut new = Box::new(node.split());
+                let mut new = node.split();
                 if rank >= 8 {
                     new.insert(rank - 8, ptr);
                     new.lens[rank - 8] -= ptr.len() as u32;
@@ -58,7 +202,7 @@ impl BitVec {
                     node.lens[rank] -= ptr.len() as u32;
                     node.n_ones[rank] -= ptr.num_ones();
                 }
-                ptr = PackedPtr::from_inner(new);
+                ptr = PackedPtr::inner_in(new, &self.alloc);
             }
         }
 
@@ -71,15 +215,15 @@ impl BitVec {
 
         let root = std::mem::replace(
             &mut self.root,
-            Box::new(Node {
+            Node {
                 lens: [len as u32; CAPACITY],
                 n_ones: [n_ones; CAPACITY],
                 ptrs: [PackedPtr::null(); CAPACITY],
-            }),
+            },
         );
         self.root.lens[0] = root.len() as u32;
         self.root.n_ones[0] = root.num_ones() as u32;
-        self.root.ptrs[0] = PackedPtr::from_inner(root);
+        self.root.ptrs[0] = PackedPtr::inner_in(root, &self.alloc);
         self.root.ptrs[1] = ptr;
     }
 
@@ -90,9 +234,9 @@ impl BitVec {
 
     pub fn insert(&mut self, index: usize, bit: bool) {
         debug_assert!(index <= self.len());
-        if index == 0 && self.len() == 0 {
+        if index == 0 && self.is_empty() {
             self.root.ptrs[0] =
-                PackedPtr::from_leaf(Box::new(Bits512::from(bit)));
+                PackedPtr::leaf_in(Bits512::from(bit), &self.alloc);
             self.root.lens = [1; CAPACITY];
             self.root.n_ones = [bit as u32; CAPACITY];
             return;
@@ -123,16 +267,16 @@ impl BitVec {
                 }
                 PtrMut::Leaf(leaf) => {
                     if leaf.is_full() {
-                        let mut new = Box::new(leaf.split());
+                        let mut new = leaf.split();
                         if index >= 256 {
-                            new.insert(index as usize - 256, bit);
+                            new.insert_bit(index as usize - 256, bit);
                         } else {
-                            leaf.insert(index as usize, bit);
+                            leaf.insert_bit(index as usize, bit);
                         }
                         stack.push((node, rank));
                         self.split(stack, new);
                     } else {
-                        leaf.insert(index as usize, bit);
+                        leaf.insert_bit(index as usize, bit);
                     }
                     break;
                 }
@@ -140,6 +284,65 @@ impl BitVec {
         }
     }
 
+    /// Append `n` zeros
+    pub fn extend_with_zeros(&mut self, n: usize) {
+        self.extend_with(false, n);
+    }
+
+    /// Append `n` ones
+    pub fn extend_with_ones(&mut self, n: usize) {
+        self.extend_with(true, n);
+    }
+
+    /// Append `n` copies of `bit`, adding whole leaves at a time
+    fn extend_with(&mut self, bit: bool, mut n: usize) {
+        // Top up the last leaf so the new leaves start on a boundary
+        while n > 0 && !self.len().is_multiple_of(512) {
+            self.insert(self.len(), bit);
+            n -= 1;
+        }
+
+        let word = if bit { u64::MAX } else { 0 };
+        for _ in 0..n / 512 {
+            self.push_leaf(Bits512::from_words([word; 8], 512));
+        }
+        for _ in 0..n % 512 {
+            self.insert(self.len(), bit);
+        }
+    }
+
+    /// Add `leaf` after the last leaf
+    fn push_leaf(&mut self, leaf: Bits512) {
+        let (len, n_ones) = (leaf.len() as u32, leaf.num_ones());
+        if self.is_empty() {
+            self.root.ptrs[0] = PackedPtr::leaf_in(leaf, &self.alloc);
+            self.root.lens = [len; CAPACITY];
+            self.root.n_ones = [n_ones; CAPACITY];
+            return;
+        }
+
+        let mut stack: Vec<(*mut Node, usize)> =
+            Vec::with_capacity(self.approx_depth());
+        let mut node: &mut Node = &mut self.root;
+        loop {
+            let rank = node.ptrs.iter().rposition(|p| !p.is_null()).unwrap();
+            for i in rank..CAPACITY {
+                node.lens[i] += len;
+                node.n_ones[i] += n_ones;
+            }
+            stack.push((node as *mut _, rank));
+
+            // See `insert` for why this goes through a raw pointer
+            let n = &mut node.ptrs[rank] as *mut PackedPtr<Node, Bits512>;
+            match unsafe { &mut *n }.expand_mut() {
+                PtrMut::None => unreachable!(),
+                PtrMut::Inner(inner) => node = inner,
+                PtrMut::Leaf(_) => break,
+            }
+        }
+        self.split(stack, leaf);
+    }
+
     pub fn set_bit(&mut self, index: usize, bit: bool) {
         let mut index = index as u32;
         let mut stack: Vec<(*mut Node, usize)> =
@@ -183,6 +386,133 @@ impl BitVec {
         }
     }
 
+    /// Set the bit at each of `indices` to `bit`
+    ///
+    /// The indices are sorted and applied in one pass over the tree, so
+    /// each node's counts are only updated once.
+    pub fn set_bits(&mut self, indices: &[usize], bit: bool) {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if let Some(&last) = indices.last() {
+            assert!(last < self.len());
+        }
+        self.root.set_bits(&indices, 0, bit);
+    }
+
+    /// Remove the bit at `index`, returning it
+    pub fn remove(&mut self, index: usize) -> bool {
+        assert!(index < self.len());
+        let mut index = index as u32;
+        let mut stack: Vec<(*mut Node, usize)> =
+            Vec::with_capacity(self.approx_depth());
+        let mut node: &mut Node = &mut self.root;
+
+        let bit = loop {
+            let rank = u32x16::rank(&node.lens, 1 + index) as usize;
+            if rank > 0 {
+                index -= node.lens[rank - 1];
+            }
+            stack.push((node as *mut _, rank));
+
+            // Use an unsafe *mut raw pointer to work around borrow checker
+            // restrictions (we are "releasing" the earlier borrows when
+            // we reassign node, so there is never a double mutable borrow)
+            let n = &mut node.ptrs[rank] as *mut PackedPtr<Node, Bits512>;
+            match unsafe { &mut *n }.expand_mut() {
+                PtrMut::None => unreachable!(),
+                PtrMut::Inner(inner) => {
+                    node = inner;
+                }
+                PtrMut::Leaf(leaf) => break leaf.remove_bit(index as usize),
+            }
+        };
+
+        for (node, rank) in stack.iter().cloned() {
+            let node = unsafe { &mut *node };
+            u32x16::decrement(&mut node.lens, rank);
+            if bit {
+                u32x16::decrement(&mut node.n_ones, rank);
+            }
+        }
+        self.shrink(stack);
+
+        #[cfg(debug_assertions)]
+        self.debug_assert_tree();
+        bit
+    }
+
+    /// Merge or free the leaf at the top of `stack` after a removal, freeing
+    /// any nodes that are left empty
+    fn shrink(&mut self, mut stack: Vec<(*mut Node, usize)>) {
+        let (node, rank) = stack.pop().unwrap();
+        let node = unsafe { &mut *node };
+
+        let len = node.ptrs[rank].len();
+        if len == 0 {
+            node.remove(rank, &self.alloc);
+        } else if len < 128 {
+            // Merge with a neighboring leaf if the two fit comfortably
+            let fits = |ptr: PackedPtr<Node, Bits512>| match ptr.expand() {
+                Ptr::Leaf(leaf) => len + leaf.len() <= 384,
+                _ => false,
+            };
+            if rank + 1 < CAPACITY && fits(node.ptrs[rank + 1]) {
+                node.merge_leaves(rank, &self.alloc);
+            } else if rank > 0 && fits(node.ptrs[rank - 1]) {
+                node.merge_leaves(rank - 1, &self.alloc);
+            }
+        }
+
+        let mut empty = node.ptrs[0].is_null();
+        while empty {
+            match stack.pop() {
+                Some((node, rank)) => {
+                    let node = unsafe { &mut *node };
+                    node.remove(rank, &self.alloc);
+                    empty = node.ptrs[0].is_null();
+                }
+                None => break,
+            }
+        }
+
+        // Collapse roots with a single inner child
+        while self.root.ptrs[1].is_null() {
+            let child = match self.root.ptrs[0].expand_mut() {
+                PtrMut::Inner(inner) => inner as *mut Node,
+                _ => break,
+            };
+            self.root.ptrs[0] = PackedPtr::null();
+            self.root = unsafe { alloc::from_raw_in(child, &self.alloc) };
+        }
+    }
+
+    /// Check that every node's cumulative counts match its children, and
+    /// that no node other than the root is empty
+    #[cfg(any(test, debug_assertions))]
+    fn debug_assert_tree(&self) {
+        let mut stack: Vec<&Node> = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            node.debug_assert_indices();
+
+            let mut seen_null = false;
+            for ptr in node.ptrs.iter() {
+                match ptr.expand() {
+                    Ptr::None => seen_null = true,
+                    Ptr::Leaf(leaf) => {
+                        debug_assert!(!seen_null);
+                        debug_assert!(!leaf.is_empty());
+                    }
+                    Ptr::Inner(inner) => {
+                        debug_assert!(!seen_null);
+                        debug_assert!(!inner.ptrs[0].is_null());
+                        stack.push(inner);
+                    }
+                }
+            }
+        }
+    }
+
     fn iter_leaf(&self) -> impl Iterator<Item = &Bits512> {
         let mut current: &Node = &self.root;
         let mut stack = Vec::with_capacity(self.approx_depth());
@@ -229,6 +559,89 @@ impl BitVec {
     pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
         self.iter_leaf().flat_map(|bits| bits.iter())
     }
+
+    /// Like `get_bit`, but return `None` if `index` is out of bounds
+    pub fn try_get_bit(&self, index: usize) -> Option<bool> {
+        if index < self.len() {
+            Some(self.get_bit(index))
+        } else {
+            None
+        }
+    }
+
+    /// Like `rank1`, but return `None` if `index` is out of bounds
+    pub fn try_rank1(&self, index: usize) -> Option<usize> {
+        if index < self.len() {
+            Some(self.rank1(index))
+        } else {
+            None
+        }
+    }
+
+    /// Like `select1`, but return `None` if there are not `index + 1` 1s
+    pub fn try_select1(&self, index: usize) -> Option<usize> {
+        if index < self.num_ones() as usize {
+            Some(self.select1(index))
+        } else {
+            None
+        }
+    }
+}
+
+impl<A: Allocator + Clone> BitVec<A> {
+    /// Return the position-wise AND, treating the shorter input as if it
+    /// were padded with 0s
+    pub fn and<B: Allocator>(&self, other: &BitVec<B>) -> BitVec<A> {
+        self.combine(other, |a, b| a & b)
+    }
+
+    /// Return the position-wise OR, treating the shorter input as if it
+    /// were padded with 0s
+    pub fn or<B: Allocator>(&self, other: &BitVec<B>) -> BitVec<A> {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Return the position-wise XOR, treating the shorter input as if it
+    /// were padded with 0s
+    pub fn xor<B: Allocator>(&self, other: &BitVec<B>) -> BitVec<A> {
+        self.combine(other, |a, b| a ^ b)
+    }
+
+    fn combine<B: Allocator>(
+        &self,
+        other: &BitVec<B>,
+        f: impl Fn(u64, u64) -> u64,
+    ) -> BitVec<A> {
+        let (mut words, other_words) = (self.words(), other.words());
+        if words.len() < other_words.len() {
+            words.resize(other_words.len(), 0);
+        }
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = f(*word, other_words.get(i).cloned().unwrap_or(0));
+        }
+
+        let len = self.len().max(other.len());
+        BitVec::from_words(&words, len, self.alloc.clone())
+    }
+
+    /// Rebuild the tree with full leaves, to reclaim the space left by
+    /// partially-empty leaves after many inserts and removes
+    pub fn compact(&mut self) {
+        *self =
+            BitVec::from_words(&self.words(), self.len(), self.alloc.clone());
+    }
+
+    /// Build a bit-vector from the first `len` bits of LSB-first words
+    fn from_words(words: &[u64], len: usize, alloc: A) -> BitVec<A> {
+        BitVec::from_leaves(
+            words.chunks(8).enumerate().map(|(i, chunk)| {
+                let mut words = [0; 8];
+                words[..chunk.len()].copy_from_slice(chunk);
+                Bits512::from_words(words, (len - 512 * i).min(512))
+            }),
+            alloc,
+        )
+    }
 }
 
 impl FromIterator<bool> for BitVec {
@@ -237,14 +650,23 @@ impl FromIterator<bool> for BitVec {
         T: IntoIterator<Item = bool>,
     {
         let mut bits = BitVec::new();
+        bits.extend(input);
+        bits
+    }
+}
+
+impl<A: Allocator> Extend<bool> for BitVec<A> {
+    fn extend<T>(&mut self, input: T)
+    where
+        T: IntoIterator<Item = bool>,
+    {
         for bit in input.into_iter() {
-            bits.insert(bits.len(), bit);
+            self.insert(self.len(), bit);
         }
-        bits
     }
 }
 
-impl SelectRank for BitVec {
+impl<A: Allocator> SelectRank for BitVec<A> {
     fn get_bit(&self, mut index: usize) -> bool {
         debug_assert!(index < self.root.lens[CAPACITY - 1] as usize);
         let mut node: &Node = &self.root;
@@ -367,23 +789,39 @@ struct Node {
     ptrs: [PackedPtr<Node, Bits512>; CAPACITY],
 }
 
-impl Drop for Node {
+impl<A: Allocator> Drop for BitVec<A> {
     fn drop(&mut self) {
+        // Free the inner nodes from an explicit stack rather than recursing
+        let mut stack = Vec::new();
+        self.root.release_children(&mut stack, &self.alloc);
+        while let Some(mut node) = stack.pop() {
+            node.release_children(&mut stack, &self.alloc);
+        }
+    }
+}
+
+impl Node {
+    /// Free the leaves and move the inner children onto `stack`, leaving
+    /// null pointers behind
+    fn release_children<A: Allocator>(
+        &mut self,
+        stack: &mut Vec<Node>,
+        alloc: &A,
+    ) {
         for ptr in self.ptrs.iter_mut() {
             match ptr.expand_mut() {
-                PtrMut::None => {}
+                PtrMut::None => continue,
                 PtrMut::Leaf(leaf) => unsafe {
-                    drop(Box::from_raw(leaf as *mut _));
+                    alloc::from_raw_in(leaf as *mut Bits512, alloc);
                 },
                 PtrMut::Inner(inner) => unsafe {
-                    drop(Box::from_raw(inner as *mut _));
+                    stack.push(alloc::from_raw_in(inner as *mut _, alloc));
                 },
             }
+            *ptr = PackedPtr::null();
         }
     }
-}
 
-impl Node {
     fn total_size(&self) -> usize {
         let mut size = std::mem::size_of::<Self>();
         for ptr in self.ptrs.iter() {
@@ -438,6 +876,103 @@ impl Node {
         self.ptrs[rank + 1] = ptr;
     }
 
+    /// Free the child at `rank`, shifting the later children down
+    fn remove<A: Allocator>(&mut self, rank: usize, alloc: &A) {
+        let (len, n_ones) = if rank == 0 {
+            (self.lens[0], self.n_ones[0])
+        } else {
+            (
+                self.lens[rank] - self.lens[rank - 1],
+                self.n_ones[rank] - self.n_ones[rank - 1],
+            )
+        };
+
+        match self.ptrs[rank].expand_mut() {
+            PtrMut::None => unreachable!(),
+            PtrMut::Leaf(leaf) => unsafe {
+                alloc::from_raw_in(leaf as *mut Bits512, alloc);
+            },
+            PtrMut::Inner(inner) => unsafe {
+                // Only empty inner nodes are removed
+                alloc::from_raw_in(inner as *mut Node, alloc);
+            },
+        }
+
+        for i in rank..(CAPACITY - 1) {
+            self.ptrs[i] = self.ptrs[i + 1];
+            self.lens[i] = self.lens[i + 1] - len;
+            self.n_ones[i] = self.n_ones[i + 1] - n_ones;
+        }
+        self.ptrs[CAPACITY - 1] = PackedPtr::null();
+        self.lens[CAPACITY - 1] -= len;
+        self.n_ones[CAPACITY - 1] -= n_ones;
+    }
+
+    /// Move the bits of the leaf at `rank + 1` into the leaf at `rank`,
+    /// freeing the emptied leaf
+    fn merge_leaves<A: Allocator>(&mut self, rank: usize, alloc: &A) {
+        let (mut left, right) = (self.ptrs[rank], self.ptrs[rank + 1]);
+        let (left, right) = match (left.expand_mut(), right.expand()) {
+            (PtrMut::Leaf(left), Ptr::Leaf(right)) => (left, right),
+            _ => unreachable!(),
+        };
+        debug_assert!(left.len() + right.len() <= 512);
+
+        for i in 0..right.len() {
+            left.insert_bit(left.len(), right.get_bit(i));
+        }
+        self.lens[rank] += right.len() as u32;
+        self.n_ones[rank] += right.num_ones();
+        self.remove(rank + 1, alloc);
+    }
+
+    /// Set the bits at the sorted `indices`, which are offset by the
+    /// position `start` of this node, returning the change in the number
+    /// of ones
+    fn set_bits(
+        &mut self,
+        mut indices: &[usize],
+        start: usize,
+        bit: bool,
+    ) -> i64 {
+        let mut total = 0;
+        for rank in 0..CAPACITY {
+            let end = start + self.lens[rank] as usize;
+            let (here, rest) =
+                indices.split_at(indices.partition_point(|&i| i < end));
+            indices = rest;
+
+            if !here.is_empty() {
+                let child_start = match rank {
+                    0 => start,
+                    _ => start + self.lens[rank - 1] as usize,
+                };
+                total += match self.ptrs[rank].expand_mut() {
+                    PtrMut::None => unreachable!(),
+                    PtrMut::Inner(inner) => {
+                        inner.set_bits(here, child_start, bit)
+                    }
+                    PtrMut::Leaf(leaf) => {
+                        let mut changed = 0;
+                        for &i in here {
+                            if leaf.get_bit(i - child_start) != bit {
+                                leaf.set_bit(i - child_start, bit);
+                                changed += 1;
+                            }
+                        }
+                        if bit {
+                            changed
+                        } else {
+                            -changed
+                        }
+                    }
+                };
+            }
+            self.n_ones[rank] = (i64::from(self.n_ones[rank]) + total) as u32;
+        }
+        total
+    }
+
     fn add_bit_count(&mut self, rank: usize, bit: bool) {
         u32x16::increment(&mut self.lens, rank);
         if bit {
@@ -461,7 +996,7 @@ impl Node {
         self.lens[CAPACITY - 1] as usize
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, debug_assertions))]
     fn debug_assert_indices(&self) {
         let mut len = 0;
         let mut n_ones = 0;
@@ -511,6 +1046,22 @@ mod test {
     use super::*;
     use bit_parallel::u9x7::u9x7;
 
+    #[test]
+    fn test_bitvec_drop_large() {
+        let bits = (0..4_000_000).map(|i| i % 3 == 0).collect::<Vec<_>>();
+        let bitvec = BitVec::from_bool_slice(&bits);
+        assert_eq!(bitvec.len(), 4_000_000);
+
+        // Drop on a thread with a small stack so a deep recursive drop
+        // would overflow
+        std::thread::Builder::new()
+            .stack_size(32 * 1024)
+            .spawn(move || drop(bitvec))
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
     #[test]
     fn test_bitvec_total_size() {
         // In the worst case (assuming that all nodes in our tree are at
@@ -524,12 +1075,56 @@ mod test {
         let bits = BitVec::from_iter(vec![true; 80]);
         // 304 bytes to store 10 bytes of data
         //  = 34.4 x overhead
-        assert_eq!(bits.total_size(), 344);
+        assert_eq!(bits.total_size(), 336);
 
         let bits = BitVec::from_iter(vec![false; 80000]);
         // 47240 bytes to store 10000 bytes of data
         //  = 3.60x overhead
-        assert_eq!(bits.total_size(), 35976);
+        assert_eq!(bits.total_size(), 35968);
+    }
+
+    #[test]
+    fn test_bitvec_and_or_xor() {
+        // Insert in the middle so that leaves are only partially full
+        let mut a = BitVec::new();
+        let mut expected_a = Vec::new();
+        for i in 0..3000usize {
+            let (index, bit) = (i * 7 % (a.len() + 1), i % 3 == 0);
+            a.insert(index, bit);
+            expected_a.insert(index, bit);
+        }
+        let expected_b = (0..1234).map(|i| i % 5 < 2).collect::<Vec<_>>();
+        let b = BitVec::from_iter(expected_b.iter().cloned());
+
+        let combined = |f: fn(bool, bool) -> bool| {
+            (0..3000)
+                .map(|i| {
+                    f(
+                        expected_a[i],
+                        expected_b.get(i).cloned().unwrap_or(false),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            a.and(&b).iter().collect::<Vec<_>>(),
+            combined(|x, y| x & y)
+        );
+        assert_eq!(b.or(&a).iter().collect::<Vec<_>>(), combined(|x, y| x | y));
+        assert_eq!(
+            a.xor(&b).iter().collect::<Vec<_>>(),
+            combined(|x, y| x ^ y)
+        );
+
+        let xor = a.xor(&b);
+        let expected = combined(|x, y| x ^ y);
+        assert_eq!(xor.len(), 3000);
+        assert_eq!(
+            xor.num_ones() as usize,
+            expected.iter().filter(|&&bit| bit).count()
+        );
+        assert_eq!(a.and(&BitVec::new()).num_ones(), 0);
+        assert_eq!(BitVec::new().or(&BitVec::new()).len(), 0);
     }
 
     #[test]
@@ -676,6 +1271,82 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_bitvec_from_slices() {
+        let lens = [
+            0, 1, 7, 8, 9, 63, 64, 65, 511, 512, 513, 1000, 8191, 8192, 8193,
+            100_000,
+        ];
+        for &len in lens.iter() {
+            let patterns: [&dyn Fn(usize) -> bool; 4] =
+                [&|_| false, &|_| true, &|i| i % 3 == 0, &|i| {
+                    (i * 0x9E37_79B9) % 7 < 3
+                }];
+            for pattern in patterns.iter() {
+                let expected = (0..len).map(pattern).collect::<Vec<_>>();
+                let mut bytes = vec![0u8; len.div_ceil(8) + 1];
+                for (i, &bit) in expected.iter().enumerate() {
+                    bytes[i / 8] |= (bit as u8) << (i % 8);
+                }
+                // Bits past `len` should be ignored
+                bytes[len / 8] |= 0xFF << (len % 8);
+
+                let reference = BitVec::from_iter(expected.iter().cloned());
+                let from_bools = BitVec::from_bool_slice(&expected);
+                let from_bytes = BitVec::from_byte_bitmap(&bytes, len);
+                for bits in [from_bools, from_bytes].iter() {
+                    bits.root.debug_assert_indices();
+                    assert_eq!(bits.len(), reference.len());
+                    assert_eq!(bits.num_ones(), reference.num_ones());
+                    assert_eq!(
+                        bits.iter().collect::<Vec<_>>(),
+                        reference.iter().collect::<Vec<_>>()
+                    );
+
+                    for i in (0..len).step_by(97) {
+                        assert_eq!(bits.rank1(i), reference.rank1(i));
+                    }
+                    for i in (0..bits.num_ones() as usize).step_by(89) {
+                        assert_eq!(bits.select1(i), reference.select1(i));
+                    }
+                    for i in (0..bits.num_zeros() as usize).step_by(89) {
+                        assert_eq!(bits.select0(i), reference.select0(i));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitvec_from_slice_then_insert() {
+        let mut expected = (0..20_000).map(|i| i % 5 == 0).collect::<Vec<_>>();
+        let mut bits = BitVec::from_bool_slice(&expected);
+        for i in 0..2000 {
+            let index = (i * 7919) % expected.len();
+            bits.insert(index, i % 2 == 0);
+            expected.insert(index, i % 2 == 0);
+        }
+
+        bits.root.debug_assert_indices();
+        assert_eq!(bits.iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_bitvec_checked() {
+        let bits = BitVec::from_bool_slice(&[false, true, true, false, true]);
+        assert_eq!(bits.try_get_bit(4), Some(true));
+        assert_eq!(bits.try_get_bit(5), None);
+        assert_eq!(bits.try_rank1(4), Some(2));
+        assert_eq!(bits.try_rank1(5), None);
+        assert_eq!(bits.try_select1(2), Some(4));
+        assert_eq!(bits.try_select1(3), None);
+
+        let empty = BitVec::new();
+        assert_eq!(empty.try_get_bit(0), None);
+        assert_eq!(empty.try_rank1(0), None);
+        assert_eq!(empty.try_select1(0), None);
+    }
+
     #[test]
     fn test_bitvec_set_bit() {
         let mut bits = BitVec::from_iter(vec![false; 1000]);
@@ -692,6 +1363,170 @@ mod test {
         bits.root.debug_assert_indices();
     }
 
+    #[test]
+    fn test_bitvec_compact() {
+        let mut expected = Vec::new();
+        let mut bits = BitVec::new();
+        for i in 0..40_000 {
+            let index = (i * 7919) % (expected.len() + 1);
+            bits.insert(index, i % 3 == 0);
+            expected.insert(index, i % 3 == 0);
+        }
+        for i in 0..20_000 {
+            let index = (i * 7919) % expected.len();
+            assert_eq!(bits.remove(index), expected.remove(index));
+        }
+
+        let before = bits.total_size();
+        bits.compact();
+        bits.debug_assert_tree();
+        assert!(bits.total_size() < before);
+        assert_eq!(
+            bits.total_size(),
+            BitVec::from_bool_slice(&expected).total_size()
+        );
+
+        assert_eq!(bits.len(), expected.len());
+        assert_eq!(bits.iter().collect::<Vec<_>>(), expected);
+        let mut ones = 0;
+        for (i, &bit) in expected.iter().enumerate() {
+            assert_eq!(bits.get_bit(i), bit);
+            assert_eq!(bits.rank1(i), ones);
+            if bit {
+                assert_eq!(bits.select1(ones), i);
+                ones += 1;
+            }
+        }
+
+        let mut empty = BitVec::new();
+        empty.compact();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_bitvec_extend() {
+        let extra = (0..1000).map(|i| i % 7 < 3).collect::<Vec<_>>();
+        let mut bits = BitVec::new();
+        let mut by_insert = BitVec::new();
+        bits.extend(extra.iter().cloned());
+        for &bit in extra.iter() {
+            by_insert.insert(by_insert.len(), bit);
+        }
+        assert!(bits.iter().eq(by_insert.iter()));
+
+        let mut expected = extra.clone();
+        for &(bit, n) in &[(false, 5000), (true, 1300), (true, 100_000)] {
+            if bit {
+                bits.extend_with_ones(n);
+            } else {
+                bits.extend_with_zeros(n);
+            }
+            expected.extend(vec![bit; n]);
+            bits.extend(extra.iter().cloned());
+            expected.extend(extra.iter().cloned());
+            bits.debug_assert_tree();
+        }
+        let mut empty = BitVec::new();
+        empty.extend_with_ones(1024);
+        assert_eq!((empty.len(), empty.num_ones()), (1024, 1024));
+
+        assert_eq!(bits.len(), expected.len());
+        assert_eq!(bits.iter().collect::<Vec<_>>(), expected);
+        let mut ones = 0;
+        for (i, &bit) in expected.iter().enumerate() {
+            assert_eq!(bits.rank1(i), ones);
+            if bit {
+                assert_eq!(bits.select1(ones), i);
+                ones += 1;
+            }
+        }
+        assert_eq!(bits.num_ones() as usize, ones);
+    }
+
+    #[test]
+    fn test_bitvec_set_bits() {
+        let mut bits = BitVec::new();
+        for i in 0..20_000usize {
+            bits.insert((i * 7919) % (bits.len() + 1), i % 5 == 0);
+        }
+        let mut expected = BitVec::from_iter(bits.iter());
+
+        for round in 0..6usize {
+            let bit = round % 2 == 0;
+            let indices = (0..3000)
+                .map(|i| (i * i * 31 + round * 104_729) % 20_000)
+                .collect::<Vec<_>>();
+            bits.set_bits(&indices, bit);
+            for &i in indices.iter() {
+                expected.set_bit(i, bit);
+            }
+
+            bits.debug_assert_tree();
+            assert_eq!(bits.num_ones(), expected.num_ones());
+            assert!(bits.iter().eq(expected.iter()));
+            for i in (0..20_000).step_by(37) {
+                assert_eq!(bits.rank1(i), expected.rank1(i));
+            }
+        }
+
+        bits.set_bits(&[], true);
+        assert!(bits.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn test_bitvec_from_positions() {
+        for &(len, step) in
+            &[(0, 1), (1, 1), (700, 1), (5000, 3), (70_000, 997)]
+        {
+            let ones = (0..len).step_by(step).collect::<Vec<_>>();
+            let mut bools = vec![false; len];
+            for &i in ones.iter() {
+                bools[i] = true;
+            }
+
+            let bits = BitVec::from_positions(len, &ones);
+            bits.debug_assert_tree();
+            assert_eq!(bits.len(), len);
+            assert_eq!(bits.num_ones() as usize, ones.len());
+            assert!(bits.iter().eq(BitVec::from_iter(bools).iter()));
+            for (rank, &i) in ones.iter().enumerate() {
+                assert_eq!(bits.select1(rank), i);
+                assert_eq!(bits.rank1(i), rank);
+            }
+        }
+
+        // Repeated and unsorted positions are fine
+        let bits = BitVec::from_positions(10, &[7, 2, 7, 0]);
+        assert_eq!(
+            bits.iter().collect::<Vec<_>>(),
+            (0..10).map(|i| [0, 2, 7].contains(&i)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_bitvec_remove() {
+        let mut expected = (0..20000).map(|i| i % 3 == 0).collect::<Vec<_>>();
+        let mut bits = BitVec::from_bool_slice(&expected);
+        for i in 0..19000 {
+            let index = (i * 7919) % expected.len();
+            assert_eq!(bits.remove(index), expected.remove(index));
+        }
+        assert_eq!(bits.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(
+            bits.num_ones() as usize,
+            expected.iter().filter(|&&b| b).count()
+        );
+
+        while !expected.is_empty() {
+            assert_eq!(bits.remove(0), expected.remove(0));
+        }
+        assert_eq!(bits.len(), 0);
+        assert!(bits.root.ptrs[0].is_null());
+
+        bits.insert(0, true);
+        assert_eq!(bits.iter().collect::<Vec<_>>(), vec![true]);
+    }
+
     #[test]
     fn test_node_split_1() {
         let mut node = Node {
@@ -702,11 +1537,12 @@ mod test {
 
         let ptrs = (0..CAPACITY)
             .map(|_| {
-                PackedPtr::from_leaf(Box::new(Bits512 {
+                let leaf = Bits512 {
                     n_ones: u9x7::new([64, 128, 192, 256, 320, 384, 448]),
                     len: 512,
                     bits: [u64::max_value(); 8],
-                }))
+                };
+                PackedPtr::leaf_in(leaf, &Global)
             })
             .collect::<Vec<_>>();
         for i in 0..CAPACITY {
@@ -715,7 +1551,7 @@ mod test {
             node.n_ones[i] = 512 + i as u32 * 512;
         }
 
-        let new = node.split();
+        let mut new = node.split();
         let expected = [
             512, 1024, 1536, 2048, 2560, 3072, 3584, 4096, 4096, 4096, 4096,
             4096, 4096, 4096, 4096, 4096,
@@ -742,6 +1578,37 @@ mod test {
                 null,
             ]
         );
+
+        // Nodes only free their children through a `BitVec`
+        node.release_children(&mut Vec::new(), &Global);
+        new.release_children(&mut Vec::new(), &Global);
+    }
+
+    #[cfg(feature = "allocator_api")]
+    #[test]
+    fn test_bitvec_new_in() {
+        // An arena allocator (e.g. `&bumpalo::Bump`) works the same way, and
+        // avoids a separate heap allocation for every leaf
+        let mut bits = BitVec::new_in(std::alloc::Global);
+        let mut expected = Vec::new();
+        for i in 0..20_000 {
+            let index = (i * 7919) % (expected.len() + 1);
+            bits.insert(index, i % 3 == 0);
+            expected.insert(index, i % 3 == 0);
+        }
+        for i in 0..10_000 {
+            let index = (i * 7919) % expected.len();
+            assert_eq!(bits.remove(index), expected.remove(index));
+        }
+        assert_eq!(bits.iter().collect::<Vec<_>>(), expected);
+
+        let both = bits.and(&BitVec::from_bool_slice(&expected));
+        assert_eq!(both.iter().collect::<Vec<_>>(), expected);
+        bits.compact();
+        assert_eq!(
+            bits.total_size(),
+            BitVec::from_bool_slice(&expected).total_size()
+        );
     }
 
     #[test]
@@ -7,7 +7,7 @@ mod u64;
 pub use bits256::Bits256;
 pub use bits512::Bits512;
 pub use bitvec::BitVec;
-pub use sbitvec::SBitVec;
+pub use sbitvec::{SBitVec, SBitVecError};
 
 pub trait SelectRank {
     /// Return the ith bit
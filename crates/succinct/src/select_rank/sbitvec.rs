@@ -1,3 +1,4 @@
+use std::fmt;
 use std::iter::FromIterator;
 
 use bit_parallel::{u16x32, u9x7::u9x7};
@@ -15,7 +16,180 @@ pub struct SBitVec {
     index3: Vec<u32>,
 }
 
+/// The first inconsistency found by `SBitVec::validate`
+#[derive(Debug, Eq, PartialEq)]
+pub enum SBitVecError {
+    /// An index has the wrong number of entries for `len`
+    Length {
+        index: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    /// The last block has bits set past `len`
+    Padding,
+    /// A cumulative count in `index1` disagrees with `blocks`
+    Index1 {
+        entry: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// A cumulative count in `index2` disagrees with `blocks`
+    Index2 {
+        entry: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// A cumulative count in `index3` disagrees with `blocks`
+    Index3 {
+        entry: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for SBitVecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SBitVecError::Length {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} has {} entries, but expected {}",
+                index, actual, expected
+            ),
+            SBitVecError::Padding => {
+                write!(f, "bits are set past the end of the bit-vector")
+            }
+            SBitVecError::Index1 {
+                entry,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "index1 entry {} is {}, but expected {}",
+                entry, actual, expected
+            ),
+            SBitVecError::Index2 {
+                entry,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "index2 entry {} is {}, but expected {}",
+                entry, actual, expected
+            ),
+            SBitVecError::Index3 {
+                entry,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "index3 entry {} is {}, but expected {}",
+                entry, actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SBitVecError {}
+
+impl Default for SBitVec {
+    fn default() -> Self {
+        SBitVec::new()
+    }
+}
+
 impl SBitVec {
+    /// Create an empty bit-vector
+    pub fn new() -> SBitVec {
+        SBitVec::from_iter(std::iter::empty())
+    }
+
+    /// Check that the rank indices agree with the stored bits
+    ///
+    /// `rank` and `select` trust the indices, so this should be run on any
+    /// `SBitVec` that was not built by `from_iter` (e.g. one read from disk).
+    pub fn validate(&self) -> Result<(), SBitVecError> {
+        fn check_len(
+            index: &'static str,
+            expected: usize,
+            actual: usize,
+        ) -> Result<(), SBitVecError> {
+            if expected == actual {
+                Ok(())
+            } else {
+                Err(SBitVecError::Length {
+                    index,
+                    expected,
+                    actual,
+                })
+            }
+        }
+
+        check_len("blocks", self.len.div_ceil(64), self.blocks.len())?;
+        check_len("index1", self.blocks.len().div_ceil(8), self.index1.len())?;
+        check_len("index2", self.index1.len().div_ceil(33), self.index2.len())?;
+        check_len("index3", self.index2.len(), self.index3.len())?;
+
+        let tail = self.len % 64;
+        if tail > 0 {
+            let last = self.blocks[self.blocks.len() - 1];
+            if last >> tail != 0 {
+                return Err(SBitVecError::Padding);
+            }
+        }
+
+        let mut total = 0;
+        for (i2, index2) in self.index2.iter().enumerate() {
+            let mut superblock = 0;
+            for j2 in 0..33 {
+                let i1 = i2 * 33 + j2;
+                if j2 > 0 {
+                    let actual = index2[j2 - 1] as usize;
+                    if actual != superblock {
+                        return Err(SBitVecError::Index2 {
+                            entry: i2 * 32 + j2 - 1,
+                            expected: superblock,
+                            actual,
+                        });
+                    }
+                }
+
+                let mut block = 0;
+                for j1 in 0..8 {
+                    if j1 > 0 && i1 < self.index1.len() {
+                        let actual = self.index1[i1].get(j1 - 1) as usize;
+                        if actual != block {
+                            return Err(SBitVecError::Index1 {
+                                entry: i1 * 7 + j1 - 1,
+                                expected: block,
+                                actual,
+                            });
+                        }
+                    }
+                    if let Some(word) = self.blocks.get(i1 * 8 + j1) {
+                        block += word.count_ones() as usize;
+                    }
+                }
+                superblock += block;
+            }
+
+            total += superblock;
+            let actual = self.index3[i2] as usize;
+            if actual != total {
+                return Err(SBitVecError::Index3 {
+                    entry: i2,
+                    expected: total,
+                    actual,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn total_size(&self) -> usize {
         std::mem::size_of::<Self>()
             + 8 * self.blocks.len()
@@ -32,13 +206,71 @@ impl SBitVec {
         self.len
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+    pub fn num_ones(&self) -> usize {
+        self.index3.last().map_or(0, |&n| n as usize)
+    }
+
+    /// The packed bits, 64 to a word with bit `i` at `1 << (i % 64)`
+    pub fn words(&self) -> &[u64] {
+        &self.blocks
+    }
+
+    /// Return the number of 1s before the `block`th 64-bit word
+    pub fn block_rank(&self, block: usize) -> usize {
+        let (i1, rem1) = (block / 8, block % 8);
+        let (i2, rem2) = (i1 / 33, i1 % 33);
+
+        let a = if i2 == 0 { 0 } else { self.index3[i2 - 1] };
+        let b = if rem2 == 0 {
+            0
+        } else {
+            self.index2[i2][rem2 - 1]
+        };
+        let c = if rem1 == 0 {
+            0
+        } else {
+            self.index1[i1].get(rem1 - 1)
+        };
+
+        (a as usize) + (b as usize) + (c as usize)
+    }
+
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = bool> + ExactSizeIterator + '_ {
         (0..self.len()).map(move |i| {
             let block_index = i / 64;
             let bit_index = i % 64;
             self.blocks[block_index] & (1 << bit_index) != 0
         })
     }
+
+    /// Like `get_bit`, but return `None` if `index` is out of bounds
+    pub fn try_get_bit(&self, index: usize) -> Option<bool> {
+        if index < self.len() {
+            Some(self.get_bit(index))
+        } else {
+            None
+        }
+    }
+
+    /// Like `rank1`, but return `None` if `index` is out of bounds
+    pub fn try_rank1(&self, index: usize) -> Option<usize> {
+        if index < self.len() {
+            Some(self.rank1(index))
+        } else {
+            None
+        }
+    }
+
+    /// Like `select1`, but return `None` if there are not `index + 1` 1s
+    pub fn try_select1(&self, index: usize) -> Option<usize> {
+        if index < self.num_ones() {
+            Some(self.select1(index))
+        } else {
+            None
+        }
+    }
 }
 
 impl SelectRank for SBitVec {
@@ -55,25 +287,7 @@ impl SelectRank for SBitVec {
     fn rank1(&self, index: usize) -> usize {
         assert!(index < self.len);
         let block_rank = index / 64;
-        let (i1, rem1) = (block_rank / 8, block_rank % 8);
-        let (i2, rem2) = (i1 / 33, i1 % 33);
-
-        let a = if i2 == 0 { 0 } else { self.index3[i2 - 1] };
-        let b = if rem2 == 0 {
-            0
-        } else {
-            self.index2[i2][rem2 - 1]
-        };
-        let c = if rem1 == 0 {
-            0
-        } else {
-            self.index1[i1].get(rem1 - 1)
-        };
-
-        (a as usize)
-            + (b as usize)
-            + (c as usize)
-            + self.blocks[block_rank].rank1(index % 64)
+        self.block_rank(block_rank) + self.blocks[block_rank].rank1(index % 64)
     }
 
     /// Return the position of the `i`th 1 (0-indexed)
@@ -184,7 +398,7 @@ impl FromIterator<bool> for SBitVec {
         let mut index3 = 0;
 
         for (i, bit) in iter.enumerate() {
-            sbitvec.len = i;
+            sbitvec.len = i + 1;
             if i > 0 {
                 if i % 64 == 0 {
                     sbitvec.blocks.push(block);
@@ -221,7 +435,9 @@ impl FromIterator<bool> for SBitVec {
                 block |= 1 << (i % 64);
             }
         }
-        sbitvec.len += 1;
+        if sbitvec.len == 0 {
+            return sbitvec;
+        }
 
         // Handle stragglers:
         let i = sbitvec.len - 1;
@@ -292,6 +508,38 @@ mod test {
         assert_eq!(bits.index3, vec![3]);
     }
 
+    #[test]
+    fn test_sbitvec_from_iter_empty() {
+        let bits = SBitVec::new();
+        assert_eq!(bits.len(), 0);
+        assert_eq!(bits.num_ones(), 0);
+        assert!(bits.blocks.is_empty());
+        assert_eq!(bits.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_sbitvec_iter_rev() {
+        let bits = (0..1000).map(|i| i % 7 == 0 || i % 11 == 0);
+        let sbitvec = SBitVec::from_iter(bits.clone());
+
+        let forward = sbitvec.iter().collect::<Vec<_>>();
+        let mut backward = sbitvec.iter().rev().collect::<Vec<_>>();
+        backward.reverse();
+        assert_eq!(forward, bits.collect::<Vec<_>>());
+        assert_eq!(forward, backward);
+
+        let mut iter = sbitvec.iter();
+        for remaining in (0..=1000).rev() {
+            assert_eq!(iter.len(), remaining);
+            if remaining % 2 == 0 {
+                iter.next();
+            } else {
+                iter.next_back();
+            }
+        }
+        assert_eq!(SBitVec::new().iter().len(), 0);
+    }
+
     #[test]
     fn test_sbitvec_from_iter_block_size() {
         let items = vec![true; 64];
@@ -394,6 +642,74 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_sbitvec_block_rank() {
+        let items = (0..40000).map(|i| i * i % 7 < 3).collect::<Vec<_>>();
+        let bits = SBitVec::from_iter(items.iter().cloned());
+
+        assert_eq!(bits.words().len(), 625);
+        for (b, word) in bits.words().iter().enumerate() {
+            assert_eq!(bits.block_rank(b), bits.rank1(b * 64));
+            for i in 0..64 {
+                assert_eq!(word & (1 << i) != 0, items[b * 64 + i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sbitvec_validate() {
+        let items = (0..40001).map(|i| i * i % 7 < 3).collect::<Vec<_>>();
+        let mut bits = SBitVec::from_iter(items.iter().cloned());
+        assert_eq!(bits.validate(), Ok(()));
+        assert_eq!(SBitVec::from_iter(vec![true; 64]).validate(), Ok(()));
+
+        let expected = items[..512 * 33].iter().filter(|&&b| b).count();
+        bits.index3[0] += 1;
+        let err = bits.validate().unwrap_err();
+        assert_eq!(
+            err,
+            SBitVecError::Index3 {
+                entry: 0,
+                expected,
+                actual: expected + 1,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "index3 entry 0 is {}, but expected {}",
+                expected + 1,
+                expected
+            )
+        );
+        bits.index3[0] -= 1;
+
+        bits.index2[1][3] -= 1;
+        assert!(matches!(
+            bits.validate(),
+            Err(SBitVecError::Index2 { entry: 35, .. })
+        ));
+        bits.index2[1][3] += 1;
+
+        bits.blocks[625] |= 1 << 63;
+        assert_eq!(bits.validate(), Err(SBitVecError::Padding));
+    }
+
+    #[test]
+    fn test_sbitvec_checked() {
+        let items = (0..20000).map(|i| i % 5 == 0).collect::<Vec<_>>();
+        let bits = SBitVec::from_iter(items.iter().cloned());
+        assert_eq!(bits.num_ones(), 4000);
+
+        assert_eq!(bits.try_get_bit(19995), Some(true));
+        assert_eq!(bits.try_get_bit(19999), Some(false));
+        assert_eq!(bits.try_get_bit(20000), None);
+        assert_eq!(bits.try_rank1(19999), Some(4000));
+        assert_eq!(bits.try_rank1(20000), None);
+        assert_eq!(bits.try_select1(3999), Some(19995));
+        assert_eq!(bits.try_select1(4000), None);
+    }
+
     #[test]
     fn test_sbitvec_boundary_construction() {
         assert_eq!(SBitVec::from_iter(vec![false; 64]).blocks, vec![0]);
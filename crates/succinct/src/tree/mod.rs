@@ -4,32 +4,70 @@ use std::ptr::NonNull;
 
 use bit_parallel::u32x16;
 
+use crate::alloc::{self, Allocator, Global};
+
 pub(crate) use ptr::{PackedPtr, Ptr, PtrMut};
 
 const CAPACITY: usize = 16;
 
-pub(crate) struct Tree<L: Leaf> {
-    root: Box<Node<L>>,
+/// A B-tree of leaves, where every node below the (inline) root is
+/// allocated in `A`
+pub(crate) struct Tree<L: Leaf, A: Allocator = Global> {
+    root: Node<L>,
+    config: L::Config,
+    alloc: A,
 }
 
 impl<L: Leaf> Tree<L> {
-    pub(crate) fn new() -> Tree<L> {
+    pub(crate) fn new() -> Tree<L>
+    where
+        L::Config: Default,
+    {
+        Tree::new_in(Global)
+    }
+
+    /// Create an empty tree whose leaves are created with `config`
+    pub(crate) fn with_config(config: L::Config) -> Tree<L> {
+        Tree::with_config_in(config, Global)
+    }
+}
+
+impl<L: Leaf, A: Allocator> Tree<L, A> {
+    /// Create an empty tree whose nodes are allocated in `alloc`
+    pub(crate) fn new_in(alloc: A) -> Tree<L, A>
+    where
+        L::Config: Default,
+    {
+        Tree::with_config_in(L::Config::default(), alloc)
+    }
+
+    /// Like `with_config`, but allocating the nodes in `alloc`
+    pub(crate) fn with_config_in(config: L::Config, alloc: A) -> Tree<L, A> {
         Tree {
-            root: Box::new(Node {
+            root: Node {
                 lens: [0; CAPACITY],
                 ptrs: [PackedPtr::null(); CAPACITY],
-            }),
+            },
+            config,
+            alloc,
         }
     }
 
+    pub(crate) fn config(&self) -> L::Config {
+        self.config
+    }
+
     pub(crate) fn total_size(&self) -> usize {
-        std::mem::size_of::<Self>() + self.root.total_size()
+        // The root is counted by `size_of::<Self>()`
+        std::mem::size_of::<Self>() - std::mem::size_of::<Node<L>>()
+            + self.root.total_size()
     }
 
     pub(crate) fn len(&self) -> usize {
         self.root.lens[CAPACITY - 1] as usize
     }
 
+    #[cfg(test)]
     pub(crate) fn iter_leaf(&self) -> impl Iterator<Item = &L> + '_ {
         let mut current: &Node<L> = &self.root;
         let mut stack = Vec::new();
@@ -120,7 +158,8 @@ impl<L: Leaf> Tree<L> {
     pub(crate) fn insert(&mut self, index: usize, value: L::Output) {
         debug_assert!(index <= self.len());
         if index == 0 && self.len() == 0 {
-            self.root.ptrs[0] = PackedPtr::from_leaf(Box::new(L::new(value)));
+            let leaf = L::new(self.config, value);
+            self.root.ptrs[0] = PackedPtr::leaf_in(leaf, &self.alloc);
             self.root.lens = [1; CAPACITY];
             return;
         }
@@ -144,14 +183,15 @@ impl<L: Leaf> Tree<L> {
                 }
                 PtrMut::Leaf(leaf) => {
                     if leaf.is_full() {
-                        let mut new = leaf.split();
+                        let new = leaf.split(&self.alloc);
                         if index as usize > L::CAPACITY / 2 {
-                            new.insert(index as usize - L::CAPACITY / 2, value);
+                            let index = index as usize - L::CAPACITY / 2;
+                            unsafe { &mut *new }.insert(index, value);
                         } else {
                             leaf.insert(index as usize, value);
                         }
                         stack.push((NonNull::from(node), rank));
-                        self.split(stack, new);
+                        self.split(stack, PackedPtr::from_leaf(new));
                     } else {
                         leaf.insert(index as usize, value);
                     }
@@ -161,9 +201,13 @@ impl<L: Leaf> Tree<L> {
         }
     }
 
-    fn split(&mut self, stack: Vec<(NonNull<Node<L>>, usize)>, new: Box<L>) {
-        let mut ptr = PackedPtr::from_leaf(new);
-
+    /// Link `ptr` (a new leaf) in after the last node of `stack`, splitting
+    /// full ancestors
+    fn split(
+        &mut self,
+        stack: Vec<(NonNull<Node<L>>, usize)>,
+        mut ptr: PackedPtr<Node<L>, L>,
+    ) {
         for (node, rank) in stack.iter().rev().cloned() {
             let node = unsafe { &mut *node.as_ptr() };
             if !node.is_full() {
@@ -172,7 +216,7 @@ impl<L: Leaf> Tree<L> {
                 node.lens[rank] -= ptr.len() as u32;
                 return;
             } else {
-                let mut new = Box::new(node.split());
+                let mut new = node.split();
 
                 if rank >= 8 {
                     new.shift_right(rank - 8);
@@ -183,7 +227,7 @@ impl<L: Leaf> Tree<L> {
                     node.ptrs[rank + 1] = ptr;
                     node.lens[rank] -= ptr.len() as u32;
                 }
-                ptr = PackedPtr::from_inner(new);
+                ptr = PackedPtr::inner_in(new, &self.alloc);
             }
         }
 
@@ -194,40 +238,93 @@ impl<L: Leaf> Tree<L> {
         let len = self.root.lens[CAPACITY - 1] + ptr.len() as u32;
         let root = std::mem::replace(
             &mut self.root,
-            Box::new(Node {
+            Node {
                 lens: [len as u32; CAPACITY],
                 ptrs: [PackedPtr::null(); CAPACITY],
-            }),
+            },
         );
         self.root.lens[0] -= ptr.len() as u32;
-        self.root.ptrs[0] = PackedPtr::from_inner(root);
+        self.root.ptrs[0] = PackedPtr::inner_in(root, &self.alloc);
         self.root.ptrs[1] = ptr;
     }
 }
 
+impl<L: RemoveLeaf, A: Allocator> Tree<L, A> {
+    /// Remove the value at `index`, returning it
+    ///
+    /// Children left empty are freed, but nodes are never merged.
+    pub(crate) fn remove(&mut self, index: usize) -> L::Output {
+        debug_assert!(index < self.len());
+        let mut stack: Vec<(NonNull<Node<L>>, usize)> = Vec::new();
+        let mut node: &mut Node<L> = &mut self.root;
+        let mut index = index as u32;
+        let value = loop {
+            let rank = u32x16::rank(&node.lens, 1 + index) as usize;
+            if rank > 0 {
+                index -= node.lens[rank - 1];
+            }
+            u32x16::decrement(&mut node.lens, rank);
+
+            let n = &mut node.ptrs[rank] as *mut PackedPtr<Node<L>, L>;
+            stack.push((NonNull::from(node), rank));
+            match unsafe { &mut *n }.expand_mut() {
+                PtrMut::None => unreachable!(),
+                PtrMut::Inner(inner) => {
+                    node = inner;
+                }
+                PtrMut::Leaf(leaf) => break leaf.remove(index as usize),
+            }
+        };
+
+        for (node, rank) in stack.into_iter().rev() {
+            let node = unsafe { &mut *node.as_ptr() };
+            if node.ptrs[rank].len() > 0 {
+                break;
+            }
+            node.remove_child(rank, &self.alloc);
+        }
+        value
+    }
+}
+
 struct Node<L: Leaf> {
     lens: [u32; CAPACITY],
     ptrs: [PackedPtr<Node<L>, L>; CAPACITY],
 }
 
-impl<L: Leaf> Drop for Node<L> {
+impl<L: Leaf, A: Allocator> Drop for Tree<L, A> {
     fn drop(&mut self) {
-        // TODO: avoid recursive drop
+        // Free the inner nodes from an explicit stack rather than recursing
+        let mut stack = Vec::new();
+        self.root.release_children(&mut stack, &self.alloc);
+        while let Some(mut node) = stack.pop() {
+            node.release_children(&mut stack, &self.alloc);
+        }
+    }
+}
+
+impl<L: Leaf> Node<L> {
+    /// Free the leaves and move the inner children onto `stack`, leaving
+    /// null pointers behind
+    fn release_children<A: Allocator>(
+        &mut self,
+        stack: &mut Vec<Node<L>>,
+        alloc: &A,
+    ) {
         for ptr in self.ptrs.iter_mut() {
             match ptr.expand_mut() {
-                PtrMut::None => {}
+                PtrMut::None => continue,
                 PtrMut::Leaf(leaf) => unsafe {
-                    drop(Box::from_raw(leaf as *mut _));
+                    alloc::from_raw_in(leaf as *mut L, alloc);
                 },
                 PtrMut::Inner(inner) => unsafe {
-                    drop(Box::from_raw(inner as *mut _));
+                    stack.push(alloc::from_raw_in(inner as *mut _, alloc));
                 },
             }
+            *ptr = PackedPtr::null();
         }
     }
-}
 
-impl<L: Leaf> Node<L> {
     fn total_size(&self) -> usize {
         let mut size = std::mem::size_of::<Self>();
         for ptr in self.ptrs.iter() {
@@ -259,6 +356,24 @@ impl<L: Leaf> Node<L> {
         node
     }
 
+    /// Free the (empty) child at `rank`, shifting the later children down
+    fn remove_child<A: Allocator>(&mut self, rank: usize, alloc: &A) {
+        match self.ptrs[rank].expand_mut() {
+            PtrMut::None => unreachable!(),
+            PtrMut::Leaf(leaf) => unsafe {
+                alloc::from_raw_in(leaf as *mut L, alloc);
+            },
+            PtrMut::Inner(inner) => unsafe {
+                alloc::from_raw_in(inner as *mut Node<L>, alloc);
+            },
+        }
+        for i in rank..CAPACITY - 1 {
+            self.ptrs[i] = self.ptrs[i + 1];
+            self.lens[i] = self.lens[i + 1];
+        }
+        self.ptrs[CAPACITY - 1] = PackedPtr::null();
+    }
+
     fn shift_right(&mut self, rank: usize) {
         debug_assert!(rank < CAPACITY - 1);
         debug_assert!(self.ptrs[CAPACITY - 1].is_null());
@@ -290,6 +405,8 @@ impl<L: Leaf> PackedPtr<Node<L>, L> {
 
 pub(crate) trait Leaf {
     type Output;
+    /// Shared by every leaf of a tree, and passed to `new`
+    type Config: Copy;
     const CAPACITY: usize;
 
     fn total_size(&self) -> usize;
@@ -299,7 +416,17 @@ pub(crate) trait Leaf {
         self.len() == Self::CAPACITY
     }
 
-    fn new(value: Self::Output) -> Self;
-    fn split(&mut self) -> Box<Self>;
+    fn new(config: Self::Config, value: Self::Output) -> Self;
+    /// Move the upper half of this (full) leaf into a new leaf allocated in
+    /// `alloc` by `alloc::into_raw_in`
+    fn split<A: Allocator>(&mut self, alloc: &A) -> *mut Self;
     fn insert(&mut self, index: usize, value: Self::Output);
 }
+
+/// A leaf that values can be removed from
+///
+/// This is separate from `Leaf` since emptied leaves are freed, which
+/// would break the links between `ByteLeaf`s.
+pub(crate) trait RemoveLeaf: Leaf {
+    fn remove(&mut self, index: usize) -> Self::Output;
+}
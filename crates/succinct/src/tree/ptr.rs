@@ -1,6 +1,8 @@
 use std::fmt;
 use std::marker::PhantomData;
 
+use crate::alloc::{self, Allocator};
+
 pub(crate) struct PackedPtr<I, L> {
     value: usize,
     inner: PhantomData<I>,
@@ -52,8 +54,9 @@ impl<I, L> PackedPtr<I, L> {
         }
     }
 
-    pub(crate) fn from_inner(node: Box<I>) -> PackedPtr<I, L> {
-        let value = Box::into_raw(node) as usize;
+    /// Point to an inner node allocated by `alloc::into_raw_in`
+    pub(crate) fn from_inner(node: *mut I) -> PackedPtr<I, L> {
+        let value = node as usize;
         debug_assert_eq!(value & 0b1, 0);
         PackedPtr {
             value: value | 1,
@@ -62,8 +65,9 @@ impl<I, L> PackedPtr<I, L> {
         }
     }
 
-    pub(crate) fn from_leaf(node: Box<L>) -> PackedPtr<I, L> {
-        let value = Box::into_raw(node) as usize;
+    /// Point to a leaf allocated by `alloc::into_raw_in`
+    pub(crate) fn from_leaf(node: *mut L) -> PackedPtr<I, L> {
+        let value = node as usize;
         debug_assert_eq!(value & 0b1, 0);
         PackedPtr {
             value,
@@ -71,6 +75,19 @@ impl<I, L> PackedPtr<I, L> {
             inner: PhantomData,
         }
     }
+
+    /// Move `node` into `alloc`, pointing to it as an inner node
+    pub(crate) fn inner_in<A: Allocator>(
+        node: I,
+        alloc: &A,
+    ) -> PackedPtr<I, L> {
+        PackedPtr::from_inner(alloc::into_raw_in(node, alloc))
+    }
+
+    /// Move `node` into `alloc`, pointing to it as a leaf
+    pub(crate) fn leaf_in<A: Allocator>(node: L, alloc: &A) -> PackedPtr<I, L> {
+        PackedPtr::from_leaf(alloc::into_raw_in(node, alloc))
+    }
 }
 
 impl<I, L> Default for PackedPtr<I, L> {
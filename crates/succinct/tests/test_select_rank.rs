@@ -1,5 +1,28 @@
 use proptest::prelude::*;
-use succinct::select_rank::{BitVec, SelectRank};
+use proptest::test_runner::TestCaseError;
+use succinct::select_rank::{BitVec, SBitVec, SelectRank};
+
+/// Check that `other` holds the same bits as `bits` and agrees with it on
+/// every rank and select query
+fn audit_against<S: SelectRank>(
+    bits: &BitVec,
+    other: &S,
+) -> Result<(), TestCaseError> {
+    for i in 0..bits.len() {
+        prop_assert_eq!(other.get_bit(i), bits.get_bit(i), "bit {}", i);
+        prop_assert_eq!(other.rank0(i), bits.rank0(i), "rank0({})", i);
+        prop_assert_eq!(other.rank1(i), bits.rank1(i), "rank1({})", i);
+    }
+
+    let num_ones = bits.num_ones() as usize;
+    for i in 0..bits.len() - num_ones {
+        prop_assert_eq!(other.select0(i), bits.select0(i), "select0({})", i);
+    }
+    for i in 0..num_ones {
+        prop_assert_eq!(other.select1(i), bits.select1(i), "select1({})", i);
+    }
+    Ok(())
+}
 
 proptest! {
     #[test]
@@ -37,4 +60,93 @@ proptest! {
             prop_assert_eq!(expected[bits.select1(i)], true);
         }
     }
+
+    #[test]
+    #[ignore]
+    fn proptest_bitvec_and_or_xor(
+        a in prop::collection::vec(any::<(bool, usize)>(), 0..5_000),
+        b in prop::collection::vec(any::<(bool, usize)>(), 0..5_000),
+    ) {
+        let build = |input: &[(bool, usize)]| {
+            let mut expected = Vec::with_capacity(input.len());
+            let mut bits = BitVec::new();
+            for (bit, order) in input.iter().cloned() {
+                let order = order % (expected.len() + 1);
+                bits.insert(order, bit);
+                expected.insert(order, bit);
+            }
+            (bits, expected)
+        };
+        let (a, mut expected_a) = build(&a);
+        let (b, mut expected_b) = build(&b);
+        let len = expected_a.len().max(expected_b.len());
+        expected_a.resize(len, false);
+        expected_b.resize(len, false);
+
+        let zip = |f: fn(bool, bool) -> bool| {
+            expected_a
+                .iter()
+                .zip(expected_b.iter())
+                .map(|(&x, &y)| f(x, y))
+                .collect::<Vec<_>>()
+        };
+        prop_assert_eq!(a.and(&b).iter().collect::<Vec<_>>(), zip(|x, y| x & y));
+        prop_assert_eq!(a.or(&b).iter().collect::<Vec<_>>(), zip(|x, y| x | y));
+        prop_assert_eq!(a.xor(&b).iter().collect::<Vec<_>>(), zip(|x, y| x ^ y));
+    }
+
+    #[test]
+    #[ignore]
+    fn proptest_bitvec_insert_remove_set(
+        initial in prop::collection::vec(any::<bool>(), 0..20_000),
+        input in prop::collection::vec(any::<(u8, bool, usize)>(), 1..50_000),
+    ) {
+        // Start from a multi-level tree so removals have nodes to shrink
+        let mut expected = initial;
+        let mut bits = BitVec::from_bool_slice(&expected);
+
+        for (op, bit, order) in input.iter().cloned() {
+            match op % 3 {
+                1 if !expected.is_empty() => {
+                    let order = order % expected.len();
+                    prop_assert_eq!(bits.remove(order), expected.remove(order));
+                }
+                2 if !expected.is_empty() => {
+                    let order = order % expected.len();
+                    bits.set_bit(order, bit);
+                    expected[order] = bit;
+                }
+                _ => {
+                    let order = order % (expected.len() + 1);
+                    bits.insert(order, bit);
+                    expected.insert(order, bit);
+                }
+            }
+        }
+
+        prop_assert_eq!(bits.len(), expected.len());
+        prop_assert_eq!(
+            bits.num_ones() as usize,
+            expected.iter().filter(|&&bit| bit).count()
+        );
+        prop_assert_eq!(bits.iter().collect::<Vec<_>>(), expected.clone());
+        for (i, bit) in expected.iter().cloned().enumerate() {
+            prop_assert_eq!(bits.get_bit(i), bit);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn proptest_bitvec_sbitvec_audit(
+        input in prop::collection::vec(0..100u8, 0..50_000),
+        density in 0..=100u8,
+    ) {
+        // Vary the density so both sparse and dense select paths are hit
+        let expected = input.iter().map(|&x| x < density).collect::<Vec<_>>();
+        let bits = BitVec::from_bool_slice(&expected);
+        let sbits = expected.iter().cloned().collect::<SBitVec>();
+        prop_assert_eq!(sbits.len(), bits.len());
+        prop_assert_eq!(sbits.num_ones(), bits.num_ones() as usize);
+        audit_against(&bits, &sbits)?;
+    }
 }
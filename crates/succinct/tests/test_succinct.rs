@@ -1,7 +1,8 @@
 use std::iter::FromIterator;
 
 use proptest::prelude::*;
-use succinct::louds::{LoudsTrie, SLoudsTrie};
+use succinct::louds::{LoudsTrie, RadixLoudsTrie, SLoudsTrie};
+use succinct::select_rank::{BitVec, SBitVec};
 
 proptest! {
     #[test]
@@ -64,6 +65,49 @@ proptest! {
         }
     }
 
+    #[test]
+    #[ignore]
+    fn proptest_radixloudstrie_insert(
+        inputs in prop::collection::vec(any::<Vec<u8>>(), 1..1000),
+        keys: Vec<Vec<u8>>,
+    ) {
+        let mut louds = LoudsTrie::new();
+        let mut radix = RadixLoudsTrie::new();
+        for (i, input) in inputs.iter().enumerate() {
+            prop_assert_eq!(radix.insert(input, i), louds.insert(input, i));
+        }
+        prop_assert_eq!(radix.len(), louds.len());
+
+        for key in inputs.iter().chain(keys.iter()) {
+            prop_assert_eq!(radix.get(key), louds.get(key));
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn proptest_radixloudstrie_variable_length(
+        inputs in prop::collection::hash_set(any::<Vec<u8>>(), 1..1000),
+        keys: Vec<Vec<u8>>,
+    ) {
+        let radix = RadixLoudsTrie::from_iter(
+            inputs.iter().map(|i| (i as &[u8], i.len()))
+        );
+
+        for input in inputs.iter() {
+            let len = input.len();
+            prop_assert_eq!(radix.get(input), Some(&len));
+        }
+
+        for key in keys.iter() {
+            if inputs.contains(key) {
+                let len = key.len();
+                prop_assert_eq!(radix.get(key), Some(&len));
+            } else {
+                prop_assert_eq!(radix.get(key), None);
+            }
+        }
+    }
+
     #[test]
     #[ignore]
     fn proptest_sloudstrie_fixed_len(
@@ -121,3 +165,28 @@ proptest! {
         }
     }
 }
+
+#[test]
+fn test_empty_containers() {
+    let bits = BitVec::new();
+    assert!(bits.is_empty());
+    assert_eq!(bits.len(), 0);
+
+    let sbits = SBitVec::new();
+    assert!(sbits.is_empty());
+    assert_eq!(sbits.len(), 0);
+
+    let louds = LoudsTrie::<u64>::new();
+    assert!(louds.is_empty());
+    assert_eq!(louds.len(), 0);
+
+    let slouds = SLoudsTrie::<u64>::new();
+    assert!(slouds.is_empty());
+    assert_eq!(slouds.len(), 0);
+    assert_eq!(slouds.get(b""), None);
+
+    // The root's value counts towards the length
+    let slouds = SLoudsTrie::from_iter(vec![(&b""[..], 1), (b"a", 2)]);
+    assert!(!slouds.is_empty());
+    assert_eq!(slouds.len(), 2);
+}